@@ -1,6 +1,34 @@
-use std::{env, path::PathBuf};
+use std::{
+  env,
+  path::{Path, PathBuf},
+};
+
+/// The vendored C sources, relative to `vendor/GNETextSearch/GNETextSearch`.
+const SOURCES: [&str; 4] = [
+  "Set/countedset.c",
+  "String/stringbuf.c",
+  "Tree/ternarytree.c",
+  "UTF-8/tokenize.c",
+];
+
+/// The vendored headers, relative to `vendor/GNETextSearch/GNETextSearch`.
+const HEADERS: [&str; 8] = [
+  "GNETextSearch.h",
+  "GNETextSearchPrivate.h",
+  "GNETextSearchPublic.h",
+  "Set/countedset.h",
+  "String/stringbuf.h",
+  "Tree/ternarytree.h",
+  "UTF-8/tokenize.h",
+  "UTF-8/utf8_utils.h",
+];
 
 fn main() {
+  // Once any `rerun-if-changed` line is printed, Cargo only reruns the build
+  // script when one of the listed files changes, instead of whenever any file
+  // in the package does.
+  println!("cargo:rerun-if-changed=build.rs");
+
   let project_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
     .canonicalize()
     .unwrap();
@@ -8,46 +36,165 @@ fn main() {
     .join("vendor")
     .join("GNETextSearch")
     .join("GNETextSearch");
+  // Cargo sets `TARGET` to the triple being compiled for, which differs from
+  // `HOST` when cross-compiling.
+  let target = env::var("TARGET").unwrap();
+  let host = env::var("HOST").unwrap();
 
-  build_gne_text_search(&src);
-  generate_bindings(&project_dir, &src);
+  if cfg!(feature = "system-lib") {
+    link_system_gne_text_search();
+  } else {
+    build_gne_text_search(&src, &target, &host);
+  }
+  if cfg!(feature = "bindgen") {
+    generate_bindings(&project_dir, &src, &target);
+  }
 }
 
-fn build_gne_text_search(src: &PathBuf) {
+/// Compiles the vendored sources for the target. When cross-compiling, cc
+/// looks for the target's C compiler, such as `aarch64-linux-musl-gcc`, and
+/// reads the `CC_<target>` and `CFLAGS_<target>` environment variables, so a
+/// different compiler or sysroot can be chosen with, for example,
+/// `CC_aarch64_unknown_linux_musl` and `CFLAGS_aarch64_unknown_linux_musl`.
+/// The sources need the C standard library's headers, so targets without a
+/// libc, like `wasm32-unknown-unknown`, need a sysroot that provides them.
+fn build_gne_text_search(src: &PathBuf, target: &str, host: &str) {
+  rerun_if_changed(src, &SOURCES);
+  rerun_if_changed(src, &HEADERS);
   cc::Build::new()
-    .file(src.join("Set/countedset.c"))
-    .file(src.join("String/stringbuf.c"))
-    .file(src.join("Tree/ternarytree.c"))
-    .file(src.join("UTF-8/tokenize.c"))
-    .include(&src)
+    .target(target)
+    .host(host)
+    .files(SOURCES.iter().map(|source| src.join(source)))
+    .include(src)
     .include(src.join("Set"))
     .include(src.join("String"))
     .compile("GNETextSearch");
 }
 
-fn generate_bindings(project_dir: &PathBuf, src: &PathBuf) {
+/// Tells Cargo to rerun the build script when any of the files, relative to
+/// `src`, change.
+fn rerun_if_changed(src: &Path, files: &[&str]) {
+  for file in files {
+    println!("cargo:rerun-if-changed={}", src.join(file).display());
+  }
+}
+
+#[cfg(feature = "system-lib")]
+fn link_system_gne_text_search() {
+  println!("cargo:rerun-if-env-changed=GNE_TEXT_SEARCH_LIB_DIR");
+  if let Ok(lib_dir) = env::var("GNE_TEXT_SEARCH_LIB_DIR") {
+    println!("cargo:rustc-link-search=native={}", lib_dir);
+    println!("cargo:rustc-link-lib=GNETextSearch");
+    return;
+  }
+
+  pkg_config::Config::new()
+    .probe("GNETextSearch")
+    .expect("Unable to find GNETextSearch with pkg-config");
+}
+
+#[cfg(not(feature = "system-lib"))]
+fn link_system_gne_text_search() {
+  unreachable!("the system-lib feature is disabled")
+}
+
+/// Regenerates the checked-in bindings in `src/bindings.rs` from
+/// GNETextSearch's headers. This requires libclang, so it only runs when the
+/// `bindgen` feature is enabled.
+///
+/// Clang parses the headers for the target, so that the sizes of types like
+/// `size_t` match the library being linked. bindgen adds the arguments in the
+/// `BINDGEN_EXTRA_CLANG_ARGS` and `BINDGEN_EXTRA_CLANG_ARGS_<target>`
+/// environment variables, such as `--sysroot=/path/to/sysroot`, to the ones
+/// passed here. A `--target` in them replaces the one derived from `TARGET`.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(project_dir: &PathBuf, src: &PathBuf, target: &str) {
+  rerun_if_changed(src, &HEADERS);
   let header = string_from_path(src, Some("GNETextSearch.h"));
   let include_root = format!("-I{}", string_from_path(src, None));
   let include_set = format!("-I{}", string_from_path(src, Some("Set")));
+  let include_string = format!("-I{}", string_from_path(src, Some("String")));
   let include_tree = format!("-I{}", string_from_path(src, Some("Tree")));
+  let include_utf8 = format!("-I{}", string_from_path(src, Some("UTF-8")));
+
+  let extra_clang_args = extra_clang_args(target);
+  let has_target = extra_clang_args
+    .iter()
+    .any(|arg| arg.starts_with("--target") || arg == "-target");
+  let mut target_args = Vec::new();
+  if !has_target {
+    target_args.push(format!("--target={}", clang_target(target)));
+  }
 
   let bindings = bindgen::Builder::default()
     .header(header)
+    .clang_args(target_args)
     .clang_arg(include_root)
     .clang_arg(include_set)
+    .clang_arg(include_string)
     .clang_arg(include_tree)
-    .raw_line(
-      "#![allow(non_upper_case_globals, non_snake_case, non_camel_case_types, improper_ctypes)]",
-    )
+    .clang_arg(include_utf8)
+    // Only generate GNETextSearch's own items, and the few from the C
+    // standard library that it needs, so the bindings don't depend on the
+    // platform they were generated on.
+    .allowlist_function("tsearch_.*|copy_code_points|copy_utf16_code_points")
+    .allowlist_function("free")
+    .allowlist_type("tsearch_.*|GNEInteger|process_token_func")
+    .allowlist_var("failure|success")
     .generate()
     .expect("Unable to generate bindings");
 
-  let lib_rs_path = project_dir.join("src/lib.rs");
+  let bindings_path = project_dir.join("src/bindings.rs");
   bindings
-    .write_to_file(lib_rs_path)
+    .write_to_file(bindings_path)
     .expect("Unable to write bindings");
 }
 
+#[cfg(not(feature = "bindgen"))]
+fn generate_bindings(_project_dir: &PathBuf, _src: &PathBuf, _target: &str) {
+  unreachable!("the bindgen feature is disabled")
+}
+
+/// Returns the whitespace-separated arguments in the environment variables
+/// that bindgen adds to clang's arguments.
+#[cfg(feature = "bindgen")]
+fn extra_clang_args(target: &str) -> Vec<String> {
+  let names = [
+    "BINDGEN_EXTRA_CLANG_ARGS".to_owned(),
+    format!("BINDGEN_EXTRA_CLANG_ARGS_{}", target),
+    format!("BINDGEN_EXTRA_CLANG_ARGS_{}", target.replace('-', "_")),
+  ];
+  let mut args = Vec::new();
+  for name in &names {
+    println!("cargo:rerun-if-env-changed={}", name);
+    if let Ok(value) = env::var(name) {
+      args.extend(value.split_whitespace().map(str::to_owned));
+    }
+  }
+  args
+}
+
+/// Converts a Rust target triple into the triple clang uses for the same
+/// target. Most triples are the same, but a few architectures are named
+/// differently.
+#[cfg(feature = "bindgen")]
+fn clang_target(target: &str) -> String {
+  let renames = [
+    ("aarch64-apple-", "arm64-apple-"),
+    ("riscv64gc-", "riscv64-"),
+    ("riscv32imac-", "riscv32-"),
+    ("riscv32imc-", "riscv32-"),
+    ("riscv32i-", "riscv32-"),
+  ];
+  for (rust_prefix, clang_prefix) in &renames {
+    if let Some(rest) = target.strip_prefix(rust_prefix) {
+      return format!("{}{}", clang_prefix, rest);
+    }
+  }
+  target.to_owned()
+}
+
+#[cfg(feature = "bindgen")]
 fn string_from_path(root: &PathBuf, subpath: Option<&str>) -> String {
   let path = if let Some(subpath) = subpath {
     root.join(subpath)
@@ -0,0 +1,647 @@
+/* automatically generated by rust-bindgen 0.69.5 */
+
+pub const failure: u32 = 0;
+pub const success: u32 = 1;
+extern "C" {
+  pub fn free(arg1: *mut ::std::os::raw::c_void);
+}
+pub type _Result = ::std::os::raw::c_int;
+pub type GNEInteger = i64;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct tsearch_countedset {
+  _unused: [u8; 0],
+}
+pub type tsearch_countedset_ptr = *mut tsearch_countedset;
+#[doc = " A node in a counted set's balanced binary tree. The nodes are stored in a single,"]
+#[doc = " contiguous buffer and refer to their children by index. The index of a missing"]
+#[doc = " child is SIZE_MAX. Nodes whose count is 0 belong to integers that have been"]
+#[doc = " removed from the counted set. No integer is reserved as a marker, so every GNEInteger"]
+#[doc = " can be added to a counted set. The reserved field is always 0."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct tsearch_countedset_node {
+  pub integer: GNEInteger,
+  pub count: usize,
+  pub balance: ::std::os::raw::c_int,
+  pub reserved: ::std::os::raw::c_int,
+  pub left: usize,
+  pub right: usize,
+}
+#[test]
+fn bindgen_test_layout_tsearch_countedset_node() {
+  const UNINIT: ::std::mem::MaybeUninit<tsearch_countedset_node> =
+    ::std::mem::MaybeUninit::uninit();
+  let ptr = UNINIT.as_ptr();
+  assert_eq!(
+    ::std::mem::size_of::<tsearch_countedset_node>(),
+    40usize,
+    concat!("Size of: ", stringify!(tsearch_countedset_node))
+  );
+  assert_eq!(
+    ::std::mem::align_of::<tsearch_countedset_node>(),
+    8usize,
+    concat!("Alignment of ", stringify!(tsearch_countedset_node))
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).integer) as usize - ptr as usize },
+    0usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_countedset_node),
+      "::",
+      stringify!(integer)
+    )
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).count) as usize - ptr as usize },
+    8usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_countedset_node),
+      "::",
+      stringify!(count)
+    )
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).balance) as usize - ptr as usize },
+    16usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_countedset_node),
+      "::",
+      stringify!(balance)
+    )
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).reserved) as usize - ptr as usize },
+    20usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_countedset_node),
+      "::",
+      stringify!(reserved)
+    )
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).left) as usize - ptr as usize },
+    24usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_countedset_node),
+      "::",
+      stringify!(left)
+    )
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).right) as usize - ptr as usize },
+    32usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_countedset_node),
+      "::",
+      stringify!(right)
+    )
+  );
+}
+extern "C" {
+  pub fn tsearch_countedset_init() -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Creates an empty counted set whose buffer can hold the specified number of integers"]
+  #[doc = " before it needs to grow. Returns NULL if the buffer could not be allocated."]
+  pub fn tsearch_countedset_init_with_capacity(
+    capacity: usize,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Creates a counted set containing a copy of the specified buffer of nodes, such as one"]
+  #[doc = " returned by tsearch_countedset_get_nodes(). The nodes must form a valid tree whose root"]
+  #[doc = " is the first node. Returns NULL if the buffer could not be allocated."]
+  pub fn tsearch_countedset_init_with_nodes(
+    nodes: *const tsearch_countedset_node,
+    count: usize,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  pub fn tsearch_countedset_copy(
+    ptr: tsearch_countedset_ptr,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Replaces the contents of the counted set with a copy of the source counted set's contents."]
+  #[doc = " The counted set's buffer is reused if it is large enough to hold the source's nodes,"]
+  #[doc = " otherwise a larger buffer is allocated. Returns 1 if successful, otherwise 0. If copying"]
+  #[doc = " fails, the counted set is unchanged."]
+  pub fn tsearch_countedset_copy_into(
+    ptr: tsearch_countedset_ptr,
+    sourcePtr: tsearch_countedset_ptr,
+  ) -> _Result;
+}
+extern "C" {
+  pub fn tsearch_countedset_free(ptr: tsearch_countedset_ptr);
+}
+extern "C" {
+  #[doc = " Returns the number of integers in the counted set. The number is tracked as integers are"]
+  #[doc = " added and removed, so this takes constant time."]
+  pub fn tsearch_countedset_get_count(ptr: tsearch_countedset_ptr) -> usize;
+}
+extern "C" {
+  #[doc = " Returns true if the counted set contains no integers or is NULL. Takes constant time."]
+  pub fn tsearch_countedset_is_empty(ptr: tsearch_countedset_ptr) -> bool;
+}
+extern "C" {
+  #[doc = " Returns the number of integers the counted set can contain before its buffer needs to grow."]
+  #[doc = " The capacity is never less than the number of integers in the counted set. Returns 0 if the"]
+  #[doc = " counted set is NULL. Takes constant time."]
+  pub fn tsearch_countedset_get_capacity(ptr: tsearch_countedset_ptr) -> usize;
+}
+extern "C" {
+  #[doc = " Returns the counted set's buffer of nodes and stores the number of nodes in outCount."]
+  #[doc = " The root of the tree is the first node. The padding bytes of each node are zeroed."]
+  #[doc = " The buffer is owned by the counted set and is only valid until the counted set is"]
+  #[doc = " modified or freed."]
+  pub fn tsearch_countedset_get_nodes(
+    ptr: tsearch_countedset_ptr,
+    outCount: *mut usize,
+  ) -> *const tsearch_countedset_node;
+}
+extern "C" {
+  #[doc = " Returns 1 if the counted set includes the integer, otherwise 0."]
+  pub fn tsearch_countedset_contains_int(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+  ) -> bool;
+}
+extern "C" {
+  #[doc = " Returns the count for the specified integer. Returns 0 if the integer is not in the set."]
+  pub fn tsearch_countedset_get_count_for_int(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+  ) -> usize;
+}
+extern "C" {
+  #[doc = " Creates an array of all of the integers in the specified counted set in descending order"]
+  #[doc = " (the integer with the largest count is returned first). On return, the specified outIntegers"]
+  #[doc = " pointer points at the array, which must be freed by the caller."]
+  pub fn tsearch_countedset_copy_ints(
+    ptr: tsearch_countedset_ptr,
+    outIntegers: *mut *mut GNEInteger,
+    outCount: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Copies all of the integers in the specified counted set into the caller's array, which can hold"]
+  #[doc = " capacity integers, in the same order as tsearch_countedset_copy_ints(), and stores the number of"]
+  #[doc = " integers copied in outCount. If the array is too small, nothing is copied, the number of integers"]
+  #[doc = " in the counted set is stored in outCount, and 0 is returned. Returns 1 if successful, otherwise 0."]
+  pub fn tsearch_countedset_copy_ints_into(
+    ptr: tsearch_countedset_ptr,
+    integers: *mut GNEInteger,
+    capacity: usize,
+    outCount: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Adds the specified integer to the counted set. The integer's count saturates at SIZE_MAX."]
+  #[doc = " Returns 1 if successful, otherwise 0."]
+  pub fn tsearch_countedset_add_int(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Adds the specified integer to the counted set the specified number of times. The integer's"]
+  #[doc = " count saturates at SIZE_MAX. Adding an integer 0 times does nothing. Returns 1 if successful,"]
+  #[doc = " otherwise 0."]
+  pub fn tsearch_countedset_add_int_count(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+    count: usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Adds the specified integer to the counted set once. If outCount is not NULL, the integer's"]
+  #[doc = " new count is stored in it. Unlike calling tsearch_countedset_add_int() followed by"]
+  #[doc = " tsearch_countedset_get_count_for_int(), the tree is only traversed once. Returns 1 if"]
+  #[doc = " successful, otherwise 0."]
+  pub fn tsearch_countedset_increment_int(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+    outCount: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Adds each of the count integers in the specified array to the counted set. The counted set's"]
+  #[doc = " buffer is grown once, up front, to make room for all of the integers. Returns 1 if successful,"]
+  #[doc = " otherwise 0. If adding the integers fails, some of them may have been added."]
+  pub fn tsearch_countedset_add_ints(
+    ptr: tsearch_countedset_ptr,
+    integers: *const GNEInteger,
+    count: usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Removes the specified integer from the counted set. Returns 1 if successful, otherwise 0."]
+  #[doc = " Success is unrelated to whether or not the integer exists in the counted set."]
+  pub fn tsearch_countedset_remove_int(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Decrements the count of the specified integer by one, removing the integer from the counted"]
+  #[doc = " set if its count falls to 0. If outCount is not NULL, the integer's new count is stored in it."]
+  #[doc = " Returns 1 if successful, otherwise 0. Success is unrelated to whether or not the integer"]
+  #[doc = " exists in the counted set."]
+  pub fn tsearch_countedset_decrement_int(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+    outCount: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Decrements the count of the specified integer by the specified amount, saturating at 0 and"]
+  #[doc = " removing the integer from the counted set if its count falls to 0. If outCount is not NULL,"]
+  #[doc = " the integer's new count is stored in it. Returns 1 if successful, otherwise 0. Success is"]
+  #[doc = " unrelated to whether or not the integer exists in the counted set."]
+  pub fn tsearch_countedset_decrement_int_count(
+    ptr: tsearch_countedset_ptr,
+    integer: GNEInteger,
+    count: usize,
+    outCount: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Removes all of the integers from the counted set. The counted set's buffer"]
+  #[doc = " isn't freed or shrunk, and all of it can be used by new integers."]
+  pub fn tsearch_countedset_remove_all_ints(
+    ptr: tsearch_countedset_ptr,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Multiplies the count of every integer in the counted set by the specified factor. Counts"]
+  #[doc = " saturate at SIZE_MAX. A factor of 0 removes all of the integers. Takes time proportional to"]
+  #[doc = " the number of nodes in the buffer. Returns 1 if successful, otherwise 0."]
+  pub fn tsearch_countedset_multiply_counts(
+    ptr: tsearch_countedset_ptr,
+    factor: usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Shrinks the counted set's buffer to fit the integers it contains. The nodes of removed"]
+  #[doc = " integers are discarded and, if there were any, the tree is rebuilt. Takes time proportional"]
+  #[doc = " to the number of nodes in the buffer. Returns 1 if successful, otherwise 0. If shrinking"]
+  #[doc = " fails, the counted set is unchanged."]
+  pub fn tsearch_countedset_shrink_to_fit(
+    ptr: tsearch_countedset_ptr,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Grows the counted set's buffer, if necessary, so that the specified number of additional"]
+  #[doc = " integers can be added to it without the buffer needing to grow again. Does nothing if the"]
+  #[doc = " buffer is already large enough. Returns 1 if successful, otherwise 0. If reserving fails,"]
+  #[doc = " the counted set is unchanged."]
+  pub fn tsearch_countedset_reserve(
+    ptr: tsearch_countedset_ptr,
+    additional: usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Adds each integer and its count in the other counted set to specified set. Counts saturate at"]
+  #[doc = " SIZE_MAX."]
+  pub fn tsearch_countedset_union(
+    ptr: tsearch_countedset_ptr,
+    otherPtr: tsearch_countedset_ptr,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Removes from the specified counted set each integer that isn\u{2019}t a member of the other set."]
+  #[doc = " If an integer is present in both sets, its counts are added together, saturating at SIZE_MAX."]
+  pub fn tsearch_countedset_intersect(
+    ptr: tsearch_countedset_ptr,
+    otherPtr: tsearch_countedset_ptr,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Removes each integer in the other counted set from the specified set, if present."]
+  #[doc = " Each integer's count is reduced by its count in the other set, and an integer whose count would"]
+  #[doc = " drop to zero or below is removed instead."]
+  pub fn tsearch_countedset_minus(
+    ptr: tsearch_countedset_ptr,
+    otherPtr: tsearch_countedset_ptr,
+  ) -> _Result;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct tsearch_ternarytree_node {
+  _unused: [u8; 0],
+}
+pub type tsearch_ternarytree_ptr = *mut tsearch_ternarytree_node;
+#[doc = " Called with each word visited by tsearch_ternarytree_visit_prefix_words(). The word is NUL-terminated"]
+#[doc = " and length is its length in bytes. The word is only valid for the duration of the call. Return true"]
+#[doc = " to continue visiting words or false to stop."]
+pub type tsearch_ternarytree_word_func = ::std::option::Option<
+  unsafe extern "C" fn(
+    word: *const ::std::os::raw::c_char,
+    length: usize,
+    context: *mut ::std::os::raw::c_void,
+  ) -> bool,
+>;
+extern "C" {
+  pub fn tsearch_ternarytree_init() -> tsearch_ternarytree_ptr;
+}
+extern "C" {
+  pub fn tsearch_ternarytree_free(ptr: tsearch_ternarytree_ptr);
+}
+extern "C" {
+  #[doc = " Returns a deep copy of the tree, including the document IDs associated with each word. Returns NULL"]
+  #[doc = " if the copy could not be allocated. The caller is responsible for calling tsearch_ternarytree_free()."]
+  pub fn tsearch_ternarytree_copy(
+    ptr: tsearch_ternarytree_ptr,
+  ) -> tsearch_ternarytree_ptr;
+}
+extern "C" {
+  pub fn tsearch_ternarytree_insert(
+    ptr: tsearch_ternarytree_ptr,
+    newCharacter: *const ::std::os::raw::c_char,
+    documentID: GNEInteger,
+  ) -> tsearch_ternarytree_ptr;
+}
+extern "C" {
+  #[doc = " Inserts the word into the tree and adds the document ID to the word's document IDs the specified"]
+  #[doc = " number of times, as if by calling tsearch_ternarytree_insert() count times. Returns the root of the"]
+  #[doc = " tree, which is a new tree if ptr is NULL."]
+  pub fn tsearch_ternarytree_insert_count(
+    ptr: tsearch_ternarytree_ptr,
+    newCharacter: *const ::std::os::raw::c_char,
+    documentID: GNEInteger,
+    count: usize,
+  ) -> tsearch_ternarytree_ptr;
+}
+extern "C" {
+  pub fn tsearch_ternarytree_remove(
+    ptr: tsearch_ternarytree_ptr,
+    documentID: GNEInteger,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Removes all of the document IDs associated with the word, so that the tree no longer contains it."]
+  #[doc = " The word's nodes are kept in the tree, because other words may pass through them. If outDidRemove"]
+  #[doc = " is not NULL, true is stored in it if the tree contained the word. Returns 1 if successful, otherwise 0."]
+  pub fn tsearch_ternarytree_remove_word(
+    ptr: tsearch_ternarytree_ptr,
+    target: *const ::std::os::raw::c_char,
+    outDidRemove: *mut bool,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Removes the document ID, however many times it was inserted, from the IDs associated with the word."]
+  #[doc = " The tree no longer contains the word if no other document IDs are associated with it. If outDidRemove"]
+  #[doc = " is not NULL, true is stored in it if the document ID was associated with the word. Returns 1 if"]
+  #[doc = " successful, otherwise 0."]
+  pub fn tsearch_ternarytree_remove_word_id(
+    ptr: tsearch_ternarytree_ptr,
+    target: *const ::std::os::raw::c_char,
+    documentID: GNEInteger,
+    outDidRemove: *mut bool,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Returns true if the target has been inserted into the tree and at least one document ID is still"]
+  #[doc = " associated with it, otherwise false. Returns false if the target is empty."]
+  pub fn tsearch_ternarytree_contains(
+    ptr: tsearch_ternarytree_ptr,
+    target: *const ::std::os::raw::c_char,
+  ) -> bool;
+}
+extern "C" {
+  #[doc = " Returns a GNEIntegerCountedSet with the IDs of the documents containing the target. The caller is"]
+  #[doc = " responsible for calling tsearch_countedset_free()."]
+  pub fn tsearch_ternarytree_copy_search_results(
+    ptr: tsearch_ternarytree_ptr,
+    target: *const ::std::os::raw::c_char,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Returns a tsearch_countedset_ptr with the IDs of the documents containing the target prefix. The caller"]
+  #[doc = " is responsible for calling tsearch_countedset_free(). An empty prefix matches every word in the tree."]
+  #[doc = " Returns NULL if no words match the prefix."]
+  pub fn tsearch_ternarytree_copy_prefix_search_results(
+    ptr: tsearch_ternarytree_ptr,
+    prefix: *const ::std::os::raw::c_char,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Returns a tsearch_countedset_ptr with the IDs of the documents containing the target string. The caller"]
+  #[doc = " is responsible for calling tsearch_countedset_free()."]
+  pub fn tsearch_ternarytree_copy_partial_search_results(
+    ptr: tsearch_ternarytree_ptr,
+    target: *const ::std::os::raw::c_char,
+    length: usize,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Returns a tsearch_countedset_ptr with the IDs of the documents containing the target suffix. The caller"]
+  #[doc = " is responsible for calling tsearch_countedset_free()."]
+  pub fn tsearch_ternarytree_copy_suffix_search_results(
+    ptr: tsearch_ternarytree_ptr,
+    suffix: *const ::std::os::raw::c_char,
+    length: usize,
+  ) -> tsearch_countedset_ptr;
+}
+extern "C" {
+  #[doc = " Calls the callback with each word in the tree that starts with the prefix and still has at least one"]
+  #[doc = " document ID associated with it. An empty prefix matches every word in the tree. The words are visited"]
+  #[doc = " in ascending order of their bytes compared as unsigned values, which is the order of their code points"]
+  #[doc = " for UTF-8 strings. Visiting stops early if the callback returns false. Returns 1 if successful,"]
+  #[doc = " otherwise 0."]
+  pub fn tsearch_ternarytree_visit_prefix_words(
+    ptr: tsearch_ternarytree_ptr,
+    prefix: *const ::std::os::raw::c_char,
+    callback: tsearch_ternarytree_word_func,
+    context: *mut ::std::os::raw::c_void,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Calls the callback with each word in the tree that is within maxDistance edits of the target and"]
+  #[doc = " still has at least one document ID associated with it. An edit inserts, deletes, or substitutes a"]
+  #[doc = " single UTF-8 code point, so a multi-byte character counts as one edit. Branches of the tree whose"]
+  #[doc = " words are all too far from the target aren't visited. The words are visited in the same order as"]
+  #[doc = " by tsearch_ternarytree_visit_prefix_words(), and visiting stops early if the callback returns false."]
+  #[doc = " Returns 1 if successful, otherwise 0."]
+  pub fn tsearch_ternarytree_visit_words_within_distance(
+    ptr: tsearch_ternarytree_ptr,
+    target: *const ::std::os::raw::c_char,
+    maxDistance: usize,
+    callback: tsearch_ternarytree_word_func,
+    context: *mut ::std::os::raw::c_void,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Copies all words contained in the tree into outResults (which much be freed by the caller)."]
+  pub fn tsearch_ternarytree_copy_contents(
+    ptr: tsearch_ternarytree_ptr,
+    outResults: *mut *mut ::std::os::raw::c_char,
+    outLength: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  pub fn tsearch_ternarytree_print(ptr: tsearch_ternarytree_ptr);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct tsearch_stringbuf {
+  _unused: [u8; 0],
+}
+pub type tsearch_stringbuf_ptr = *mut tsearch_stringbuf;
+extern "C" {
+  #[doc = " Creates an empty string buffer. Returns a pointer to the string buffer if successful, otherwise NULL."]
+  pub fn tsearch_stringbuf_init() -> tsearch_stringbuf_ptr;
+}
+extern "C" {
+  #[doc = " Creates a string buffer containing the specified C char array."]
+  #[doc = " Returns a pointer to the string buffer if successful, otherwise NULL."]
+  #[doc = " The length parameter refers to the number of chars in cString, but should not include"]
+  #[doc = " the null terminator."]
+  pub fn tsearch_stringbuf_init_with_cstring(
+    cString: *const ::std::os::raw::c_char,
+    length: usize,
+  ) -> tsearch_stringbuf_ptr;
+}
+extern "C" {
+  pub fn tsearch_stringbuf_free(ptr: tsearch_stringbuf_ptr);
+}
+extern "C" {
+  #[doc = " Returns the length of the string buffer. The length does not include space for a null terminator."]
+  pub fn tsearch_stringbuf_get_len(ptr: tsearch_stringbuf_ptr) -> usize;
+}
+extern "C" {
+  #[doc = " Returns the char at the specified index of the string buffer."]
+  #[doc = " Returns '\\0' if the index is past the bounds of the string or if the string buffer is NULL."]
+  pub fn tsearch_stringbuf_get_char_at_idx(
+    ptr: tsearch_stringbuf_ptr,
+    index: usize,
+  ) -> ::std::os::raw::c_char;
+}
+extern "C" {
+  #[doc = " Returns the string buffer's contents, which are NOT null-terminated. Use tsearch_stringbuf_get_len()"]
+  #[doc = " to get the length of the contents. The returned char array is owned by the string buffer and is only"]
+  #[doc = " valid until the string buffer is modified or freed. Returns NULL if the string buffer is NULL."]
+  pub fn tsearch_stringbuf_get_buffer(
+    ptr: tsearch_stringbuf_ptr,
+  ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+  #[doc = " Appends the specified char to the string buffer. Returns 1 if successful, otherwise 0."]
+  pub fn tsearch_stringbuf_append_char(
+    ptr: tsearch_stringbuf_ptr,
+    character: ::std::os::raw::c_char,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Appends the specified C char array into the string buffer. Returns 1 if successful, otherwise 0."]
+  #[doc = " The length parameter refers to the number of chars in cString, but should not include"]
+  #[doc = " the null terminator."]
+  pub fn tsearch_stringbuf_append_cstring(
+    ptr: tsearch_stringbuf_ptr,
+    cString: *const ::std::os::raw::c_char,
+    length: usize,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Returns a null-terminated char representation of the mutable string's contents."]
+  #[doc = " The returned char array must be freed by the caller."]
+  pub fn tsearch_stringbuf_copy_cstring(
+    ptr: tsearch_stringbuf_ptr,
+  ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+  pub fn tsearch_stringbuf_print(ptr: tsearch_stringbuf_ptr);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct tsearch_range {
+  pub location: usize,
+  pub length: usize,
+}
+#[test]
+fn bindgen_test_layout_tsearch_range() {
+  const UNINIT: ::std::mem::MaybeUninit<tsearch_range> =
+    ::std::mem::MaybeUninit::uninit();
+  let ptr = UNINIT.as_ptr();
+  assert_eq!(
+    ::std::mem::size_of::<tsearch_range>(),
+    16usize,
+    concat!("Size of: ", stringify!(tsearch_range))
+  );
+  assert_eq!(
+    ::std::mem::align_of::<tsearch_range>(),
+    8usize,
+    concat!("Alignment of ", stringify!(tsearch_range))
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).location) as usize - ptr as usize },
+    0usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_range),
+      "::",
+      stringify!(location)
+    )
+  );
+  assert_eq!(
+    unsafe { ::std::ptr::addr_of!((*ptr).length) as usize - ptr as usize },
+    8usize,
+    concat!(
+      "Offset of field: ",
+      stringify!(tsearch_range),
+      "::",
+      stringify!(length)
+    )
+  );
+}
+pub type process_token_func = ::std::option::Option<
+  unsafe extern "C" fn(
+    string: *const ::std::os::raw::c_char,
+    range: tsearch_range,
+    token: *mut u32,
+    length: usize,
+    context: *const ::std::os::raw::c_void,
+  ),
+>;
+extern "C" {
+  pub fn tsearch_cstring_tokenize(
+    cString: *const ::std::os::raw::c_char,
+    process: process_token_func,
+    context: *mut ::std::os::raw::c_void,
+  ) -> _Result;
+}
+extern "C" {
+  #[doc = " Finds the first token in the UTF-8 string of the specified length (in bytes) that starts at or after"]
+  #[doc = " the byte at startIndex. Tokens are separated by the same whitespace as in tsearch_cstring_tokenize()."]
+  #[doc = " If a token is found, its range of bytes is stored in outRange and true is returned, otherwise false."]
+  pub fn tsearch_string_next_token(
+    string: *const ::std::os::raw::c_char,
+    length: usize,
+    startIndex: usize,
+    outRange: *mut tsearch_range,
+  ) -> bool;
+}
+extern "C" {
+  pub fn tsearch_cstring_copy_code_points(
+    cString: *const ::std::os::raw::c_char,
+    outCodePoints: *mut *mut u32,
+    outLength: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  pub fn tsearch_cstring_copy_utf16_code_points(
+    cString: *const ::std::os::raw::c_char,
+    outCodePoints: *mut *mut u32,
+    outLength: *mut usize,
+  ) -> _Result;
+}
+extern "C" {
+  pub fn tsearch_code_point_character_count(codePoint: u32) -> usize;
+}
@@ -1,11 +1,41 @@
+use crate::error::TextSearchError;
+#[cfg(feature = "rayon")]
+use rayon::iter::{
+  FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator,
+};
+#[cfg(feature = "serde")]
+use serde::{
+  de::{MapAccess, Visitor},
+  ser::SerializeMap,
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+  cmp::{Ordering, Reverse},
+  collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+  convert::{TryFrom, TryInto},
+  fmt,
+  hash::{BuildHasher, Hash, Hasher},
+  iter::FusedIterator,
+  marker::PhantomData,
+  mem,
+  ops::{BitXor, Bound, RangeBounds},
+  slice, vec,
+};
 use text_search_sys::{
-  _Result, tsearch_countedset_add_int, tsearch_countedset_contains_int,
-  tsearch_countedset_copy, tsearch_countedset_copy_ints,
-  tsearch_countedset_free, tsearch_countedset_get_count,
-  tsearch_countedset_get_count_for_int, tsearch_countedset_init,
-  tsearch_countedset_intersect, tsearch_countedset_minus,
+  _Result, tsearch_countedset_add_int_count, tsearch_countedset_add_ints,
+  tsearch_countedset_contains_int, tsearch_countedset_copy,
+  tsearch_countedset_copy_into, tsearch_countedset_copy_ints_into,
+  tsearch_countedset_decrement_int, tsearch_countedset_decrement_int_count,
+  tsearch_countedset_free, tsearch_countedset_get_capacity,
+  tsearch_countedset_get_count, tsearch_countedset_get_count_for_int,
+  tsearch_countedset_get_nodes, tsearch_countedset_increment_int,
+  tsearch_countedset_init, tsearch_countedset_init_with_capacity,
+  tsearch_countedset_init_with_nodes, tsearch_countedset_intersect,
+  tsearch_countedset_is_empty, tsearch_countedset_minus,
+  tsearch_countedset_multiply_counts, tsearch_countedset_node,
   tsearch_countedset_ptr, tsearch_countedset_remove_all_ints,
-  tsearch_countedset_remove_int, tsearch_countedset_union, GNEInteger,
+  tsearch_countedset_remove_int, tsearch_countedset_reserve,
+  tsearch_countedset_shrink_to_fit, tsearch_countedset_union,
 };
 
 // FIXME
@@ -15,9 +45,11 @@ use text_search_sys::{
 // `tsearch_countedset`, which is a balanced binary tree stored in
 // a single, contiguous buffer.
 //
-// 1. Replace this with a simple `BTreeHashMap` implementation.
-// 2. Make the class generic
-// 3. Add support for non-mutating set functions, while maintaining
+// `HashCountedSet`, in the `hash_counted_set` module, is the generic,
+// `HashMap`-backed counterpart of `CountedSet`. See the design note there for
+// why the two are separate types.
+//
+// 1. Add support for non-mutating set functions, while maintaining
 //    backwards compatability.
 
 /// A counted set of 64-bit integers implemented by wrapping
@@ -29,9 +61,18 @@ use text_search_sys::{
 /// `CountedSet` keeps track of the number of times each integer has
 /// been added to it.
 ///
+/// Every `i64`, including `0`, `i64::MIN`, and `i64::MAX`, can be added to a
+/// `CountedSet`. No value is reserved to mark empty slots or removed
+/// values in the set's buffer; those are tracked separately.
+///
 /// `CountedSet` also supports the some standard set operations:
 /// intersect, minus, and union.
 ///
+/// The comparison operators `<`, `<=`, `>`, and `>=` compare sets as
+/// multisets: `a <= b` means every value in `a` is in `b` with at least the
+/// same count. Sets where neither contains the other are incomparable, so
+/// every comparison between them returns `false`.
+///
 /// # Examples
 ///
 /// ```
@@ -54,15 +95,34 @@ use text_search_sys::{
 /// println!("{}", set.get_count(1)); // prints "1"
 /// println!("{}", set.get_count(2)); // prints "0"
 /// ```
-#[derive(Debug)]
+///
+/// # Thread Safety
+///
+/// `CountedSet` is both `Send` and `Sync`. A set can be moved to another
+/// thread, and a shared `&CountedSet` can be read from several threads at
+/// once, because none of the methods taking `&self` modify the underlying
+/// `tsearch_countedset`. Mutating a set requires `&mut CountedSet`, so
+/// writes need external synchronization, such as a `Mutex` or a
+/// [`SharedCountedSet`], which wraps a set in a `RwLock`.
+///
+/// [`SharedCountedSet`]: ../shared_counted_set/struct.SharedCountedSet.html
 pub struct CountedSet {
   raw: tsearch_countedset_ptr,
 }
 
-// TODO: Replace i64 with generic Hash and Eq
 impl CountedSet {
+  /// The version of the byte format returned by
+  /// [`to_bytes`](#method.to_bytes) and read by
+  /// [`from_bytes`](#method.from_bytes).
+  pub const BYTES_FORMAT_VERSION: u32 = 1;
+
   /// Creates an empty `CountedSet`.
   ///
+  /// # Panics
+  ///
+  /// Panics if the set could not be allocated. Use
+  /// [`try_new`](#method.try_new) to handle that case instead.
+  ///
   /// # Examples
   ///
   /// ```
@@ -70,13 +130,157 @@ impl CountedSet {
   /// let set = CountedSet::new();
   /// ```
   pub fn new() -> CountedSet {
-    CountedSet {
-      raw: unsafe { tsearch_countedset_init() },
+    CountedSet::try_new().expect("failed to allocate CountedSet")
+  }
+
+  /// Creates an empty `CountedSet`.
+  ///
+  /// This is the fallible version of [`new`](#method.new).
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::AllocationFailed` if the set could not be
+  /// allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::{counted_set::CountedSet, error::TextSearchError};
+  ///
+  /// fn unique_count(values: &[i64]) -> Result<usize, TextSearchError> {
+  ///   let mut set = CountedSet::try_new()?;
+  ///   for value in values {
+  ///     set.try_insert(*value)?;
+  ///   }
+  ///   Ok(set.len())
+  /// }
+  ///
+  /// assert_eq!(Ok(2), unique_count(&[1, 2, 1]));
+  /// ```
+  pub fn try_new() -> Result<CountedSet, TextSearchError> {
+    let raw = unsafe { tsearch_countedset_init() };
+    if raw.is_null() {
+      Err(TextSearchError::AllocationFailed)
+    } else {
+      Ok(CountedSet { raw })
     }
   }
 
+  /// Creates an empty `CountedSet` with space for at least `capacity`
+  /// distinct values.
+  ///
+  /// The capacity is only a hint used to size the set's backing buffer
+  /// up front, which avoids reallocations while the set is being filled.
+  /// It is not a limit: the set grows beyond `capacity` as needed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the backing buffer could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::with_capacity(10);
+  /// assert_eq!(true, set.is_empty());
+  /// for value in 0..20 {
+  ///   set.insert(value);
+  /// }
+  /// assert_eq!(20, set.len());
+  /// ```
+  pub fn with_capacity(capacity: usize) -> CountedSet {
+    let raw = unsafe { tsearch_countedset_init_with_capacity(capacity) };
+    assert!(!raw.is_null(), "failed to allocate CountedSet buffer");
+    CountedSet { raw }
+  }
+
+  /// Creates a `CountedSet` that takes ownership of the specified
+  /// `tsearch_countedset`, which will be freed with
+  /// `tsearch_countedset_free()` when the set is dropped.
+  ///
+  /// This is the counterpart of [`into_raw`](#method.into_raw).
+  ///
+  /// # Safety
+  ///
+  /// `raw` must be a valid, non-null counted set created by GNETextSearch,
+  /// and Rust must now be responsible for freeing it. Nothing else may free,
+  /// modify, or keep using the counted set afterwards. Calling `from_raw`
+  /// with a counted set that is still owned elsewhere, or calling it twice
+  /// with the same pointer, leads to a double free.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  ///
+  /// let raw = set.into_raw();
+  /// // Hand `raw` to C code, then take it back.
+  /// let set = unsafe { CountedSet::from_raw(raw) };
+  /// assert_eq!(true, set.contains(1));
+  /// ```
+  pub unsafe fn from_raw(raw: tsearch_countedset_ptr) -> CountedSet {
+    debug_assert!(!raw.is_null());
+    CountedSet { raw }
+  }
+
+  /// Returns the underlying `tsearch_countedset` without giving up
+  /// ownership of it.
+  ///
+  /// # Safety
+  ///
+  /// The set still owns the counted set, so the pointer must not be used
+  /// after the set is dropped and must never be freed. The counted set must
+  /// only be modified through the pointer while no references to the set
+  /// exist, and only in ways that keep it valid.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  /// use text_search_sys::tsearch_countedset_get_count;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2]);
+  /// let count = unsafe { tsearch_countedset_get_count(set.as_raw()) };
+  /// assert_eq!(2, count);
+  /// ```
+  pub unsafe fn as_raw(&self) -> tsearch_countedset_ptr {
+    self.raw
+  }
+
+  /// Consumes the set and returns the underlying `tsearch_countedset`
+  /// without freeing it.
+  ///
+  /// The caller becomes responsible for freeing the counted set, either by
+  /// calling `tsearch_countedset_free()` or by passing it back to
+  /// [`from_raw`](#method.from_raw).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  /// use text_search_sys::tsearch_countedset_free;
+  ///
+  /// let set = CountedSet::from(vec![1]);
+  /// let raw = set.into_raw();
+  /// unsafe { tsearch_countedset_free(raw) };
+  /// ```
+  pub fn into_raw(self) -> tsearch_countedset_ptr {
+    let raw = self.raw;
+    mem::forget(self);
+    raw
+  }
+
   /// Returns the number of elements in the set.
   ///
+  /// The number of elements is kept up to date by GNETextSearch as values are
+  /// added and removed, so this takes constant time. Since the count isn't
+  /// cached by the wrapper, it also reflects changes made through the pointer
+  /// returned by [`as_raw`](#method.as_raw).
+  ///
   /// # Examples
   ///
   /// ```
@@ -97,6 +301,8 @@ impl CountedSet {
 
   /// Returns `true` if the set contains no elements.
   ///
+  /// This takes constant time.
+  ///
   /// # Examples
   ///
   /// ```
@@ -108,12 +314,44 @@ impl CountedSet {
   /// assert_eq!(false, set.is_empty());
   /// ```
   pub fn is_empty(&self) -> bool {
-    // TODO: Don't get the full count for this.
-    let count = unsafe { tsearch_countedset_get_count(self.raw) };
-    count == 0
+    unsafe { tsearch_countedset_is_empty(self.raw) }
   }
 
-  /// Clears the set, removing all values.
+  /// Returns the number of distinct values the set can hold before its
+  /// buffer needs to grow.
+  ///
+  /// The capacity is an implementation detail of the set's backing buffer.
+  /// It is never less than [`len`](#method.len), but may be greater, even
+  /// after calling [`with_capacity`](#method.with_capacity) or
+  /// [`reserve`](#method.reserve), and removing values doesn't increase it.
+  /// This takes constant time.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::with_capacity(10);
+  /// assert_eq!(true, set.capacity() >= 10);
+  ///
+  /// set.extend_from_slice(&[1, 2, 3]);
+  /// assert_eq!(true, set.capacity() >= set.len());
+  /// ```
+  pub fn capacity(&self) -> usize {
+    unsafe { tsearch_countedset_get_capacity(self.raw) }
+  }
+
+  /// Reserves space for at least `additional` more distinct values, so
+  /// that they can be inserted without the set's buffer growing.
+  ///
+  /// Does nothing if the capacity is already large enough. Inserting more
+  /// occurrences of values that are already in the set never needs more
+  /// space.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the new capacity overflows `usize` or the buffer could not
+  /// be allocated. The set is unchanged in that case.
   ///
   /// # Examples
   ///
@@ -122,17 +360,108 @@ impl CountedSet {
   ///
   /// let mut set = CountedSet::new();
   /// set.insert(1);
+  /// set.reserve(100);
+  /// assert_eq!(true, set.capacity() >= 101);
+  ///
+  /// let capacity = set.capacity();
+  /// set.reserve(10);
+  /// assert_eq!(capacity, set.capacity());
+  /// ```
+  pub fn reserve(&mut self, additional: usize) {
+    unsafe {
+      tsearch_countedset_reserve(self.raw, additional).expect();
+    }
+  }
+
+  /// Clears the set, removing all values.
+  ///
+  /// The set's buffer is kept, so clearing never reduces the set's
+  /// [`capacity`](#method.capacity), and the set can be refilled with as
+  /// many values as it held before without growing the buffer again. The
+  /// space used by values that were removed before clearing can be used
+  /// again, too. Use
+  /// [`shrink_to_fit`](#method.shrink_to_fit) afterwards to free the buffer's
+  /// unused space.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.extend_from_slice(&[1, 2, 3]);
+  /// let capacity = set.capacity();
+  ///
   /// set.clear();
   /// assert_eq!(true, set.is_empty());
+  /// assert_eq!(capacity, set.capacity());
   /// ```
   pub fn clear(&mut self) {
-    unsafe { tsearch_countedset_remove_all_ints(self.raw).expect() }
+    self.try_clear().unwrap()
+  }
+
+  /// Clears the set, removing all values and keeping the set's buffer.
+  ///
+  /// This is the fallible version of [`clear`](#method.clear).
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::OperationFailed` if GNETextSearch fails to
+  /// clear the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// assert_eq!(Ok(()), set.try_clear());
+  /// assert_eq!(true, set.is_empty());
+  /// ```
+  pub fn try_clear(&mut self) -> Result<(), TextSearchError> {
+    unsafe { tsearch_countedset_remove_all_ints(self.raw).into_result() }
+  }
+
+  /// Shrinks the set's buffer to fit the values it contains.
+  ///
+  /// Removing values from a set doesn't shrink its buffer, which keeps the
+  /// size it had when the set was largest. Shrinking discards the space
+  /// used by removed values and rebuilds the set's tree if necessary. The
+  /// set's values and counts are unchanged. This takes O(n) time, where n is
+  /// the number of values that have been in the set since it was created,
+  /// cleared, or last shrunk.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the new buffer could not be allocated. The set is unchanged
+  /// in that case.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from((0..1000).collect::<Vec<i64>>());
+  /// set.retain(|value, _| value < 10);
+  ///
+  /// set.shrink_to_fit();
+  /// assert_eq!(10, set.len());
+  /// assert_eq!(true, set.contains(9));
+  /// ```
+  pub fn shrink_to_fit(&mut self) {
+    unsafe {
+      tsearch_countedset_shrink_to_fit(self.raw).expect();
+    }
   }
 
   /// Substracts the values in `other` from the set.
   ///
   /// If values in `other` have been added multiple times, the counts for
-  /// equivalent values in the set will be subtracted by that amount.
+  /// equivalent values in the set will be subtracted by that amount. Counts
+  /// saturate at zero, so a value whose count in `other` is at least its
+  /// count in the set is removed from the set, as if by
+  /// [`remove_all`](#method.remove_all).
   ///
   /// # Examples
   ///
@@ -155,9 +484,38 @@ impl CountedSet {
   /// assert_eq!(0, set.get_count(2));
   /// ```
   pub fn minus(&mut self, other: &CountedSet) {
-    unsafe {
-      tsearch_countedset_minus(self.raw, other.raw).expect();
-    }
+    self.try_minus(other).unwrap()
+  }
+
+  /// Substracts the values in `other` from the set.
+  ///
+  /// This is the fallible version of [`minus`](#method.minus).
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::OperationFailed` if GNETextSearch fails to
+  /// subtract the values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(1);
+  ///
+  /// assert_eq!(Ok(()), set.try_minus(&other));
+  /// assert_eq!(1, set.get_count(1));
+  /// ```
+  pub fn try_minus(
+    &mut self,
+    other: &CountedSet,
+  ) -> Result<(), TextSearchError> {
+    unsafe { tsearch_countedset_minus(self.raw, other.raw).into_result() }
   }
 
   /// Adds the counts of the values in `other` to the set and removes from the
@@ -165,7 +523,7 @@ impl CountedSet {
   ///
   /// If matching values in `other` have been added multiple times, the
   /// counts for equivanent values in the set will be increased by that
-  /// amount.
+  /// amount. Counts saturate at `usize::MAX` instead of overflowing.
   ///
   /// # Examples
   ///
@@ -192,16 +550,47 @@ impl CountedSet {
   /// assert_eq!(false, set.contains(4));
   /// ```
   pub fn intersect(&mut self, other: &CountedSet) {
-    unsafe {
-      tsearch_countedset_intersect(self.raw, other.raw).expect();
-    }
+    self.try_intersect(other).unwrap()
+  }
+
+  /// Adds the counts of the values in `other` to the set and removes from the
+  /// set all values not also contained in `other`.
+  ///
+  /// This is the fallible version of [`intersect`](#method.intersect).
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::OperationFailed` if GNETextSearch fails to
+  /// intersect the sets.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(2);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(1);
+  ///
+  /// assert_eq!(Ok(()), set.try_intersect(&other));
+  /// assert_eq!(2, set.get_count(1));
+  /// assert_eq!(false, set.contains(2));
+  /// ```
+  pub fn try_intersect(
+    &mut self,
+    other: &CountedSet,
+  ) -> Result<(), TextSearchError> {
+    unsafe { tsearch_countedset_intersect(self.raw, other.raw).into_result() }
   }
 
   /// Adds each value in `other` to the set.
   ///
   /// If matching values in `other` have been added multiple times, the
   /// counts for equivanent values in `self` will be increased by that
-  /// amount.
+  /// amount. Counts saturate at `usize::MAX` instead of overflowing.
   ///
   /// # Examples
   ///
@@ -229,13 +618,18 @@ impl CountedSet {
   /// assert_eq!(2, set.get_count(4));
   /// ```
   pub fn union(&mut self, other: &CountedSet) {
-    unsafe {
-      tsearch_countedset_union(self.raw, other.raw).expect();
-    }
+    self.try_union(other).unwrap()
   }
 
-  /// Returns `true` if the set contains the specified valued,
-  /// otherwise `false`.
+  /// Adds each value in `other` to the set.
+  ///
+  /// This is the fallible version of [`union`](#method.union).
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::OperationFailed` if GNETextSearch fails to
+  /// add the values, which usually means that the set's buffer could not
+  /// be grown.
   ///
   /// # Examples
   ///
@@ -244,96 +638,143 @@ impl CountedSet {
   ///
   /// let mut set = CountedSet::new();
   /// set.insert(1);
-  /// set.insert(2);
-  /// set.insert(2);
-  /// assert_eq!(true, set.contains(1));
-  /// assert_eq!(true, set.contains(2));
-  /// assert_eq!(false, set.contains(3));
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(1);
+  /// other.insert(2);
+  ///
+  /// assert_eq!(Ok(()), set.try_union(&other));
+  /// assert_eq!(2, set.get_count(1));
+  /// assert_eq!(1, set.get_count(2));
   /// ```
-  pub fn contains(&self, value: i64) -> bool {
-    unsafe { tsearch_countedset_contains_int(self.raw, value) }
+  pub fn try_union(
+    &mut self,
+    other: &CountedSet,
+  ) -> Result<(), TextSearchError> {
+    unsafe { tsearch_countedset_union(self.raw, other.raw).into_result() }
   }
 
-  /// Returns the number of times the specified value has been added
-  /// to the set.
+  /// Returns a new set containing every value in any of the sets, where each
+  /// value's count is the sum of its counts in all of the sets.
+  ///
+  /// The sets are added to a single new set, one after another, so no
+  /// intermediate results are copied. An empty iterator returns an empty set.
   ///
   /// # Examples
   ///
   /// ```
   /// use text_search::counted_set::CountedSet;
   ///
-  /// let mut set = CountedSet::new();
-  /// set.insert(1);
-  /// set.insert(2);
-  /// set.insert(2);
-  /// assert_eq!(1, set.get_count(1));
-  /// assert_eq!(2, set.get_count(2));
-  /// assert_eq!(0, set.get_count(3));
+  /// let a = CountedSet::from(vec![1, 2]);
+  /// let b = CountedSet::from(vec![2, 3]);
+  /// let c = CountedSet::from(vec![3, 4]);
+  ///
+  /// let union = CountedSet::union_all(&[a, b, c]);
+  /// assert_eq!(vec![1, 2, 2, 1], union.get_counts(&[1, 2, 3, 4]));
   /// ```
-  pub fn get_count(&self, value: i64) -> usize {
-    unsafe { tsearch_countedset_get_count_for_int(self.raw, value) }
+  pub fn union_all<'a, I>(sets: I) -> CountedSet
+  where
+    I: IntoIterator<Item = &'a CountedSet>,
+  {
+    let mut union = CountedSet::new();
+    for set in sets {
+      union.union(set);
+    }
+    union
   }
 
-  /// Adds a value to the set, returning the number of times the specified
-  /// value has been added to the set.
+  /// Returns a new set containing only the values that are in every one of
+  /// the sets, where each value's count is the sum of its counts in all of
+  /// the sets, like [`intersect`](#method.intersect).
+  ///
+  /// The first set is copied and then intersected with each of the others.
+  /// Once the intersection is empty, the remaining sets are skipped. An empty
+  /// iterator returns an empty set.
   ///
   /// # Examples
   ///
   /// ```
   /// use text_search::counted_set::CountedSet;
   ///
-  /// let mut set = CountedSet::new();
-  /// assert_eq!(1, set.insert(1));
-  /// assert_eq!(2, set.insert(1));
+  /// let a = CountedSet::from(vec![1, 2, 3]);
+  /// let b = CountedSet::from(vec![2, 3, 3]);
+  /// let c = CountedSet::from(vec![3, 4]);
+  ///
+  /// let intersection = CountedSet::intersect_all(&[a, b, c]);
+  /// assert_eq!(vec![3], intersection.to_vec());
+  /// assert_eq!(4, intersection.get_count(3));
+  ///
+  /// assert_eq!(true, CountedSet::intersect_all(&[]).is_empty());
   /// ```
-  pub fn insert(&mut self, value: i64) -> usize {
-    unsafe {
-      tsearch_countedset_add_int(self.raw, value).expect();
-      tsearch_countedset_get_count_for_int(self.raw, value)
+  pub fn intersect_all<'a, I>(sets: I) -> CountedSet
+  where
+    I: IntoIterator<Item = &'a CountedSet>,
+  {
+    let mut sets = sets.into_iter();
+    let mut intersection = match sets.next() {
+      Some(first) => first.clone(),
+      None => return CountedSet::new(),
+    };
+    while !intersection.is_empty() {
+      match sets.next() {
+        Some(set) => intersection.intersect(set),
+        None => break,
+      }
     }
+    intersection
   }
 
-  // TODO: Make sure to implement `tsearch_countedset_remove_int()` with
-  // `remove_all()` because `tsearch_countedset_remove_int()` does **not**
-  // have this method's behavior. It has the behavior of `remove_all()`.
-
-  /// Decrements by one the count of the specified value in the
-  /// set. Returns the new count of the specified value in the set.
+  /// Returns a new set containing the values that are in both `self` and
+  /// `other` and whose summed count is at least `min_total`. Each value's
+  /// count is the sum of its counts in both sets, like
+  /// [`intersect`](#method.intersect), and this set isn't changed.
+  ///
+  /// This is the same as intersecting a copy of the set with `other` and then
+  /// calling [`filtered_by_min_count`](#method.filtered_by_min_count), but it
+  /// only walks the set once and never copies values that would be dropped. A
+  /// `min_total` of `0` returns the plain intersection.
   ///
   /// # Examples
   ///
   /// ```
   /// use text_search::counted_set::CountedSet;
   ///
-  /// let mut set = CountedSet::new();
-  /// set.insert(1);
-  /// set.insert(1);
+  /// let set = CountedSet::from(vec![1, 2, 2, 3, 3, 3, 4]);
+  /// let other = CountedSet::from(vec![1, 2, 3, 5, 5, 5, 5]);
   ///
-  /// assert_eq!(1, set.remove(1));
-  /// assert_eq!(0, set.remove(1));
-  /// assert_eq!(0, set.remove(1));
+  /// let frequent = set.intersect_min(&other, 3);
+  /// assert_eq!(vec![0, 3, 4, 0, 0], frequent.get_counts(&[1, 2, 3, 4, 5]));
+  ///
+  /// assert_eq!(3, set.intersect_min(&other, 0).len());
   /// ```
-  pub fn remove(&mut self, value: i64) -> usize {
-    unsafe {
-      let before = tsearch_countedset_get_count_for_int(self.raw, value);
-      if before == 0 {
-        0
-      } else {
-        let new_count = before - 1;
-        tsearch_countedset_remove_int(self.raw, value).expect();
-        for _ in 0..new_count {
-          tsearch_countedset_add_int(self.raw, value);
-        }
-        new_count
-      }
+  pub fn intersect_min(
+    &self,
+    other: &CountedSet,
+    min_total: usize,
+  ) -> CountedSet {
+    let kept: Vec<(i64, usize)> = self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .filter_map(|node| match other.get_count(node.integer) {
+        0 => None,
+        count => Some((node.integer, node.count.saturating_add(count))),
+      })
+      .filter(|(_, total)| *total >= min_total)
+      .collect();
+    let mut intersection = CountedSet::with_capacity(kept.len());
+    for (value, count) in kept {
+      intersection.insert_n(value, count);
     }
+    intersection
   }
 
-  /// Removes the specified value from the set, regardless of how
-  /// many times it had been added to the set.
+  /// Returns a new set containing the values that are in `self` or in
+  /// `other`, but not in both.
   ///
-  /// Returns `true` if the value had been contained in the set,
-  /// otherwise `false`.
+  /// Values contained in only one of the sets keep the count they have in
+  /// that set. Values contained in both sets are dropped entirely,
+  /// regardless of their counts.
   ///
   /// # Examples
   ///
@@ -343,27 +784,232 @@ impl CountedSet {
   /// let mut set = CountedSet::new();
   /// set.insert(1);
   /// set.insert(1);
+  /// set.insert(2);
   ///
-  /// assert_eq!(true, set.remove_all(1));
-  /// assert_eq!(false, set.remove_all(1));
+  /// let mut other = CountedSet::new();
+  /// other.insert(2);
+  /// other.insert(3);
+  /// other.insert(3);
+  /// other.insert(3);
+  ///
+  /// let difference = set.symmetric_difference(&other);
+  /// assert_eq!(2, difference.get_count(1));
+  /// assert_eq!(false, difference.contains(2));
+  /// assert_eq!(3, difference.get_count(3));
   /// ```
-  pub fn remove_all(&mut self, value: i64) -> bool {
-    unsafe {
-      let before = tsearch_countedset_get_count_for_int(self.raw, value);
-      tsearch_countedset_remove_int(self.raw, value).expect();
-      before > 0
+  pub fn symmetric_difference(&self, other: &CountedSet) -> CountedSet {
+    // The counts of the shared values in `common` are the sums of their
+    // counts in both sets, so subtracting `common` from either set removes
+    // the shared values completely.
+    let mut common = self.clone();
+    common.intersect(other);
+
+    let mut difference = self.clone();
+    difference.minus(&common);
+
+    let mut other_only = other.clone();
+    other_only.minus(&common);
+
+    difference.union(&other_only);
+    difference
+  }
+
+  /// Returns an iterator over the values in `self` that are left after
+  /// subtracting `other` with [`minus`](#method.minus): the values whose
+  /// count in `self` is greater than their count in `other`.
+  ///
+  /// Neither set is modified, and no new set is allocated. Each value is
+  /// looked up in `other` as the iterator reaches it. The values are
+  /// returned in the order of `self`'s buffer, which is neither sorted nor
+  /// ordered by count.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 1, 1, 2, 3]);
+  /// let other = CountedSet::from(vec![1, 1, 2, 4]);
+  ///
+  /// let mut difference: Vec<i64> = set.difference(&other).collect();
+  /// difference.sort_unstable();
+  /// assert_eq!(vec![1, 3], difference);
+  /// ```
+  pub fn difference<'a>(&'a self, other: &'a CountedSet) -> Difference<'a> {
+    Difference {
+      nodes: self.nodes().iter(),
+      other,
     }
   }
 
-  /// Copies the values contained in the set into a new `Vec`.
+  /// Returns an iterator over the values contained in both `self` and
+  /// `other`, which are the values [`intersect`](#method.intersect) would
+  /// keep.
   ///
-  /// The values in the returned `Vec` are sorted in decending order
-  /// according to how many times the values were added to the set.
+  /// Neither set is modified, and no new set is allocated. Each value is
+  /// looked up in `other` as the iterator reaches it, so iterating over the
+  /// smaller set is faster. The values are returned in the order of `self`'s
+  /// buffer, which is neither sorted nor ordered by count.
   ///
-  /// The order of values added the same number of times to the set
-  /// is undefined. So, if both `1` and `2` were each added three times
-  /// to the set, the returned `Vec` could be either `vec![1, 2]` or
-  /// `vec![2, 1]`.
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 1, 2, 3]);
+  /// let other = CountedSet::from(vec![3, 1, 5]);
+  ///
+  /// let mut intersection: Vec<i64> = set.intersection(&other).collect();
+  /// intersection.sort_unstable();
+  /// assert_eq!(vec![1, 3], intersection);
+  /// ```
+  pub fn intersection<'a>(&'a self, other: &'a CountedSet) -> Intersection<'a> {
+    Intersection {
+      nodes: self.nodes().iter(),
+      other,
+    }
+  }
+
+  /// Combines `other` into the set, using the specified closure to decide
+  /// each value's new count.
+  ///
+  /// For each value in either set, the closure is called with the value's
+  /// count in `self` and its count in `other`, where a set that doesn't
+  /// contain the value has a count of `0`. The value's count in `self` is
+  /// set to the closure's result, and a result of `0` removes the value from
+  /// the set. The values are visited in no particular order.
+  ///
+  /// The other set operations can be expressed with `merge_with`:
+  ///
+  /// - [`union`](#method.union) is `|a, b| a.saturating_add(b)`.
+  /// - [`intersect`](#method.intersect) is
+  ///   `|a, b| if a > 0 && b > 0 { a + b } else { 0 }`.
+  /// - [`minus`](#method.minus) is `|a, b| a.saturating_sub(b)`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 1, 1, 2]);
+  /// let other = CountedSet::from(vec![1, 2, 2, 3]);
+  ///
+  /// set.merge_with(&other, std::cmp::max);
+  /// assert_eq!(vec![3, 2, 1], set.get_counts(&[1, 2, 3]));
+  ///
+  /// set.merge_with(&other, std::cmp::min);
+  /// assert_eq!(vec![1, 2, 1], set.get_counts(&[1, 2, 3]));
+  /// ```
+  pub fn merge_with<F>(&mut self, other: &CountedSet, mut f: F)
+  where
+    F: FnMut(usize, usize) -> usize,
+  {
+    let mut other_counts: HashMap<i64, usize> =
+      other.values_and_counts().into_iter().collect();
+    for (value, count) in self.values_and_counts() {
+      let other_count = other_counts.remove(&value).unwrap_or(0);
+      let new_count = f(count, other_count);
+      if new_count != count {
+        self.set_count(value, new_count);
+      }
+    }
+    for (value, other_count) in other_counts {
+      let new_count = f(0, other_count);
+      if new_count > 0 {
+        self.set_count(value, new_count);
+      }
+    }
+  }
+
+  /// Returns `true` if the set contains the specified valued,
+  /// otherwise `false`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(2);
+  /// set.insert(2);
+  /// assert_eq!(true, set.contains(1));
+  /// assert_eq!(true, set.contains(2));
+  /// assert_eq!(false, set.contains(3));
+  /// ```
+  pub fn contains(&self, value: i64) -> bool {
+    unsafe { tsearch_countedset_contains_int(self.raw, value) }
+  }
+
+  /// Returns `true` if the set contains every one of the specified values.
+  /// Returns `true` if no values are specified.
+  ///
+  /// Instead of looking up each value in the set's tree, the set's buffer is
+  /// scanned once, stopping as soon as every value has been found.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 3]);
+  /// assert_eq!(true, set.contains_all(&[3, 1]));
+  /// assert_eq!(false, set.contains_all(&[1, 4]));
+  /// assert_eq!(true, set.contains_all(&[]));
+  /// ```
+  pub fn contains_all(&self, values: &[i64]) -> bool {
+    let mut missing: HashSet<i64> = values.iter().copied().collect();
+    if missing.len() > self.len() {
+      return false;
+    }
+    for node in self.nodes() {
+      if missing.is_empty() {
+        break;
+      }
+      if node.count > 0 {
+        missing.remove(&node.integer);
+      }
+    }
+    missing.is_empty()
+  }
+
+  /// Returns `true` if `self` and `other` contain the same values,
+  /// regardless of how many times each value has been added to either set.
+  ///
+  /// Use `==` to also compare the counts of the values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  /// set.insert(2);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(2);
+  /// other.insert(1);
+  ///
+  /// assert_eq!(true, set.has_same_elements(&other));
+  /// assert_ne!(set, other);
+  /// ```
+  pub fn has_same_elements(&self, other: &CountedSet) -> bool {
+    if self.len() != other.len() {
+      return false;
+    }
+    self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .all(|node| other.contains(node.integer))
+  }
+
+  /// Returns `true` if every value in `self` is also contained in `other`.
+  ///
+  /// This is a membership test: the number of times each value has been
+  /// added to either set is ignored.
   ///
   /// # Examples
   ///
@@ -373,274 +1019,5530 @@ impl CountedSet {
   /// let mut set = CountedSet::new();
   /// set.insert(1);
   /// set.insert(1);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(1);
+  /// other.insert(2);
+  ///
+  /// assert_eq!(true, set.is_subset(&other));
+  /// assert_eq!(false, other.is_subset(&set));
+  /// ```
+  pub fn is_subset(&self, other: &CountedSet) -> bool {
+    if self.len() > other.len() {
+      return false;
+    }
+    self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .all(|node| other.contains(node.integer))
+  }
+
+  /// Returns `true` if every value in `self` is contained in `other` with
+  /// at least the same count.
+  fn is_sub_multiset(&self, other: &CountedSet) -> bool {
+    if self.len() > other.len() {
+      return false;
+    }
+    self
+      .values_and_counts()
+      .into_iter()
+      .all(|(value, count)| count <= other.get_count(value))
+  }
+
+  /// Returns `true` if every value in `other` is also contained in `self`.
+  ///
+  /// This is a membership test: the number of times each value has been
+  /// added to either set is ignored.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
   /// set.insert(2);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(1);
+  /// other.insert(1);
+  ///
+  /// assert_eq!(true, set.is_superset(&other));
+  /// assert_eq!(false, other.is_superset(&set));
+  /// ```
+  pub fn is_superset(&self, other: &CountedSet) -> bool {
+    other.is_subset(self)
+  }
+
+  /// Returns `true` if `self` and `other` have no values in common.
+  ///
+  /// This is a membership test: the number of times each value has been
+  /// added to either set is ignored.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(2);
+  /// assert_eq!(true, set.is_disjoint(&other));
+  ///
+  /// other.insert(1);
+  /// assert_eq!(false, set.is_disjoint(&other));
+  /// ```
+  pub fn is_disjoint(&self, other: &CountedSet) -> bool {
+    let (smaller, larger) = if self.len() <= other.len() {
+      (self, other)
+    } else {
+      (other, self)
+    };
+    smaller
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .all(|node| !larger.contains(node.integer))
+  }
+
+  /// Returns the number of times the specified value has been added
+  /// to the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
   /// set.insert(2);
   /// set.insert(2);
-  /// set.insert(4);
+  /// assert_eq!(1, set.get_count(1));
+  /// assert_eq!(2, set.get_count(2));
+  /// assert_eq!(0, set.get_count(3));
+  /// ```
+  pub fn get_count(&self, value: i64) -> usize {
+    unsafe { tsearch_countedset_get_count_for_int(self.raw, value) }
+  }
+
+  /// Returns the number of times the specified value has been added to the
+  /// set, or `None` if the set doesn't contain the value.
+  ///
+  /// A value is removed from the set as soon as its count falls to zero, so
+  /// this never returns `Some(0)`. Unlike calling
+  /// [`contains`](#method.contains) and then
+  /// [`get_count`](#method.get_count), the set's tree is only searched once.
+  ///
+  /// # Examples
   ///
-  /// assert_eq!(vec![2, 1, 4], set.to_vec());
   /// ```
-  pub fn to_vec(&self) -> Vec<i64> {
-    let mut integers: Vec<GNEInteger> = Vec::with_capacity(0);
-    let inout_count: Box<usize> = Box::new(0);
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 2, 2]);
+  /// assert_eq!(Some(2), set.count_of(2));
+  /// assert_eq!(None, set.count_of(3));
+  ///
+  /// set.remove_all(2);
+  /// assert_eq!(None, set.count_of(2));
+  ///
+  /// if let Some(count) = set.count_of(1) {
+  ///   assert_eq!(1, count);
+  /// }
+  /// ```
+  pub fn count_of(&self, value: i64) -> Option<usize> {
+    match self.get_count(value) {
+      0 => None,
+      count => Some(count),
+    }
+  }
 
-    unsafe {
-      let integers_ptr: *mut *mut GNEInteger = &mut integers.as_mut_ptr();
-      let inout_count_ptr = Box::into_raw(inout_count);
-      tsearch_countedset_copy_ints(self.raw, integers_ptr, inout_count_ptr)
-        .expect();
-      let count = *Box::from_raw(inout_count_ptr);
-      Vec::from_raw_parts(*integers_ptr, count, count)
+  /// Returns the number of times each of the specified values has been added
+  /// to the set, in the same order as the values. Values that aren't in the
+  /// set have a count of `0`.
+  ///
+  /// Instead of looking up each value in the set's tree, the set's buffer is
+  /// scanned once and each node is checked against the queried values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2]);
+  /// assert_eq!(vec![2, 0, 1, 2], set.get_counts(&[2, 3, 1, 2]));
+  /// ```
+  pub fn get_counts(&self, values: &[i64]) -> Vec<usize> {
+    if values.is_empty() {
+      return Vec::new();
+    }
+    let mut counts: HashMap<i64, usize> =
+      values.iter().map(|value| (*value, 0)).collect();
+    for node in self.nodes() {
+      if let Some(count) = counts.get_mut(&node.integer) {
+        *count = node.count;
+      }
+    }
+    values.iter().map(|value| counts[value]).collect()
+  }
+
+  /// Returns the sum of the counts of the specified values, which is the same
+  /// as adding up [`get_count`] for each value. Values that aren't in the set
+  /// add nothing, and a value that's queried more than once is added once for
+  /// each time it's queried. The sum saturates at `usize::MAX`.
+  ///
+  /// Like [`get_counts`], the set's buffer is scanned once instead of looking
+  /// up each value in the set's tree, which makes this suited to scoring a
+  /// set of terms against a query.
+  ///
+  /// [`get_count`]: #method.get_count
+  /// [`get_counts`]: #method.get_counts
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2, 3, 3, 3]);
+  /// assert_eq!(5, set.sum_counts_of(&[2, 3, 4]));
+  /// assert_eq!(7, set.sum_counts_of(&[2, 2, 3]));
+  /// assert_eq!(0, set.sum_counts_of(&[]));
+  /// ```
+  pub fn sum_counts_of(&self, values: &[i64]) -> usize {
+    if values.is_empty() {
+      return 0;
+    }
+    let mut multiplicities: HashMap<i64, usize> = HashMap::new();
+    for value in values {
+      *multiplicities.entry(*value).or_insert(0) += 1;
+    }
+    self.nodes().iter().fold(0, |total, node| {
+      match multiplicities.get(&node.integer) {
+        Some(multiplicity) => {
+          total.saturating_add(node.count.saturating_mul(*multiplicity))
+        },
+        None => total,
+      }
+    })
+  }
+
+  /// Returns the number of distinct values in the set for which the predicate
+  /// returns true. The predicate is called once for each value in the set with
+  /// the value and its count, in no particular order.
+  ///
+  /// The set's buffer is scanned once and nothing is allocated, which makes
+  /// this cheaper than filtering the result of [`to_vec`].
+  ///
+  /// [`to_vec`]: #method.to_vec
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2, 10, 11, 11, 11]);
+  /// assert_eq!(2, set.count_distinct_matching(|value, _| value >= 10));
+  /// assert_eq!(2, set.count_distinct_matching(|_, count| count > 1));
+  /// assert_eq!(set.len(), set.count_distinct_matching(|_, _| true));
+  /// ```
+  pub fn count_distinct_matching<F>(&self, mut f: F) -> usize
+  where
+    F: FnMut(i64, usize) -> bool,
+  {
+    self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0 && f(node.integer, node.count))
+      .count()
+  }
+
+  /// Returns how many distinct values have each count, mapping each count
+  /// found in the set to the number of values that were added to the set
+  /// that many times.
+  ///
+  /// The set's buffer is scanned once. Summing the map's values gives
+  /// [`len`](#method.len), which makes it easy to see how many values a
+  /// threshold passed to
+  /// [`filtered_by_min_count`](#method.filtered_by_min_count) would keep.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::BTreeMap;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 1, 1, 2, 2, 2, 4]);
+  ///
+  /// let expected: BTreeMap<usize, usize> = vec![(1, 1), (3, 2)].into_iter().collect();
+  /// assert_eq!(expected, set.histogram());
+  /// assert_eq!(true, CountedSet::new().histogram().is_empty());
+  /// ```
+  pub fn histogram(&self) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for node in self.nodes() {
+      if node.count > 0 {
+        *histogram.entry(node.count).or_insert(0) += 1;
+      }
+    }
+    histogram
+  }
+
+  /// Adds a value to the set, returning the number of times the specified
+  /// value has been added to the set.
+  ///
+  /// The value's count saturates at `usize::MAX` instead of overflowing.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(1, set.insert(1));
+  /// assert_eq!(2, set.insert(1));
+  /// ```
+  pub fn insert(&mut self, value: i64) -> usize {
+    self.try_insert(value).unwrap()
+  }
+
+  /// Adds a value to the set, returning the number of times the specified
+  /// value has been added to the set.
+  ///
+  /// This is the fallible version of [`insert`](#method.insert).
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::OperationFailed` if GNETextSearch fails to
+  /// add the value, which usually means that the set's buffer could not
+  /// be grown.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(Ok(1), set.try_insert(1));
+  /// assert_eq!(Ok(2), set.try_insert(1));
+  /// ```
+  pub fn try_insert(&mut self, value: i64) -> Result<usize, TextSearchError> {
+    let mut new_count: usize = 0;
+    unsafe {
+      tsearch_countedset_increment_int(self.raw, value, &mut new_count)
+        .into_result()?;
+    }
+    Ok(new_count)
+  }
+
+  /// Returns an [`Entry`] for the specified value, which can be used to read,
+  /// increment, or remove the value's count.
+  ///
+  /// Creating the entry doesn't look the value up in the set's tree. Each of
+  /// the entry's methods traverses the tree once, so
+  /// `set.entry(value).increment()` adds the value and returns its new count
+  /// in a single traversal.
+  ///
+  /// [`Entry`]: struct.Entry.html
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(0, set.entry(1).count());
+  /// assert_eq!(1, set.entry(1).increment());
+  /// assert_eq!(2, set.entry(1).increment());
+  /// assert_eq!(2, set.entry(1).count());
+  ///
+  /// set.entry(1).or_remove();
+  /// assert_eq!(false, set.contains(1));
+  /// ```
+  pub fn entry(&mut self, value: i64) -> Entry<'_> {
+    Entry { set: self, value }
+  }
+
+  /// Adds a value to the set `count` times, returning the number of times
+  /// the specified value has now been added to the set.
+  ///
+  /// This is much faster than calling [`insert`](#method.insert) `count`
+  /// times. If `count` is `0`, the set isn't changed. The value's count
+  /// saturates at `usize::MAX`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the value could not be added, which usually means that the
+  /// set's buffer could not be grown.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(3, set.insert_n(1, 3));
+  /// assert_eq!(5, set.insert_n(1, 2));
+  /// assert_eq!(5, set.insert_n(1, 0));
+  /// assert_eq!(0, set.insert_n(2, 0));
+  /// assert_eq!(false, set.contains(2));
+  /// ```
+  pub fn insert_n(&mut self, value: i64, count: usize) -> usize {
+    unsafe {
+      tsearch_countedset_add_int_count(self.raw, value, count).expect();
+      tsearch_countedset_get_count_for_int(self.raw, value)
+    }
+  }
+
+  /// Adds each value in the slice to the set.
+  ///
+  /// The whole slice is handed to GNETextSearch at once, which grows the
+  /// set's buffer a single time before adding the values. In release builds,
+  /// this is roughly two to three times faster than calling
+  /// [`insert`](#method.insert) for each value: about 1.8x for 200,000
+  /// distinct values and about 2.8x for 1,000,000 values drawn from 1,000
+  /// distinct ones.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the values could not be added, which usually means that the
+  /// set's buffer could not be grown.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.extend_from_slice(&[3, 1, 3, 2, 3]);
+  /// assert_eq!(3, set.len());
+  /// assert_eq!(3, set.get_count(3));
+  /// assert_eq!(1, set.get_count(1));
+  /// ```
+  pub fn extend_from_slice(&mut self, values: &[i64]) {
+    unsafe {
+      tsearch_countedset_add_ints(self.raw, values.as_ptr(), values.len())
+        .expect();
+    }
+  }
+
+  /// Decrements by one the count of the specified value in the
+  /// set. Returns the new count of the specified value in the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  ///
+  /// assert_eq!(1, set.remove(1));
+  /// assert_eq!(0, set.remove(1));
+  /// assert_eq!(0, set.remove(1));
+  /// ```
+  pub fn remove(&mut self, value: i64) -> usize {
+    self.remove_checked(value).unwrap_or(0)
+  }
+
+  /// Decrements by one the count of the specified value in the set.
+  ///
+  /// Returns the new count of the specified value in the set, or `None` if
+  /// the set didn't contain the value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  ///
+  /// assert_eq!(Some(1), set.remove_checked(1));
+  /// assert_eq!(Some(0), set.remove_checked(1));
+  /// assert_eq!(None, set.remove_checked(1));
+  /// ```
+  pub fn remove_checked(&mut self, value: i64) -> Option<usize> {
+    unsafe {
+      if !tsearch_countedset_contains_int(self.raw, value) {
+        return None;
+      }
+      let mut new_count: usize = 0;
+      tsearch_countedset_decrement_int(self.raw, value, &mut new_count)
+        .expect();
+      Some(new_count)
+    }
+  }
+
+  /// Decrements the count of the specified value in the set by up to
+  /// `count`, removing the value from the set if its count reaches zero.
+  /// Returns the new count of the specified value in the set.
+  ///
+  /// The count saturates at zero, so removing more than the value's count
+  /// removes the value entirely. If `count` is `0`, the set isn't changed.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert_n(1, 5);
+  ///
+  /// assert_eq!(3, set.remove_n(1, 2));
+  /// assert_eq!(0, set.remove_n(1, 10));
+  /// assert_eq!(false, set.contains(1));
+  /// assert_eq!(0, set.remove_n(1, 1));
+  /// ```
+  pub fn remove_n(&mut self, value: i64, count: usize) -> usize {
+    let mut new_count: usize = 0;
+    unsafe {
+      tsearch_countedset_decrement_int_count(
+        self.raw,
+        value,
+        count,
+        &mut new_count,
+      )
+      .expect();
+    }
+    new_count
+  }
+
+  /// Removes the specified value from the set, regardless of how
+  /// many times it had been added to the set.
+  ///
+  /// Returns `true` if the value had been contained in the set,
+  /// otherwise `false`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  ///
+  /// assert_eq!(true, set.remove_all(1));
+  /// assert_eq!(false, set.remove_all(1));
+  /// ```
+  pub fn remove_all(&mut self, value: i64) -> bool {
+    unsafe {
+      let before = tsearch_countedset_get_count_for_int(self.raw, value);
+      tsearch_countedset_remove_int(self.raw, value).expect();
+      before > 0
+    }
+  }
+
+  /// Retains only the values for which the specified predicate returns
+  /// `true`. The predicate is called with each value in the set and its
+  /// count. Every other value is removed from the set, regardless of how
+  /// many times it had been added to the set, as if by [`remove_all`].
+  ///
+  /// [`remove_all`]: #method.remove_all
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(-1);
+  /// set.insert(-1);
+  /// set.insert(1);
+  /// set.insert(2);
+  /// set.insert(2);
+  ///
+  /// set.retain(|value, count| value > 0 && count > 1);
+  /// assert_eq!(vec![2], set.to_vec());
+  /// assert_eq!(2, set.get_count(2));
+  /// ```
+  pub fn retain<F: FnMut(i64, usize) -> bool>(&mut self, mut f: F) {
+    for (value, count) in self.values_and_counts() {
+      if !f(value, count) {
+        self.remove_all(value);
+      }
+    }
+  }
+
+  /// Retains only the `n` values with the highest counts, removing every
+  /// other value from the set.
+  ///
+  /// The retained values are the ones [`most_common(n)`] returns, so values
+  /// with equal counts are ranked by ascending value, and the result is the
+  /// same every time. If the set contains `n` or fewer values, it isn't
+  /// changed. This finds the lowest ranked value to keep in a single pass
+  /// over the set, then removes the values ranked below it, keeping the
+  /// set's buffer as [`retain`] does.
+  ///
+  /// [`most_common(n)`]: #method.most_common
+  /// [`retain`]: #method.retain
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 2, 2, 3, 4, 4, 5, 5, 5]);
+  /// set.retain_top(3);
+  /// assert_eq!(vec![5, 2, 4], set.to_vec_sorted());
+  /// assert_eq!(vec![2, 2, 3], set.get_counts(&[2, 4, 5]));
+  ///
+  /// set.retain_top(5);
+  /// assert_eq!(3, set.len());
+  /// ```
+  pub fn retain_top(&mut self, n: usize) {
+    if self.len() <= n {
+      return;
+    }
+    if n == 0 {
+      self.clear();
+      return;
+    }
+    let (last_value, last_count) = self.most_common(n)[n - 1];
+    let threshold = (last_count, Reverse(last_value));
+    self.retain(|value, count| (count, Reverse(value)) >= threshold);
+  }
+
+  /// Multiplies the count of every value in the set by `factor`.
+  ///
+  /// Counts saturate at `usize::MAX` instead of overflowing. A factor of `0`
+  /// removes every value from the set, and a factor of `1` leaves the set
+  /// unchanged. This takes O(n) time, where n is the number of values that
+  /// have been in the set since it was created or last shrunk.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 2, 2]);
+  /// set.scale(3);
+  /// assert_eq!(vec![3, 6], set.get_counts(&[1, 2]));
+  ///
+  /// set.scale(0);
+  /// assert_eq!(true, set.is_empty());
+  /// ```
+  pub fn scale(&mut self, factor: usize) {
+    unsafe {
+      tsearch_countedset_multiply_counts(self.raw, factor).expect();
+    }
+  }
+
+  /// Returns a copy of the set with the count of every value multiplied by
+  /// `factor`. This set isn't changed.
+  ///
+  /// See [`scale`](#method.scale) for details. Scaling sets before combining
+  /// them weights their counts, for example to make matches in a document's
+  /// title count more than matches in its body.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let title_matches = CountedSet::from(vec![1, 2]);
+  /// let body_matches = CountedSet::from(vec![2, 3, 3]);
+  ///
+  /// let mut matches = title_matches.scaled(3);
+  /// matches.union(&body_matches);
+  /// assert_eq!(vec![3, 4, 2], matches.get_counts(&[1, 2, 3]));
+  /// assert_eq!(vec![1, 1, 0], title_matches.get_counts(&[1, 2, 3]));
+  /// ```
+  pub fn scaled(&self, factor: usize) -> CountedSet {
+    if factor == 0 {
+      return CountedSet::new();
+    }
+    let mut scaled = self.clone();
+    scaled.scale(factor);
+    scaled
+  }
+
+  /// Returns a new set containing only the values whose count is at least
+  /// `threshold`, with the same counts as in this set. This set isn't
+  /// changed.
+  ///
+  /// The new set's buffer is allocated at the size of the pruned set. A
+  /// threshold of `0` returns a copy of the whole set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2, 3, 3, 3]);
+  ///
+  /// let common = set.filtered_by_min_count(2);
+  /// assert_eq!(vec![0, 2, 3], common.get_counts(&[1, 2, 3]));
+  /// assert_eq!(3, set.len());
+  /// ```
+  pub fn filtered_by_min_count(&self, threshold: usize) -> CountedSet {
+    if threshold == 0 {
+      return self.clone();
+    }
+    let kept: Vec<(i64, usize)> = self
+      .values_and_counts()
+      .into_iter()
+      .filter(|(_, count)| *count >= threshold)
+      .collect();
+    let mut filtered = CountedSet::with_capacity(kept.len());
+    for (value, count) in kept {
+      filtered.insert_n(value, count);
+    }
+    filtered
+  }
+
+  /// Returns a new set containing each value in this set passed through
+  /// `f`, with the same count. This set isn't changed.
+  ///
+  /// If `f` maps several values to the same value, their counts are added
+  /// together, saturating at `usize::MAX`. `f` is called once for each value
+  /// in the set, in no particular order. This is useful for translating
+  /// document ids from one id space to another, such as from the ids in a
+  /// shard of an index to global ids.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let local = CountedSet::from(vec![1, 2, 2, 3]);
+  ///
+  /// let global = local.map_values(|id| id + 1000);
+  /// assert_eq!(vec![1, 2, 1], global.get_counts(&[1001, 1002, 1003]));
+  ///
+  /// let parity = local.map_values(|id| id % 2);
+  /// assert_eq!(vec![2, 2], parity.get_counts(&[0, 1]));
+  /// ```
+  pub fn map_values<F: FnMut(i64) -> i64>(&self, mut f: F) -> CountedSet {
+    let values_and_counts = self.values_and_counts();
+    let mut mapped = CountedSet::with_capacity(values_and_counts.len());
+    for (value, count) in values_and_counts {
+      mapped.insert_n(f(value), count);
+    }
+    mapped
+  }
+
+  /// Returns a new set containing only the values that fall within the
+  /// range, with the same counts as in this set. This set isn't changed.
+  ///
+  /// The range can be any kind of range over `i64`s, such as `lo..hi` or
+  /// `lo..=hi`. The set's tree is ordered by value, so only the subtrees that
+  /// can hold values within the range are visited. An empty range returns an
+  /// empty set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 10, 10, 11, 20, 25, 30]);
+  ///
+  /// let shard = set.values_in_range(10..25);
+  /// assert_eq!(vec![2, 1, 1], shard.get_counts(&[10, 11, 20]));
+  /// assert_eq!(3, shard.len());
+  ///
+  /// assert_eq!(2, set.values_in_range(25..=30).len());
+  /// assert_eq!(3, set.values_in_range(..20).len());
+  /// assert_eq!(true, set.values_in_range(12..12).is_empty());
+  /// ```
+  pub fn values_in_range<R: RangeBounds<i64>>(&self, range: R) -> CountedSet {
+    let nodes = self.nodes();
+    let mut kept: Vec<(i64, usize)> = Vec::new();
+    let mut stack = if self.is_empty() { vec![] } else { vec![0] };
+    while let Some(index) = stack.pop() {
+      let node = &nodes[index];
+      let integer = node.integer;
+      if node.count > 0 && range.contains(&integer) {
+        kept.push((integer, node.count));
+      }
+      let below_start = match range.start_bound() {
+        Bound::Included(start) | Bound::Excluded(start) => integer <= *start,
+        Bound::Unbounded => false,
+      };
+      let above_end = match range.end_bound() {
+        Bound::Included(end) | Bound::Excluded(end) => integer >= *end,
+        Bound::Unbounded => false,
+      };
+      // Every value in the left subtree is smaller than the node's value and
+      // every value in the right subtree is larger.
+      if node.left != usize::MAX && !below_start {
+        stack.push(node.left);
+      }
+      if node.right != usize::MAX && !above_end {
+        stack.push(node.right);
+      }
+    }
+
+    let mut subset = CountedSet::with_capacity(kept.len());
+    for (value, count) in kept {
+      subset.insert_n(value, count);
+    }
+    subset
+  }
+
+  /// Returns the set's contents and leaves an empty set in its place.
+  ///
+  /// This is the same as `std::mem::take(&mut set)`. The returned set keeps
+  /// the original buffer, so none of its values are copied, but the new empty
+  /// set allocates a small buffer of its own. Use [`swap`](#method.swap)
+  /// with a set that's kept around for reuse to avoid that allocation.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the new empty set could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut scratch = CountedSet::from(vec![1, 2, 2]);
+  ///
+  /// let built = scratch.take();
+  /// assert_eq!(vec![1, 2], built.get_counts(&[1, 2]));
+  /// assert_eq!(true, scratch.is_empty());
+  /// ```
+  pub fn take(&mut self) -> CountedSet {
+    mem::take(self)
+  }
+
+  /// Swaps the contents of the set with those of `other`.
+  ///
+  /// Only the pointers to the sets' underlying `tsearch_countedset`s are
+  /// exchanged, so this takes constant time and never allocates, and each
+  /// set still frees exactly the buffer it now owns when it's dropped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut current = CountedSet::from(vec![1, 1]);
+  /// let mut next = CountedSet::from(vec![2]);
+  ///
+  /// current.swap(&mut next);
+  /// assert_eq!(vec![2], current.to_vec());
+  /// assert_eq!(2, next.get_count(1));
+  /// ```
+  pub fn swap(&mut self, other: &mut CountedSet) {
+    mem::swap(&mut self.raw, &mut other.raw);
+  }
+
+  /// Moves every value in `other` into the set, leaving `other` empty. Each
+  /// value's count in the set becomes the sum of its counts in both sets,
+  /// like [`union`](#method.union).
+  ///
+  /// If `other` contains more values than the set, the sets are
+  /// [swapped](#method.swap) first, so only the smaller set's values are
+  /// copied, and appending a large set to an empty one copies nothing.
+  /// Afterwards, `other` keeps whichever buffer wasn't used, cleared, so it
+  /// can be refilled without allocating.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the set's buffer could not grow to fit the values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 2]);
+  /// let mut other = CountedSet::from(vec![2, 3, 3]);
+  ///
+  /// set.append(&mut other);
+  /// assert_eq!(vec![1, 2, 2], set.get_counts(&[1, 2, 3]));
+  /// assert_eq!(true, other.is_empty());
+  /// ```
+  pub fn append(&mut self, other: &mut CountedSet) {
+    if other.len() > self.len() {
+      self.swap(other);
+    }
+    self.union(other);
+    other.clear();
+  }
+
+  /// Clears the set, returning each value it contained paired with its count
+  /// as an iterator.
+  ///
+  /// The set is empty once the iterator has been dropped, even if it wasn't
+  /// fully consumed. The values are returned in no particular order.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.extend_from_slice(&[1, 2, 2]);
+  ///
+  /// let mut drained: Vec<(i64, usize)> = set.drain().collect();
+  /// drained.sort();
+  /// assert_eq!(vec![(1, 1), (2, 2)], drained);
+  /// assert_eq!(true, set.is_empty());
+  ///
+  /// // The set can be reused.
+  /// set.insert(3);
+  /// assert_eq!(1, set.len());
+  /// ```
+  pub fn drain(&mut self) -> Drain<'_> {
+    Drain {
+      values_and_counts: self.values_and_counts().into_iter(),
+      set: self,
+    }
+  }
+
+  /// Copies the values contained in the set into a new `Vec`.
+  ///
+  /// The values in the returned `Vec` are sorted in decending order
+  /// according to how many times the values were added to the set.
+  ///
+  /// The order of values added the same number of times to the set
+  /// is undefined. So, if both `1` and `2` were each added three times
+  /// to the set, the returned `Vec` could be either `vec![1, 2]` or
+  /// `vec![2, 1]`. Use [`to_vec_sorted`](#method.to_vec_sorted) when the
+  /// order has to be the same every time.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  /// set.insert(2);
+  /// set.insert(2);
+  /// set.insert(2);
+  /// set.insert(4);
+  ///
+  /// assert_eq!(vec![2, 1, 4], set.to_vec());
+  /// ```
+  pub fn to_vec(&self) -> Vec<i64> {
+    let mut vec = Vec::new();
+    self.to_vec_into(&mut vec);
+    vec
+  }
+
+  /// Clears `buf` and fills it with the values contained in the set, in the
+  /// same order as [`to_vec`](#method.to_vec).
+  ///
+  /// The values are copied directly into `buf`, whose allocation is reused
+  /// if it's large enough to hold them, so calling this repeatedly with the
+  /// same `Vec` avoids allocating a new one each time. Sorting the values
+  /// by count still uses a temporary copy of the set's nodes.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut buf = Vec::with_capacity(16);
+  /// let sets = vec![
+  ///   CountedSet::from(vec![1, 2, 2]),
+  ///   CountedSet::from(vec![3, 3, 3, 4]),
+  /// ];
+  ///
+  /// sets[0].to_vec_into(&mut buf);
+  /// assert_eq!(vec![2, 1], buf);
+  ///
+  /// sets[1].to_vec_into(&mut buf);
+  /// assert_eq!(vec![3, 4], buf);
+  /// assert_eq!(16, buf.capacity());
+  /// ```
+  pub fn to_vec_into(&self, buf: &mut Vec<i64>) {
+    buf.clear();
+    let len = self.len();
+    if len == 0 {
+      return;
+    }
+
+    buf.reserve(len);
+    let mut count = 0;
+    unsafe {
+      tsearch_countedset_copy_ints_into(
+        self.raw,
+        buf.as_mut_ptr(),
+        buf.capacity(),
+        &mut count,
+      )
+      .expect();
+      // The C library initialized the first `count` values in the buffer.
+      buf.set_len(count);
+    }
+  }
+
+  /// Returns a `Vec` containing the values in the set, sorted in descending
+  /// order of count like [`to_vec`](#method.to_vec), with values added the
+  /// same number of times sorted in ascending order.
+  ///
+  /// Unlike `to_vec`, the order doesn't depend on the layout of the set's
+  /// buffer, so it's the same on every platform and for every set with the
+  /// same values and counts. Sorting the values makes this slower than
+  /// `to_vec`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![3, 2, 2, 1, 5, 5, 4]);
+  /// assert_eq!(vec![2, 5, 1, 3, 4], set.to_vec_sorted());
+  /// ```
+  pub fn to_vec_sorted(&self) -> Vec<i64> {
+    self.iter().collect()
+  }
+
+  /// Adds a `u32` value to the set, returning the number of times it has been
+  /// added to the set.
+  ///
+  /// The value is widened to an `i64` without changing it, so it's the same
+  /// as calling [`insert`](#method.insert) with `i64::from(value)`. The
+  /// `u32` methods are meant for sets that only ever hold `u32` ids, so that
+  /// ids above `i32::MAX` can't accidentally be stored as negative numbers.
+  /// Mixing them with the `i64` methods on the same set is the caller's
+  /// responsibility.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(1, set.insert_u32(u32::MAX));
+  /// assert_eq!(2, set.insert_u32(u32::MAX));
+  /// assert_eq!(2, set.get_count(4_294_967_295));
+  /// assert_eq!(false, set.contains(-1));
+  /// ```
+  pub fn insert_u32(&mut self, value: u32) -> usize {
+    self.insert(i64::from(value))
+  }
+
+  /// Returns `true` if the set contains the `u32` value.
+  ///
+  /// See [`insert_u32`](#method.insert_u32) for how `u32` values are stored.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert_u32(3_000_000_000);
+  ///
+  /// assert_eq!(true, set.contains_u32(3_000_000_000));
+  /// assert_eq!(false, set.contains_u32(1));
+  /// ```
+  pub fn contains_u32(&self, value: u32) -> bool {
+    self.contains(i64::from(value))
+  }
+
+  /// Returns the number of times the `u32` value has been added to the set.
+  ///
+  /// See [`insert_u32`](#method.insert_u32) for how `u32` values are stored.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert_u32(7);
+  /// set.insert_u32(7);
+  ///
+  /// assert_eq!(2, set.get_count_u32(7));
+  /// assert_eq!(0, set.get_count_u32(8));
+  /// ```
+  pub fn get_count_u32(&self, value: u32) -> usize {
+    self.get_count(i64::from(value))
+  }
+
+  /// Copies the values contained in the set into a new `Vec` of `u32`s, in
+  /// the same order as [`to_vec`](#method.to_vec).
+  ///
+  /// Values that don't fit in a `u32`, which can only have been added with
+  /// the `i64` methods, are skipped, so the result never contains a
+  /// truncated or wrapped value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert_u32(u32::MAX);
+  /// set.insert_u32(u32::MAX);
+  /// set.insert_u32(1);
+  /// set.insert(-1);
+  ///
+  /// assert_eq!(vec![u32::MAX, 1], set.to_vec_u32());
+  /// ```
+  pub fn to_vec_u32(&self) -> Vec<u32> {
+    self
+      .to_vec()
+      .into_iter()
+      .filter_map(|value| u32::try_from(value).ok())
+      .collect()
+  }
+
+  /// Returns an iterator over the values in the set, in descending order of
+  /// count like [`to_vec`](#method.to_vec).
+  ///
+  /// Values with equal counts are ordered by ascending value. The values are
+  /// copied and sorted when the iterator is created, so the iterator knows
+  /// its length and can be reversed with `rev` to visit the values with the
+  /// lowest counts first.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2, 3, 3, 3, 4]);
+  /// let mut iter = set.iter();
+  /// assert_eq!(4, iter.len());
+  /// assert_eq!(Some(3), iter.next());
+  /// assert_eq!(3, iter.len());
+  ///
+  /// assert_eq!(vec![4, 1], set.iter().rev().take(2).collect::<Vec<i64>>());
+  /// ```
+  pub fn iter(&self) -> Iter<'_> {
+    Iter {
+      inner: self.iter_with_counts(),
+    }
+  }
+
+  /// Returns an iterator over the values in the set paired with their
+  /// counts, in the same order as [`iter`](#method.iter).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![5, 7, 7, 7, 9, 9]);
+  /// assert_eq!(
+  ///   vec![(7, 3), (9, 2), (5, 1)],
+  ///   set.iter_with_counts().collect::<Vec<(i64, usize)>>()
+  /// );
+  ///
+  /// // The rarest values first.
+  /// let rarest: Vec<(i64, usize)> = set.iter_with_counts().rev().take(2).collect();
+  /// assert_eq!(vec![(5, 1), (9, 2)], rarest);
+  /// ```
+  pub fn iter_with_counts(&self) -> IterWithCounts<'_> {
+    let mut values_and_counts = self.values_and_counts();
+    values_and_counts
+      .sort_unstable_by_key(|(value, count)| (Reverse(*count), *value));
+    IterWithCounts {
+      values_and_counts: values_and_counts.into_iter(),
+      set: PhantomData,
+    }
+  }
+
+  /// Returns an iterator over the values in the set paired with their
+  /// counts, in ascending order of value.
+  ///
+  /// Unlike [`iter_with_counts`](#method.iter_with_counts), this doesn't
+  /// copy or sort the values. It walks GNETextSearch's binary search tree in
+  /// order, which visits the values in ascending order, so it takes O(n)
+  /// time in total, where n is the number of values that have been in the
+  /// set since it was created or last shrunk.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![9, -2, 4, 9, 9, 4]);
+  /// assert_eq!(
+  ///   vec![(-2, 1), (4, 2), (9, 3)],
+  ///   set.entries_iter().collect::<Vec<(i64, usize)>>()
+  /// );
+  /// ```
+  pub fn entries_iter(&self) -> EntriesIter<'_> {
+    EntriesIter {
+      nodes: self.nodes(),
+      stack: Vec::new(),
+      next: 0,
+      remaining: self.len(),
+    }
+  }
+
+  /// Returns the `n` values with the highest counts, paired with their
+  /// counts, in descending order of count.
+  ///
+  /// Values with equal counts are ordered by ascending value, so the result
+  /// is the same every time. If `n` is larger than the number of values in
+  /// the set, every value is returned.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(2);
+  /// set.insert(2);
+  /// set.insert(3);
+  /// set.insert(4);
+  /// set.insert(4);
+  ///
+  /// assert_eq!(vec![(2, 2), (4, 2), (1, 1)], set.most_common(3));
+  /// assert_eq!(4, set.most_common(10).len());
+  /// ```
+  pub fn most_common(&self, n: usize) -> Vec<(i64, usize)> {
+    if n == 0 {
+      return Vec::new();
+    }
+
+    // Higher counts rank first, followed by lower values. The heap holds the
+    // `n` highest ranked entries seen so far, with the lowest ranked of them
+    // at the top, so that it can be popped when a better entry is found.
+    let mut heap = BinaryHeap::with_capacity(n.min(self.len()) + 1);
+    for (value, count) in self.values_and_counts() {
+      heap.push(Reverse((count, Reverse(value))));
+      if heap.len() > n {
+        heap.pop();
+      }
+    }
+
+    heap
+      .into_sorted_vec()
+      .into_iter()
+      .map(|Reverse((count, Reverse(value)))| (value, count))
+      .collect()
+  }
+
+  /// Returns the value with the highest count, paired with its count, or
+  /// `None` if the set is empty. If several values share the highest count,
+  /// the smallest of them is returned.
+  ///
+  /// This scans the set's buffer once, without sorting it.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![7, 3, 3, 5, 5, 1]);
+  /// assert_eq!(Some((3, 2)), set.max_by_count());
+  /// assert_eq!(None, CountedSet::new().max_by_count());
+  /// ```
+  pub fn max_by_count(&self) -> Option<(i64, usize)> {
+    self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .max_by_key(|node| (node.count, Reverse(node.integer)))
+      .map(|node| (node.integer, node.count))
+  }
+
+  /// Returns the value with the lowest count, paired with its count, or
+  /// `None` if the set is empty. If several values share the lowest count,
+  /// the smallest of them is returned.
+  ///
+  /// This scans the set's buffer once, without sorting it.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![7, 3, 3, 5, 5, 1]);
+  /// assert_eq!(Some((1, 1)), set.min_by_count());
+  /// assert_eq!(None, CountedSet::new().min_by_count());
+  /// ```
+  pub fn min_by_count(&self) -> Option<(i64, usize)> {
+    self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .min_by_key(|node| (node.count, node.integer))
+      .map(|node| (node.integer, node.count))
+  }
+
+  /// Returns the sum of the counts of all of the values in the set, which is
+  /// the number of times values have been inserted into the set, less the
+  /// number of times they have been removed.
+  ///
+  /// The counts are summed in a single pass over the set's buffer, so this is
+  /// much faster than calling [`get_count`] for each value. The sum saturates
+  /// at `usize::MAX`.
+  ///
+  /// [`get_count`]: #method.get_count
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(0, set.total_count());
+  ///
+  /// set.insert(1);
+  /// set.insert(1);
+  /// set.insert(2);
+  /// assert_eq!(2, set.len());
+  /// assert_eq!(3, set.total_count());
+  /// ```
+  pub fn total_count(&self) -> usize {
+    self
+      .nodes()
+      .iter()
+      .fold(0, |total, node| total.saturating_add(node.count))
+  }
+
+  /// Serializes the set's nodes, so the set can be written to a file and
+  /// later turned back into an equal set with
+  /// [`from_bytes`](#method.from_bytes).
+  ///
+  /// # Format
+  ///
+  /// Every integer is stored in little-endian byte order and every `usize`
+  /// is widened to a `u64`, so the bytes can be read on any platform. The
+  /// bytes start with a header:
+  ///
+  /// | Offset | Type     | Field                                        |
+  /// |--------|----------|----------------------------------------------|
+  /// | 0      | `[u8; 4]`| The magic bytes `b"TSCS"`                    |
+  /// | 4      | `u32`    | The format version, currently `1`            |
+  /// | 8      | `u64`    | The number of nodes                          |
+  ///
+  /// The header is followed by the nodes of the set's balanced binary tree,
+  /// root first, each 40 bytes long:
+  ///
+  /// | Offset | Type  | Field                                           |
+  /// |--------|-------|-------------------------------------------------|
+  /// | 0      | `i64` | The value                                       |
+  /// | 8      | `u64` | The value's count, or 0 if it has been removed  |
+  /// | 16     | `i32` | The node's balance: -1, 0, or 1                 |
+  /// | 20     | `i32` | Reserved, always 0                              |
+  /// | 24     | `u64` | The index of the left child, or `u64::MAX`      |
+  /// | 32     | `u64` | The index of the right child, or `u64::MAX`     |
+  ///
+  /// # Versioning
+  ///
+  /// The format version is
+  /// [`BYTES_FORMAT_VERSION`](#associatedconstant.BYTES_FORMAT_VERSION). If
+  /// the format ever changes, the version will be incremented, and
+  /// `from_bytes` will keep reading version 1 bytes.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// assert_eq!(16, set.to_bytes().len());
+  ///
+  /// set.insert(1);
+  /// set.insert(2);
+  /// set.insert(2);
+  /// let bytes = set.to_bytes();
+  /// assert_eq!(b"TSCS", &bytes[..4]);
+  /// assert_eq!(16 + 2 * 40, bytes.len());
+  /// ```
+  pub fn to_bytes(&self) -> Vec<u8> {
+    write_nodes(self.nodes())
+  }
+
+  /// Creates a set from bytes returned by [`to_bytes`](#method.to_bytes).
+  ///
+  /// The bytes don't need to be aligned, and bytes written on a platform
+  /// with a different byte order or pointer width are read the same way.
+  /// See `to_bytes` for a description of the format.
+  ///
+  /// The nodes are checked before they're handed to GNETextSearch, so bytes
+  /// from an untrusted source can be passed safely: every child index must
+  /// be in bounds, every node must be reachable from the root exactly once,
+  /// and the values must be ordered like a binary search tree. Bytes that
+  /// fail any check are rejected instead of creating a set that could be
+  /// read out of bounds. The values of a valid tree are then laid out in a
+  /// new balanced tree, with balance fields matching its heights, because
+  /// GNETextSearch trusts the balance fields and rebalances recursively, so
+  /// a very deep tree could otherwise overflow the stack on the next
+  /// insert. The new set's bytes therefore don't need to match the bytes
+  /// passed in, and removed values aren't kept. The checks and the rebuild
+  /// take O(n) time and memory, where n is the number of nodes.
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::InvalidBytes` if the bytes don't start with
+  /// the format's header, have an unsupported version, are truncated or
+  /// followed by extra bytes, hold a count or child index that doesn't fit
+  /// in a `usize` on this platform, or if the nodes don't form a valid tree.
+  /// Returns `TextSearchError::AllocationFailed` if the set's buffer could
+  /// not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::{counted_set::CountedSet, error::TextSearchError};
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.extend_from_slice(&[3, 1, 4, 1, 5]);
+  ///
+  /// let bytes = set.to_bytes();
+  /// assert_eq!(set, CountedSet::from_bytes(&bytes).unwrap());
+  ///
+  /// assert_eq!(
+  ///   Err(TextSearchError::InvalidBytes),
+  ///   CountedSet::from_bytes(&bytes[1..])
+  /// );
+  /// ```
+  pub fn from_bytes(bytes: &[u8]) -> Result<CountedSet, TextSearchError> {
+    let nodes = read_nodes(bytes).ok_or(TextSearchError::InvalidBytes)?;
+    if !is_valid_tree(&nodes) {
+      return Err(TextSearchError::InvalidBytes);
+    }
+
+    // The in-order walk uses its own stack, so even a tree that is one long
+    // chain is read without recursing.
+    let values: Vec<(i64, usize)> = EntriesIter {
+      nodes: &nodes,
+      stack: Vec::new(),
+      next: 0,
+      remaining: nodes.iter().filter(|node| node.count > 0).count(),
+    }
+    .collect();
+    let mut balanced = Vec::with_capacity(values.len());
+    build_balanced_tree(&values, &mut balanced);
+
+    let raw = unsafe {
+      tsearch_countedset_init_with_nodes(balanced.as_ptr(), balanced.len())
+    };
+    if raw.is_null() {
+      Err(TextSearchError::AllocationFailed)
+    } else {
+      Ok(CountedSet { raw })
+    }
+  }
+
+  /// Returns a copy of the set.
+  ///
+  /// This is the fallible version of `clone`, which panics if the copy
+  /// could not be allocated.
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::AllocationFailed` if the copy could not be
+  /// allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 1, 2]);
+  /// let copy = set.try_clone().unwrap();
+  /// assert_eq!(set, copy);
+  /// ```
+  pub fn try_clone(&self) -> Result<CountedSet, TextSearchError> {
+    let raw = unsafe { tsearch_countedset_copy(self.raw) };
+    if raw.is_null() {
+      Err(TextSearchError::AllocationFailed)
+    } else {
+      Ok(CountedSet { raw })
+    }
+  }
+
+  /// Returns each value contained in the set paired with its count.
+  pub(crate) fn values_and_counts(&self) -> Vec<(i64, usize)> {
+    self
+      .nodes()
+      .iter()
+      .filter(|node| node.count > 0)
+      .map(|node| (node.integer, node.count))
+      .collect()
+  }
+
+  /// Returns the nodes in the set's buffer. Nodes with a count of zero belong
+  /// to values that have been removed from the set.
+  fn nodes(&self) -> &[tsearch_countedset_node] {
+    let mut count: usize = 0;
+    unsafe {
+      let nodes = tsearch_countedset_get_nodes(self.raw, &mut count);
+      if nodes.is_null() {
+        &[]
+      } else {
+        // The buffer is only modified or freed through `&mut self`, so it
+        // stays valid for as long as the returned slice borrows `self`.
+        slice::from_raw_parts(nodes, count)
+      }
+    }
+  }
+
+  /// Sets the count of the specified value, removing the value from the set
+  /// if the count is `0`.
+  fn set_count(&mut self, value: i64, count: usize) {
+    unsafe {
+      tsearch_countedset_remove_int(self.raw, value).expect();
+      tsearch_countedset_add_int_count(self.raw, value, count).expect();
+    }
+  }
+}
+
+/// The first bytes of every serialized `CountedSet`.
+const BYTES_MAGIC: &[u8; 4] = b"TSCS";
+
+/// The length of the header at the start of a serialized `CountedSet`.
+const BYTES_HEADER_LEN: usize = 16;
+
+/// The length of each serialized node.
+const NODE_BYTES_LEN: usize = 40;
+
+/// Serializes the nodes in the format described by `CountedSet::to_bytes`.
+fn write_nodes(nodes: &[tsearch_countedset_node]) -> Vec<u8> {
+  let mut bytes =
+    Vec::with_capacity(BYTES_HEADER_LEN + nodes.len() * NODE_BYTES_LEN);
+  bytes.extend_from_slice(BYTES_MAGIC);
+  bytes.extend_from_slice(&CountedSet::BYTES_FORMAT_VERSION.to_le_bytes());
+  bytes.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+  for node in nodes {
+    bytes.extend_from_slice(&node.integer.to_le_bytes());
+    bytes.extend_from_slice(&(node.count as u64).to_le_bytes());
+    bytes.extend_from_slice(&node.balance.to_le_bytes());
+    bytes.extend_from_slice(&node.reserved.to_le_bytes());
+    bytes.extend_from_slice(&write_index(node.left).to_le_bytes());
+    bytes.extend_from_slice(&write_index(node.right).to_le_bytes());
+  }
+  bytes
+}
+
+/// Reads the nodes from bytes returned by `CountedSet::to_bytes`. Returns
+/// `None` if the header is invalid, if the number of bytes doesn't match the
+/// number of nodes, or if a count or index doesn't fit in a `usize`.
+fn read_nodes(bytes: &[u8]) -> Option<Vec<tsearch_countedset_node>> {
+  if bytes.len() < BYTES_HEADER_LEN || &bytes[..4] != BYTES_MAGIC {
+    return None;
+  }
+  let version = u32::from_le_bytes(bytes[4..8].try_into().ok()?);
+  if version != CountedSet::BYTES_FORMAT_VERSION {
+    return None;
+  }
+  let count = u64::from_le_bytes(bytes[8..16].try_into().ok()?);
+  let nodes = bytes[BYTES_HEADER_LEN..].chunks_exact(NODE_BYTES_LEN);
+  if !nodes.remainder().is_empty() || nodes.len() as u64 != count {
+    return None;
+  }
+  nodes.map(read_node).collect()
+}
+
+/// Reads a node serialized by `CountedSet::to_bytes`.
+fn read_node(bytes: &[u8]) -> Option<tsearch_countedset_node> {
+  let u64_at = |offset: usize| {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+  };
+  let i32_at = |offset: usize| {
+    i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+  };
+  Some(tsearch_countedset_node {
+    integer: u64_at(0) as i64,
+    count: usize::try_from(u64_at(8)).ok()?,
+    balance: i32_at(16),
+    reserved: i32_at(20),
+    left: read_index(u64_at(24))?,
+    right: read_index(u64_at(32))?,
+  })
+}
+
+/// Widens a child index to a `u64`, keeping `usize::MAX` as the marker for a
+/// missing child on every platform.
+fn write_index(index: usize) -> u64 {
+  if index == usize::MAX {
+    u64::MAX
+  } else {
+    index as u64
+  }
+}
+
+/// Narrows a child index written by `write_index`. Returns `None` if the
+/// index doesn't fit in a `usize`.
+fn read_index(index: u64) -> Option<usize> {
+  if index == u64::MAX {
+    Some(usize::MAX)
+  } else {
+    usize::try_from(index).ok()
+  }
+}
+
+/// Returns true if the nodes form a binary search tree rooted at the first
+/// node, which every node belongs to exactly once.
+fn is_valid_tree(nodes: &[tsearch_countedset_node]) -> bool {
+  if nodes.is_empty() {
+    return true;
+  }
+
+  let mut visited = vec![false; nodes.len()];
+  // Each entry holds a node's index and the exclusive bounds of its value.
+  let mut stack = vec![(0, None, None)];
+  while let Some((index, lower, upper)) = stack.pop() {
+    if index >= nodes.len() || visited[index] {
+      return false;
+    }
+    visited[index] = true;
+
+    let node = &nodes[index];
+    let integer = node.integer;
+    if node.reserved != 0 || node.balance < -1 || node.balance > 1 {
+      return false;
+    }
+    let in_bounds = lower.iter().all(|lower| integer > *lower)
+      && upper.iter().all(|upper| integer < *upper);
+    if !in_bounds {
+      return false;
+    }
+    if node.left != usize::MAX {
+      stack.push((node.left, lower, Some(integer)));
+    }
+    if node.right != usize::MAX {
+      stack.push((node.right, Some(integer), upper));
+    }
+  }
+  visited.into_iter().all(|visited| visited)
+}
+
+impl Default for CountedSet {
+  /// Creates an empty `CountedSet`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set: CountedSet = Default::default();
+  /// assert_eq!(true, set.is_empty());
+  /// ```
+  fn default() -> CountedSet {
+    CountedSet::new()
+  }
+}
+
+/// The number of values printed by `CountedSet`'s `Debug` implementation
+/// before the rest are summarized.
+const DEBUG_MAX_VALUES: usize = 100;
+
+impl fmt::Debug for CountedSet {
+  /// Formats the set as its values and their counts, ordered by ascending
+  /// value. Only the first 100 values are printed, followed by the number of
+  /// values that were left out.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![91, 91, 91, 123456, -1]);
+  /// assert_eq!(
+  ///   "CountedSet { -1: 1, 91: 3, 123456: 1 }",
+  ///   format!("{:?}", set)
+  /// );
+  ///
+  /// let large = CountedSet::from((0..150).collect::<Vec<i64>>());
+  /// assert_eq!(true, format!("{:?}", large).ends_with("99: 1, ... 50 more }"));
+  /// ```
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut values_and_counts = self.values_and_counts();
+    values_and_counts.sort_unstable();
+    let remaining = values_and_counts.len().saturating_sub(DEBUG_MAX_VALUES);
+    let shown = values_and_counts.iter().take(DEBUG_MAX_VALUES);
+
+    if values_and_counts.is_empty() {
+      return f.write_str("CountedSet {}");
+    }
+    if f.alternate() {
+      f.write_str("CountedSet {\n")?;
+      for (value, count) in shown {
+        writeln!(f, "    {}: {},", value, count)?;
+      }
+      if remaining > 0 {
+        writeln!(f, "    ... {} more", remaining)?;
+      }
+      f.write_str("}")
+    } else {
+      f.write_str("CountedSet { ")?;
+      for (i, (value, count)) in shown.enumerate() {
+        if i > 0 {
+          f.write_str(", ")?;
+        }
+        write!(f, "{}: {}", value, count)?;
+      }
+      if remaining > 0 {
+        write!(f, ", ... {} more", remaining)?;
+      }
+      f.write_str(" }")
+    }
+  }
+}
+
+impl Clone for CountedSet {
+  /// Returns a copy of the set.
+  ///
+  /// The copy has its own buffer, into which the set's nodes are copied, so
+  /// modifying or dropping either set never affects the other.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the copy could not be allocated. Use
+  /// [`try_clone`](#method.try_clone) to handle that case instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  /// set.insert(2);
+  ///
+  /// let mut copy = set.clone();
+  /// copy.insert(3);
+  ///
+  /// assert_eq!(copy.get_count(1), set.get_count(1));
+  /// assert_eq!(copy.get_count(2), set.get_count(2));
+  /// assert_ne!(copy.get_count(3), set.get_count(3));
+  /// ```
+  fn clone(&self) -> CountedSet {
+    self.try_clone().expect("failed to copy CountedSet")
+  }
+
+  /// Replaces the contents of the set with a copy of `source`'s contents.
+  ///
+  /// The set's buffer is reused if it is large enough to hold `source`'s
+  /// values, so repeatedly refreshing a set from another one doesn't
+  /// allocate a new buffer each time.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the set's buffer had to grow and the new buffer could not be
+  /// allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let source = CountedSet::from(vec![1, 1, 2]);
+  /// let mut set = CountedSet::from(vec![3, 4, 5, 6]);
+  ///
+  /// set.clone_from(&source);
+  /// assert_eq!(source, set);
+  /// ```
+  fn clone_from(&mut self, source: &CountedSet) {
+    unsafe {
+      tsearch_countedset_copy_into(self.raw, source.raw).expect();
+    }
+  }
+}
+
+impl PartialEq for CountedSet {
+  /// Returns `true` if `self` and `other` contain the same values and each
+  /// value has the same count in both sets.
+  ///
+  /// The order in which the values were added to the sets is irrelevant.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(2);
+  /// set.insert(2);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(2);
+  /// other.insert(1);
+  /// assert_ne!(set, other);
+  ///
+  /// other.insert(2);
+  /// assert_eq!(set, other);
+  /// ```
+  fn eq(&self, other: &CountedSet) -> bool {
+    if self.len() != other.len() {
+      return false;
+    }
+    if self.is_empty() {
+      return true;
+    }
+    self
+      .to_vec()
+      .into_iter()
+      .all(|value| self.get_count(value) == other.get_count(value))
+  }
+}
+
+impl Eq for CountedSet {}
+
+impl PartialOrd for CountedSet {
+  /// Compares the sets as multisets, by containment.
+  ///
+  /// **This isn't a lexicographic comparison.** `a <= b` means that every
+  /// value in `a` is also in `b` with at least the same count, so `a` is a
+  /// sub-multiset of `b`. Unlike [`is_subset`](#method.is_subset), which
+  /// ignores counts, a value added twice to `a` but once to `b` keeps `a`
+  /// from being less than or equal to `b`.
+  ///
+  /// Returns `Some(Ordering::Equal)` if the sets are equal,
+  /// `Some(Ordering::Less)` if `self` is a proper sub-multiset of `other`,
+  /// `Some(Ordering::Greater)` if `other` is a proper sub-multiset of
+  /// `self`, and `None` if neither set contains the other. Because some sets
+  /// are incomparable, `CountedSet` doesn't implement `Ord`, and `a < b`
+  /// being `false` doesn't imply `a >= b`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::cmp::Ordering;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let small = CountedSet::from(vec![1, 2]);
+  /// let large = CountedSet::from(vec![1, 2, 2, 3]);
+  /// assert_eq!(true, small < large);
+  /// assert_eq!(true, large >= small);
+  ///
+  /// // Both sets contain the same values, but with different counts.
+  /// let a = CountedSet::from(vec![1, 1, 2]);
+  /// let b = CountedSet::from(vec![1, 2, 2]);
+  /// assert_eq!(None, a.partial_cmp(&b));
+  /// assert_eq!(false, a <= b);
+  /// assert_eq!(false, a >= b);
+  ///
+  /// assert_eq!(Some(Ordering::Equal), a.partial_cmp(&a.clone()));
+  /// ```
+  fn partial_cmp(&self, other: &CountedSet) -> Option<Ordering> {
+    match (self.is_sub_multiset(other), other.is_sub_multiset(self)) {
+      (true, true) => Some(Ordering::Equal),
+      (true, false) => Some(Ordering::Less),
+      (false, true) => Some(Ordering::Greater),
+      (false, false) => None,
+    }
+  }
+}
+
+impl Hash for CountedSet {
+  /// Feeds the values of the set and their counts into `state`.
+  ///
+  /// The values are hashed in ascending order, so sets that are equal
+  /// produce the same hash, regardless of the order in which their values
+  /// were added.
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    state.write_usize(self.len());
+    for (value, count) in self.entries_iter() {
+      value.hash(state);
+      count.hash(state);
+    }
+  }
+}
+
+impl BitXor<&CountedSet> for &CountedSet {
+  type Output = CountedSet;
+
+  /// Returns the symmetric difference of `self` and `rhs` as a new
+  /// `CountedSet`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(1);
+  /// set.insert(2);
+  ///
+  /// let mut other = CountedSet::new();
+  /// other.insert(2);
+  /// other.insert(3);
+  ///
+  /// let difference = &set ^ &other;
+  /// assert_eq!(true, difference.contains(1));
+  /// assert_eq!(false, difference.contains(2));
+  /// assert_eq!(true, difference.contains(3));
+  /// ```
+  fn bitxor(self, rhs: &CountedSet) -> CountedSet {
+    self.symmetric_difference(rhs)
+  }
+}
+
+impl From<Vec<i64>> for CountedSet {
+  /// Creates a set containing the values in the vector, where each value's
+  /// count is the number of times it appears in the vector.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 2, 2, 3, 3, 3]);
+  /// assert_eq!(3, set.len());
+  /// assert_eq!(3, set.get_count(3));
+  /// ```
+  fn from(values: Vec<i64>) -> CountedSet {
+    CountedSet::from(values.as_slice())
+  }
+}
+
+impl From<&[i64]> for CountedSet {
+  /// Creates a set containing the values in the slice, where each value's
+  /// count is the number of times it appears in the slice.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(&[5, 7, 5][..]);
+  /// assert_eq!(2, set.get_count(5));
+  /// assert_eq!(1, set.get_count(7));
+  /// ```
+  fn from(values: &[i64]) -> CountedSet {
+    let mut set = CountedSet::new();
+    set.extend_from_slice(values);
+    set
+  }
+}
+
+impl<S: BuildHasher> From<HashMap<i64, usize, S>> for CountedSet {
+  /// Creates a set containing the keys of the map, where each key's count is
+  /// its value in the map. Keys whose value is 0 aren't added to the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::HashMap;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut map = HashMap::new();
+  /// map.insert(1, 4);
+  /// map.insert(2, 0);
+  ///
+  /// let set = CountedSet::from(map);
+  /// assert_eq!(1, set.len());
+  /// assert_eq!(4, set.get_count(1));
+  /// ```
+  fn from(counts: HashMap<i64, usize, S>) -> CountedSet {
+    let mut set = CountedSet::with_capacity(counts.len());
+    for (value, count) in counts {
+      set.insert_n(value, count);
+    }
+    set
+  }
+}
+
+impl<S: BuildHasher + Default> From<CountedSet> for HashMap<i64, usize, S> {
+  /// Creates a map from each value in the set to its count.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::HashMap;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set = CountedSet::from(vec![1, 1, 2]);
+  /// let map: HashMap<i64, usize> = set.into();
+  ///
+  /// assert_eq!(2, map.len());
+  /// assert_eq!(Some(&2), map.get(&1));
+  /// assert_eq!(Some(&1), map.get(&2));
+  /// ```
+  fn from(set: CountedSet) -> HashMap<i64, usize, S> {
+    set.values_and_counts().into_iter().collect()
+  }
+}
+
+impl Extend<(i64, usize)> for CountedSet {
+  /// Adds each value produced by the iterator to the set the specified
+  /// number of times, like [`insert_n`](#method.insert_n).
+  ///
+  /// Counts for a value that appears more than once are added together, and
+  /// values whose count is 0 aren't added. Counts saturate at `usize::MAX`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::HashMap;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut counts = HashMap::new();
+  /// counts.insert(1, 3);
+  /// counts.insert(2, 1);
+  ///
+  /// let mut set = CountedSet::from(vec![1]);
+  /// set.extend(counts.iter().map(|(&value, &count)| (value, count)));
+  /// set.extend(vec![(2, 5), (3, 0)]);
+  ///
+  /// assert_eq!(4, set.get_count(1));
+  /// assert_eq!(6, set.get_count(2));
+  /// assert_eq!(false, set.contains(3));
+  /// ```
+  fn extend<I: IntoIterator<Item = (i64, usize)>>(&mut self, iter: I) {
+    for (value, count) in iter {
+      self.insert_n(value, count);
+    }
+  }
+}
+
+#[cfg(feature = "rayon")]
+impl ParallelExtend<i64> for CountedSet {
+  /// Adds each value produced by the parallel iterator to the set.
+  ///
+  /// GNETextSearch's counted set can't be modified from several threads at
+  /// once, so each of rayon's worker threads adds its share of the values to
+  /// a separate set. The partial sets are combined with
+  /// [`union`](#method.union), which adds their counts together, and the
+  /// result is added to this set.
+  ///
+  /// Combining the partial sets takes time proportional to the number of
+  /// distinct values in them, so parallelism only pays off when values are
+  /// repeated. In release builds, measured on a single thread against
+  /// [`extend_from_slice`](#method.extend_from_slice):
+  ///
+  /// - With 1,000 distinct values, `par_extend` is within 3% of
+  ///   `extend_from_slice` from 1,000,000 values up, and combining the
+  ///   partial sets takes well under a millisecond, so the work divides
+  ///   across the worker threads. At 100,000 values it's about 20% slower.
+  /// - With 250,000 distinct values among 1,000,000, it's about 1.6x slower,
+  ///   and each union of two partial sets costs about a fifth of
+  ///   `extend_from_slice`. That's roughly the crossover point for 8
+  ///   threads.
+  /// - With only distinct values, it's about 2x slower, and each union costs
+  ///   about half of `extend_from_slice`, so it's slower on any number of
+  ///   threads.
+  ///
+  /// Prefer `extend_from_slice` for fewer than about 1,000,000 values or
+  /// when each distinct value appears fewer than about 4 times.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the values could not be added, which usually means that a
+  /// set's buffer could not be grown.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rayon::prelude::*;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![0]);
+  /// set.par_extend((0..10_000).into_par_iter().map(|id| id % 100));
+  ///
+  /// assert_eq!(100, set.len());
+  /// assert_eq!(101, set.get_count(0));
+  /// assert_eq!(100, set.get_count(99));
+  /// ```
+  fn par_extend<I>(&mut self, par_iter: I)
+  where
+    I: IntoParallelIterator<Item = i64>,
+  {
+    let partial = par_iter
+      .into_par_iter()
+      .fold(CountedSet::new, |mut set, value| {
+        set.insert(value);
+        set
+      })
+      .reduce(CountedSet::new, |mut a, mut b| {
+        if a.len() < b.len() {
+          mem::swap(&mut a, &mut b);
+        }
+        a.union(&b);
+        a
+      });
+    if self.is_empty() {
+      *self = partial;
+    } else {
+      self.union(&partial);
+    }
+  }
+}
+
+#[cfg(feature = "rayon")]
+impl FromParallelIterator<i64> for CountedSet {
+  /// Creates a set from the values produced by the parallel iterator, as if
+  /// by [`par_extend`](#method.par_extend).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rayon::prelude::*;
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let set: CountedSet = vec![3, 1, 3].into_par_iter().collect();
+  /// assert_eq!(vec![3, 1], set.to_vec());
+  /// assert_eq!(2, set.get_count(3));
+  /// ```
+  fn from_par_iter<I>(par_iter: I) -> CountedSet
+  where
+    I: IntoParallelIterator<Item = i64>,
+  {
+    let mut set = CountedSet::new();
+    set.par_extend(par_iter);
+    set
+  }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for CountedSet {
+  /// Serializes the set as a map from each value to its count. The
+  /// entries are ordered by ascending value.
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(self.len()))?;
+    for (value, count) in self.entries_iter() {
+      map.serialize_entry(&value, &count)?;
+    }
+    map.end()
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CountedSet {
+  /// Deserializes a set from a map from each value to its count.
+  ///
+  /// Counts for repeated values are added together and values with a
+  /// count of zero are skipped.
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<CountedSet, D::Error> {
+    deserializer.deserialize_map(CountedSetVisitor)
+  }
+}
+
+#[cfg(feature = "serde")]
+struct CountedSetVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for CountedSetVisitor {
+  type Value = CountedSet;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a map from integers to their counts")
+  }
+
+  fn visit_map<A: MapAccess<'de>>(
+    self,
+    mut access: A,
+  ) -> Result<CountedSet, A::Error> {
+    let mut set = CountedSet::with_capacity(access.size_hint().unwrap_or(0));
+    while let Some((value, count)) = access.next_entry::<i64, usize>()? {
+      set.insert_n(value, count);
+    }
+    Ok(set)
+  }
+}
+
+// SAFETY: Each `CountedSet` exclusively owns its `tsearch_countedset`, and
+// GNETextSearch's counted set keeps no global or thread-local state, so it
+// can be freed or modified from any thread.
+unsafe impl Send for CountedSet {}
+
+// SAFETY: The C functions called by methods taking `&self` only read the
+// counted set's buffer. `tsearch_countedset_copy_ints()` sorts a private copy
+// of the buffer, not the buffer itself.
+unsafe impl Sync for CountedSet {}
+
+impl Drop for CountedSet {
+  fn drop(&mut self) {
+    // GNETextSearch only ever frees the counted set here. When one of its
+    // functions fails, it has either not changed the set yet or has finished
+    // the changes it made, and a failed `realloc` keeps the old buffer, so
+    // `self.raw` can always be freed, even while unwinding from a panic in
+    // the middle of an operation.
+    unsafe {
+      tsearch_countedset_free(self.raw);
+    }
+  }
+}
+
+/// An iterator over the values in a `CountedSet`.
+///
+/// This `struct` is created by the [`iter`](struct.CountedSet.html#method.iter)
+/// method on `CountedSet`.
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+  inner: IterWithCounts<'a>,
+}
+
+impl Iterator for Iter<'_> {
+  type Item = i64;
+
+  fn next(&mut self) -> Option<i64> {
+    self.inner.next().map(|(value, _)| value)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+  fn next_back(&mut self) -> Option<i64> {
+    self.inner.next_back().map(|(value, _)| value)
+  }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+/// An iterator over the values in a `CountedSet` and their counts.
+///
+/// This `struct` is created by the
+/// [`iter_with_counts`](struct.CountedSet.html#method.iter_with_counts)
+/// method on `CountedSet`.
+#[derive(Clone, Debug)]
+pub struct IterWithCounts<'a> {
+  values_and_counts: vec::IntoIter<(i64, usize)>,
+  set: PhantomData<&'a CountedSet>,
+}
+
+impl Iterator for IterWithCounts<'_> {
+  type Item = (i64, usize);
+
+  fn next(&mut self) -> Option<(i64, usize)> {
+    self.values_and_counts.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.values_and_counts.size_hint()
+  }
+}
+
+impl DoubleEndedIterator for IterWithCounts<'_> {
+  fn next_back(&mut self) -> Option<(i64, usize)> {
+    self.values_and_counts.next_back()
+  }
+}
+
+impl ExactSizeIterator for IterWithCounts<'_> {}
+
+impl FusedIterator for IterWithCounts<'_> {}
+
+/// An iterator over the values in a `CountedSet` and their counts, in
+/// ascending order of value.
+///
+/// This `struct` is created by the
+/// [`entries_iter`](struct.CountedSet.html#method.entries_iter) method on
+/// `CountedSet`.
+#[derive(Clone, Debug)]
+pub struct EntriesIter<'a> {
+  nodes: &'a [tsearch_countedset_node],
+  /// The indices of the nodes whose left subtrees are being visited.
+  stack: Vec<usize>,
+  /// The index of the root of the next subtree to visit, which is out of
+  /// bounds if the subtree is empty.
+  next: usize,
+  /// The number of values that haven't been visited yet.
+  remaining: usize,
+}
+
+impl Iterator for EntriesIter<'_> {
+  type Item = (i64, usize);
+
+  fn next(&mut self) -> Option<(i64, usize)> {
+    loop {
+      while let Some(node) = self.nodes.get(self.next) {
+        self.stack.push(self.next);
+        self.next = node.left;
+      }
+      let node = &self.nodes[self.stack.pop()?];
+      self.next = node.right;
+      if node.count > 0 {
+        self.remaining -= 1;
+        return Some((node.integer, node.count));
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl ExactSizeIterator for EntriesIter<'_> {}
+
+impl FusedIterator for EntriesIter<'_> {}
+
+/// A lazy iterator over the values in one `CountedSet` that are left after
+/// subtracting another.
+///
+/// This `struct` is created by the
+/// [`difference`](struct.CountedSet.html#method.difference) method on
+/// `CountedSet`.
+#[derive(Clone, Debug)]
+pub struct Difference<'a> {
+  nodes: slice::Iter<'a, tsearch_countedset_node>,
+  other: &'a CountedSet,
+}
+
+impl Iterator for Difference<'_> {
+  type Item = i64;
+
+  fn next(&mut self) -> Option<i64> {
+    let other = self.other;
+    self
+      .nodes
+      .by_ref()
+      .find(|node| node.count > other.get_count(node.integer))
+      .map(|node| node.integer)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (0, Some(self.nodes.len()))
+  }
+}
+
+impl FusedIterator for Difference<'_> {}
+
+/// A lazy iterator over the values contained in two `CountedSet`s.
+///
+/// This `struct` is created by the
+/// [`intersection`](struct.CountedSet.html#method.intersection) method on
+/// `CountedSet`.
+#[derive(Clone, Debug)]
+pub struct Intersection<'a> {
+  nodes: slice::Iter<'a, tsearch_countedset_node>,
+  other: &'a CountedSet,
+}
+
+impl Iterator for Intersection<'_> {
+  type Item = i64;
+
+  fn next(&mut self) -> Option<i64> {
+    let other = self.other;
+    self
+      .nodes
+      .by_ref()
+      .find(|node| node.count > 0 && other.contains(node.integer))
+      .map(|node| node.integer)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (0, Some(self.nodes.len()))
+  }
+}
+
+impl FusedIterator for Intersection<'_> {}
+
+/// A draining iterator over the values in a `CountedSet` and their counts.
+///
+/// This `struct` is created by the [`drain`](struct.CountedSet.html#method.drain)
+/// method on `CountedSet`.
+#[derive(Debug)]
+pub struct Drain<'a> {
+  set: &'a mut CountedSet,
+  values_and_counts: vec::IntoIter<(i64, usize)>,
+}
+
+impl Iterator for Drain<'_> {
+  type Item = (i64, usize);
+
+  fn next(&mut self) -> Option<(i64, usize)> {
+    self.values_and_counts.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.values_and_counts.size_hint()
+  }
+}
+
+impl ExactSizeIterator for Drain<'_> {}
+
+impl FusedIterator for Drain<'_> {}
+
+impl Drop for Drain<'_> {
+  fn drop(&mut self) {
+    self.set.clear();
+  }
+}
+
+/// A view into a single value in a `CountedSet`, which may or may not have
+/// been added to the set.
+///
+/// This `struct` is created by the [`entry`](struct.CountedSet.html#method.entry)
+/// method on `CountedSet`.
+#[derive(Debug)]
+pub struct Entry<'a> {
+  set: &'a mut CountedSet,
+  value: i64,
+}
+
+impl Entry<'_> {
+  /// Returns the number of times the entry's value has been added to the
+  /// set, or `0` if the set doesn't contain the value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 1]);
+  /// assert_eq!(2, set.entry(1).count());
+  /// assert_eq!(0, set.entry(2).count());
+  /// ```
+  pub fn count(&self) -> usize {
+    self.set.get_count(self.value)
+  }
+
+  /// Adds the entry's value to the set, returning the number of times the
+  /// value has now been added to the set.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the value could not be added, which usually means that the
+  /// set's buffer could not be grown.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::new();
+  /// set.insert(7);
+  /// assert_eq!(2, set.entry(7).increment());
+  /// assert_eq!(1, set.entry(3).increment());
+  /// ```
+  pub fn increment(self) -> usize {
+    let mut new_count: usize = 0;
+    unsafe {
+      tsearch_countedset_increment_int(
+        self.set.raw,
+        self.value,
+        &mut new_count,
+      )
+      .expect();
+    }
+    new_count
+  }
+
+  /// Removes the entry's value from the set, regardless of how many times it
+  /// had been added to the set. Does nothing if the set doesn't contain the
+  /// value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSet;
+  ///
+  /// let mut set = CountedSet::from(vec![1, 1, 2]);
+  /// set.entry(1).or_remove();
+  /// set.entry(3).or_remove();
+  /// assert_eq!(vec![2], set.to_vec());
+  /// ```
+  pub fn or_remove(self) {
+    unsafe {
+      tsearch_countedset_remove_int(self.set.raw, self.value).expect();
+    }
+  }
+}
+
+/// Builds a `CountedSet` from values that are added in ascending order.
+///
+/// Inserting values into a `CountedSet` one at a time searches its tree and
+/// rebalances it after each new value. When the values are already sorted,
+/// such as when they're read from a sorted posting list, the builder instead
+/// collects them and then lays out the set's balanced tree in a single pass
+/// when [`build`](#method.build) is called. Inserting ascending values one
+/// at a time takes time that grows with the square of their number, so in
+/// release builds, the builder is about 170x faster than calling
+/// [`CountedSet::insert`] for 10,000 distinct values, about 4,800x faster
+/// for 100,000 distinct values, and about 500x faster for 1,000,000 values
+/// drawn from 1,000 distinct ones.
+///
+/// [`CountedSet::insert`]: struct.CountedSet.html#method.insert
+///
+/// Values must be pushed in ascending order. The same value can be pushed
+/// several times in a row, in which case its counts are added together.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::counted_set::CountedSetBuilder;
+///
+/// let mut builder = CountedSetBuilder::new();
+/// builder.push(1);
+/// builder.push(1);
+/// builder.push_n(5, 3);
+/// builder.push(8);
+///
+/// let set = builder.build();
+/// assert_eq!(vec![2, 3, 1], set.get_counts(&[1, 5, 8]));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CountedSetBuilder {
+  /// The distinct values pushed so far and their counts, in the order they
+  /// were pushed.
+  values: Vec<(i64, usize)>,
+  /// Whether the values were pushed in ascending order.
+  is_sorted: bool,
+}
+
+impl CountedSetBuilder {
+  /// Creates an empty `CountedSetBuilder`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSetBuilder;
+  ///
+  /// let set = CountedSetBuilder::new().build();
+  /// assert_eq!(true, set.is_empty());
+  /// ```
+  pub fn new() -> CountedSetBuilder {
+    CountedSetBuilder::with_capacity(0)
+  }
+
+  /// Creates an empty `CountedSetBuilder` that can hold the specified number
+  /// of distinct values before it needs to reallocate.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSetBuilder;
+  ///
+  /// let mut builder = CountedSetBuilder::with_capacity(1000);
+  /// for value in 0..1000 {
+  ///   builder.push(value);
+  /// }
+  /// assert_eq!(1000, builder.build().len());
+  /// ```
+  pub fn with_capacity(capacity: usize) -> CountedSetBuilder {
+    CountedSetBuilder {
+      values: Vec::with_capacity(capacity),
+      is_sorted: true,
+    }
+  }
+
+  /// Adds a value to the set being built.
+  ///
+  /// The value must be greater than or equal to the value pushed before it.
+  ///
+  /// # Panics
+  ///
+  /// Panics in debug builds if the value is less than the previous value. In
+  /// release builds, [`build`](#method.build) inserts out of order values
+  /// one at a time instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSetBuilder;
+  ///
+  /// let mut builder = CountedSetBuilder::new();
+  /// builder.push(-3);
+  /// builder.push(-3);
+  /// builder.push(4);
+  ///
+  /// assert_eq!(vec![2, 1], builder.build().get_counts(&[-3, 4]));
+  /// ```
+  pub fn push(&mut self, value: i64) {
+    self.push_n(value, 1);
+  }
+
+  /// Adds a value to the set being built `count` times. If `count` is `0`,
+  /// the value isn't added. Counts saturate at `usize::MAX`.
+  ///
+  /// The value must be greater than or equal to the value pushed before it.
+  ///
+  /// # Panics
+  ///
+  /// Panics in debug builds if the value is less than the previous value. In
+  /// release builds, [`build`](#method.build) inserts out of order values
+  /// one at a time instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::CountedSetBuilder;
+  ///
+  /// let mut builder = CountedSetBuilder::new();
+  /// builder.push_n(1, 2);
+  /// builder.push_n(1, 3);
+  /// builder.push_n(2, 0);
+  ///
+  /// let set = builder.build();
+  /// assert_eq!(5, set.get_count(1));
+  /// assert_eq!(false, set.contains(2));
+  /// ```
+  pub fn push_n(&mut self, value: i64, count: usize) {
+    if count == 0 {
+      return;
+    }
+    match self.values.last_mut() {
+      Some((last, last_count)) if *last == value => {
+        *last_count = last_count.saturating_add(count);
+        return;
+      },
+      Some((last, _)) if *last > value => {
+        debug_assert!(
+          false,
+          "CountedSetBuilder values must be pushed in ascending order"
+        );
+        self.is_sorted = false;
+      },
+      _ => {},
+    }
+    self.values.push((value, count));
+  }
+
+  /// Builds the set from the values pushed so far.
+  ///
+  /// The set's buffer is allocated at the size of the set, and its balanced
+  /// tree is laid out directly from the sorted values, without searching or
+  /// rebalancing.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the set could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::counted_set::{CountedSet, CountedSetBuilder};
+  ///
+  /// let mut builder = CountedSetBuilder::new();
+  /// for value in 0..100 {
+  ///   builder.push_n(value, value as usize % 3 + 1);
+  /// }
+  /// let set = builder.build();
+  ///
+  /// let mut expected = CountedSet::new();
+  /// for value in 0..100 {
+  ///   expected.insert_n(value, value as usize % 3 + 1);
+  /// }
+  /// assert_eq!(expected, set);
+  /// ```
+  pub fn build(self) -> CountedSet {
+    if !self.is_sorted {
+      let mut set = CountedSet::with_capacity(self.values.len());
+      set.extend(self.values);
+      return set;
+    }
+    if self.values.is_empty() {
+      return CountedSet::new();
+    }
+
+    let mut nodes = Vec::with_capacity(self.values.len());
+    build_balanced_tree(&self.values, &mut nodes);
+    let raw = unsafe {
+      tsearch_countedset_init_with_nodes(nodes.as_ptr(), nodes.len())
+    };
+    assert!(!raw.is_null(), "failed to allocate CountedSet");
+    CountedSet { raw }
+  }
+}
+
+/// Appends the values, which must be sorted, to `nodes` as a balanced tree,
+/// with the middle value at the root and each half below it built the same
+/// way. Returns the index of the root, or `usize::MAX` if there are no
+/// values, and the height of the tree.
+fn build_balanced_tree(
+  values: &[(i64, usize)],
+  nodes: &mut Vec<tsearch_countedset_node>,
+) -> (usize, i32) {
+  if values.is_empty() {
+    return (usize::MAX, 0);
+  }
+  let middle = values.len() / 2;
+  let (integer, count) = values[middle];
+  let index = nodes.len();
+  nodes.push(tsearch_countedset_node {
+    integer,
+    count,
+    balance: 0,
+    reserved: 0,
+    left: usize::MAX,
+    right: usize::MAX,
+  });
+  let (left, left_height) = build_balanced_tree(&values[..middle], nodes);
+  let (right, right_height) = build_balanced_tree(&values[middle + 1..], nodes);
+  let node = &mut nodes[index];
+  node.left = left;
+  node.right = right;
+  // Like GNETextSearch, a positive balance means the left subtree is taller.
+  node.balance = left_height - right_height;
+  (index, left_height.max(right_height) + 1)
+}
+
+trait _ResultExt {
+  fn expect(self);
+  fn into_result(self) -> Result<(), TextSearchError>;
+}
+
+impl _ResultExt for _Result {
+  fn expect(self) {
+    match self {
+      1 => {}
+      _ => panic!("GNETextSearch failed to update the counted set"),
+    };
+  }
+
+  fn into_result(self) -> Result<(), TextSearchError> {
+    match self {
+      1 => Ok(()),
+      _ => Err(TextSearchError::OperationFailed),
+    }
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::useless_vec)]
+pub(crate) mod tests {
+  use super::*;
+  use std::ops::Range;
+  use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::Arc,
+    thread,
+  };
+  use text_search_sys::tsearch_countedset_add_int;
+
+  #[test]
+  fn default_counted_set() {
+    #[derive(Default)]
+    struct Container {
+      set: CountedSet,
+    }
+
+    let mut container = Container::default();
+    assert_eq!(true, container.set.is_empty());
+    container.set.insert(1);
+    assert_eq!(1, container.set.get_count(1));
+  }
+
+  #[test]
+  fn counted_set_with_capacity() {
+    let mut set = CountedSet::with_capacity(0);
+    assert_eq!(true, set.is_empty());
+    insert_integers(&mut set, vec![1, 1, 2]);
+    assert_eq!(2, set.len());
+
+    let mut set = CountedSet::with_capacity(1000);
+    assert_eq!(true, set.is_empty());
+    for int in -1000..1000 {
+      set.insert(int);
+      set.insert(int);
+    }
+    assert_eq!(2000, set.len());
+    assert_eq!(2, set.get_count(-1000));
+    assert_eq!(2, set.get_count(999));
+  }
+
+  #[test]
+  fn length_of_counted_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![-1, 0, 0, 1, 2]);
+    assert_eq!(4, set.len());
+  }
+
+  #[test]
+  fn is_counted_set_empty() {
+    let mut set = CountedSet::new();
+    assert_eq!(true, set.is_empty());
+
+    insert_integers(&mut set, vec![0, 0, 1, 2]);
+    for int in vec![0, 1, 2] {
+      set.remove(int);
+      assert_eq!(false, set.is_empty());
+    }
+    set.remove(0);
+    assert_eq!(true, set.is_empty());
+  }
+
+  #[test]
+  fn clear_counted_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![91, 123456, -1]);
+    assert_eq!(false, set.is_empty());
+    set.clear();
+    assert_eq!(true, set.is_empty());
+  }
+
+  #[test]
+  fn clear_keeps_capacity() {
+    let mut set = CountedSet::new();
+    set.extend_from_slice(&(0..1000).collect::<Vec<i64>>());
+    let capacity = set.capacity();
+    assert_eq!(true, capacity >= 1000);
+
+    for round in 0..3 {
+      set.clear();
+      assert_eq!(true, set.is_empty());
+      assert_eq!(capacity, set.capacity());
+      assert_eq!(false, set.contains(0));
+      assert_eq!(true, set.nodes().is_empty());
+
+      let values: Vec<i64> = (0..1000).map(|v| v * 3 + round).collect();
+      set.extend_from_slice(&values);
+      set.insert(round);
+      assert_eq!(1000, set.len());
+      assert_eq!(capacity, set.capacity());
+      assert_eq!(2, set.get_count(round));
+      assert_eq!(1, set.get_count(999 * 3 + round));
+      assert_eq!(false, set.contains(1 - round));
+      let mut contents = set.to_vec();
+      contents.sort_unstable();
+      assert_eq!(values, contents);
+    }
+
+    // The space used by removed values can be used again after clearing.
+    set.remove_all(2);
+    assert_eq!(capacity - 1, set.capacity());
+    set.clear();
+    assert_eq!(capacity, set.capacity());
+  }
+
+  #[test]
+  fn minus_counted_set() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(
+      &mut set,
+      vec![-999, 0, 999, -998, 12345, 999, 999, -1000, -12345],
+    );
+    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+
+    set.minus(&other);
+
+    assert_eq!(1, set.get_count(-999));
+    assert_eq!(0, set.get_count(0));
+    assert_eq!(2, set.get_count(999));
+    assert_eq!(0, set.get_count(-998));
+    assert_eq!(1, set.get_count(12345));
+    assert_eq!(0, set.get_count(-1000));
+    assert_eq!(1, set.get_count(-12345));
+    assert_eq!(0, set.get_count(1234));
+    assert_eq!(0, set.get_count(-1234));
+  }
+
+  #[test]
+  fn minus_saturates_at_zero() {
+    let mut set = CountedSet::from(vec![1]);
+    let other = CountedSet::from(vec![1, 1, 1, 1, 1]);
+    set.minus(&other);
+    assert_eq!(0, set.get_count(1));
+    assert_eq!(false, set.contains(1));
+    assert_eq!(true, set.is_empty());
+    assert_eq!(Vec::<i64>::new(), set.to_vec());
+
+    let mut set = CountedSet::new();
+    set.insert_n(1, 3);
+    set.insert_n(2, 3);
+    set.insert_n(3, 3);
+    set.insert_n(4, usize::MAX);
+    set.insert(5);
+    set.remove(5);
+    let mut other = CountedSet::new();
+    other.insert_n(1, 2);
+    other.insert_n(2, 3);
+    other.insert_n(3, usize::MAX);
+    other.insert_n(4, usize::MAX - 1);
+    other.insert_n(5, 7);
+    other.insert(6);
+    other.remove(6);
+
+    set.minus(&other);
+    assert_eq!(vec![1, 0, 0, 1, 0, 0], counts(&set, &[1, 2, 3, 4, 5, 6]));
+    assert_eq!(2, set.len());
+    assert_eq!(2, set.total_count());
+    assert_consistent(&set);
+
+    // Subtracting again removes what's left without wrapping around.
+    set.minus(&other);
+    assert_eq!(true, set.is_empty());
+    assert_eq!(0, set.total_count());
+    assert_consistent(&set);
+  }
+
+  #[test]
+  fn remove_n_saturates_at_zero() {
+    let mut set = CountedSet::from(vec![1]);
+    assert_eq!(0, set.remove_n(1, 5));
+    assert_eq!(false, set.contains(1));
+    assert_eq!(0, set.remove_n(1, usize::MAX));
+    assert_eq!(true, set.is_empty());
+
+    set.insert_n(2, usize::MAX);
+    assert_eq!(1, set.remove_n(2, usize::MAX - 1));
+    assert_eq!(0, set.remove_n(2, usize::MAX));
+    assert_eq!(0, set.len());
+    assert_consistent(&set);
+  }
+
+  #[test]
+  fn intersect_counted_set() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![-999, 0, 999, -998, 12345, -1000, -12345]);
+    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+
+    set.intersect(&other);
+
+    assert_eq!(2, set.get_count(0));
+    assert_eq!(3, set.get_count(-998));
+    assert_eq!(2, set.get_count(999));
+    assert_eq!(2, set.get_count(-1000));
+
+    assert_eq!(0, set.get_count(-999));
+    assert_eq!(0, set.get_count(12345));
+    assert_eq!(0, set.get_count(-12345));
+    assert_eq!(0, set.get_count(1234));
+    assert_eq!(0, set.get_count(-1234));
+  }
+
+  #[test]
+  fn intersect_min_counted_set() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![-999, 0, 999, -998, 12345, -1000, -12345]);
+    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+    set.insert_n(999, 4);
+    set.insert(-1234);
+    set.remove(-1234);
+
+    let mut intersection = set.clone();
+    intersection.intersect(&other);
+    for min_total in 0..10 {
+      let expected = intersection.filtered_by_min_count(min_total);
+      let actual = set.intersect_min(&other, min_total);
+      let mut expected = expected.values_and_counts();
+      let mut actual = actual.values_and_counts();
+      expected.sort();
+      actual.sort();
+      assert_eq!(expected, actual);
+    }
+
+    // A summed count equal to `min_total` is kept.
+    let exact = set.intersect_min(&other, 3);
+    assert_eq!(vec![0, 3, 6, 0], exact.get_counts(&[0, -998, 999, -1000]));
+    assert_eq!(vec![999], set.intersect_min(&other, 4).to_vec());
+    assert_eq!(true, set.intersect_min(&other, 7).is_empty());
+    assert_eq!(true, set.intersect_min(&CountedSet::new(), 0).is_empty());
+    assert_eq!(true, CountedSet::new().intersect_min(&other, 0).is_empty());
+    assert_eq!(7, set.len());
+    assert_eq!(6, other.len());
+
+    let mut huge = CountedSet::new();
+    huge.insert_n(1, usize::MAX - 1);
+    let mut ones = CountedSet::new();
+    ones.insert_n(1, 2);
+    let saturated = huge.intersect_min(&ones, usize::MAX);
+    assert_eq!(usize::MAX, saturated.get_count(1));
+  }
+
+  #[test]
+  fn union_counted_set() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![-999, 0, 999, -998, 12345, -1000, -12345]);
+    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+
+    set.union(&other);
+
+    assert_eq!(1, set.get_count(-999));
+    assert_eq!(2, set.get_count(0));
+    assert_eq!(3, set.get_count(-998));
+    assert_eq!(2, set.get_count(999));
+    assert_eq!(2, set.get_count(-1000));
+    assert_eq!(1, set.get_count(12345));
+    assert_eq!(1, set.get_count(-12345));
+    assert_eq!(1, set.get_count(1234));
+    assert_eq!(1, set.get_count(-1234));
+  }
+
+  #[test]
+  fn symmetric_difference_counted_set() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![-999, 0, 999, 999, -998, 12345, -1000]);
+    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, 1234, 1234]);
+
+    let difference = set.symmetric_difference(&other);
+
+    assert_eq!(5, difference.len());
+    assert_eq!(1, difference.get_count(-999));
+    assert_eq!(1, difference.get_count(12345));
+    assert_eq!(1, difference.get_count(-1000));
+    assert_eq!(1, difference.get_count(-1234));
+    assert_eq!(2, difference.get_count(1234));
+
+    assert_eq!(false, difference.contains(0));
+    assert_eq!(false, difference.contains(999));
+    assert_eq!(false, difference.contains(-998));
+
+    // The inputs are left untouched.
+    assert_eq!(2, set.get_count(999));
+    assert_eq!(2, other.get_count(-998));
+  }
+
+  #[test]
+  fn symmetric_difference_with_removed_values() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![1, 2, 3]);
+    set.remove_all(3);
+    insert_integers(&mut other, vec![3, 3, 3]);
+
+    let difference = set.symmetric_difference(&other);
+
+    assert_eq!(3, difference.len());
+    assert_eq!(1, difference.get_count(1));
+    assert_eq!(1, difference.get_count(2));
+    assert_eq!(3, difference.get_count(3));
+  }
+
+  #[test]
+  fn symmetric_difference_with_empty_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![1, 1, 2]);
+    let empty = CountedSet::new();
+
+    let difference = &set ^ &empty;
+    assert_eq!(2, difference.len());
+    assert_eq!(2, difference.get_count(1));
+    assert_eq!(1, difference.get_count(2));
+
+    let difference = &empty ^ &empty;
+    assert_eq!(true, difference.is_empty());
+  }
+
+  #[test]
+  fn contains_value() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![91, 123456, -1]);
+    assert_eq!(true, set.contains(-1));
+    assert_eq!(true, set.contains(91));
+    assert_eq!(true, set.contains(123456));
+
+    set.clear();
+    assert_eq!(false, set.contains(-1));
+    assert_eq!(false, set.contains(91));
+    assert_eq!(false, set.contains(123456));
+  }
+
+  #[test]
+  fn has_same_elements() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+    assert_eq!(true, set.has_same_elements(&other));
+
+    insert_integers(&mut set, vec![-1, 0, 0, 1]);
+    insert_integers(&mut other, vec![1, 1, 1, 0, -1]);
+    assert_eq!(true, set.has_same_elements(&other));
+    assert_eq!(true, other.has_same_elements(&set));
+
+    other.insert(2);
+    assert_eq!(false, set.has_same_elements(&other));
+    assert_eq!(false, other.has_same_elements(&set));
+  }
+
+  #[test]
+  fn empty_counted_sets_are_equal() {
+    let set = CountedSet::new();
+    let other = CountedSet::new();
+    assert_eq!(set, other);
+  }
+
+  #[test]
+  fn counted_sets_with_same_counts_are_equal() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![91, 91, 123456, 91, -1]);
+    insert_integers(&mut other, vec![-1, 91, 123456, 91, 91]);
+    assert_eq!(set, other);
+
+    set.clear();
+    other.clear();
+    assert_eq!(set, other);
+  }
+
+  #[test]
+  fn counted_sets_with_different_counts_are_not_equal() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![1, 1, 2]);
+    insert_integers(&mut other, vec![1, 2, 2]);
+    assert_ne!(set, other);
+    assert_eq!(true, set.has_same_elements(&other));
+  }
+
+  #[test]
+  fn disjoint_counted_sets_are_not_equal() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+
+    insert_integers(&mut set, vec![1, 2]);
+    insert_integers(&mut other, vec![3, 4]);
+    assert_ne!(set, other);
+    assert_eq!(false, set.has_same_elements(&other));
+
+    let empty = CountedSet::new();
+    assert_ne!(set, empty);
+    assert_ne!(empty, set);
+  }
+
+  #[test]
+  fn equal_counted_sets_have_equal_hashes() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+    assert_eq!(hash(&set), hash(&other));
+
+    insert_integers(&mut set, vec![91, 91, 123456, 91, -1, 0]);
+    insert_integers(&mut other, vec![0, -1, 91, 123456, 91, 91]);
+    assert_eq!(hash(&set), hash(&other));
+
+    other.insert(0);
+    assert_ne!(hash(&set), hash(&other));
+  }
+
+  #[test]
+  fn counted_set_in_hash_set() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+    insert_integers(&mut set, vec![1, 2, 2]);
+    insert_integers(&mut other, vec![2, 1, 2]);
+
+    let mut hash_set = HashSet::new();
+    assert_eq!(true, hash_set.insert(set));
+    assert_eq!(false, hash_set.insert(other));
+    assert_eq!(1, hash_set.len());
+  }
+
+  #[test]
+  fn subset_and_superset() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+    assert_eq!(true, set.is_subset(&other));
+    assert_eq!(true, set.is_superset(&other));
+
+    insert_integers(&mut other, vec![-1, 0, 1]);
+    assert_eq!(true, set.is_subset(&other));
+    assert_eq!(false, set.is_superset(&other));
+    assert_eq!(false, other.is_subset(&set));
+    assert_eq!(true, other.is_superset(&set));
+
+    // Counts are ignored.
+    insert_integers(&mut set, vec![0, 0, 0, 1]);
+    assert_eq!(true, set.is_subset(&other));
+    assert_eq!(true, other.is_superset(&set));
+
+    insert_integers(&mut set, vec![2]);
+    assert_eq!(false, set.is_subset(&other));
+    assert_eq!(false, other.is_superset(&set));
+
+    insert_integers(&mut other, vec![2]);
+    set.insert(-1);
+    assert_eq!(true, set.is_subset(&other));
+    assert_eq!(true, set.is_superset(&other));
+  }
+
+  #[test]
+  fn disjoint_counted_sets() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+    assert_eq!(true, set.is_disjoint(&other));
+
+    insert_integers(&mut set, vec![1, 1, 2, 3]);
+    assert_eq!(true, set.is_disjoint(&other));
+    assert_eq!(true, other.is_disjoint(&set));
+
+    insert_integers(&mut other, vec![4, 5, 5]);
+    assert_eq!(true, set.is_disjoint(&other));
+
+    other.insert(3);
+    assert_eq!(false, set.is_disjoint(&other));
+    assert_eq!(false, other.is_disjoint(&set));
+
+    set.remove_all(3);
+    assert_eq!(true, set.is_disjoint(&other));
+  }
+
+  #[test]
+  fn count_of_value() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![-1, -1, 0, 0, 0, 1, 1, 2]);
+    assert_eq!(2, set.get_count(-1));
+    assert_eq!(3, set.get_count(0));
+    assert_eq!(2, set.get_count(1));
+    assert_eq!(1, set.get_count(2));
+  }
+
+  #[test]
+  fn insert_into_counted_set() {
+    let mut set = CountedSet::new();
+    assert_eq!(1, set.insert(1));
+    assert_eq!(2, set.insert(1));
+    assert_eq!(3, set.insert(1));
+    assert_eq!(1, set.insert(2));
+  }
+
+  #[test]
+  fn try_insert_into_counted_set() {
+    let mut set = CountedSet::new();
+    assert_eq!(Ok(1), set.try_insert(1));
+    assert_eq!(Ok(2), set.try_insert(1));
+    assert_eq!(Ok(1), set.try_insert(-1));
+  }
+
+  #[test]
+  fn insert_returns_counts_of_many_values() {
+    let mut set = CountedSet::new();
+    for value in -500..500 {
+      assert_eq!(1, set.insert(value * 7 % 1000));
+    }
+    for value in -500..500 {
+      assert_eq!(2, set.insert(value * 7 % 1000));
+    }
+    set.remove_all(0);
+    assert_eq!(1, set.insert(0));
+  }
+
+  #[test]
+  fn entry_count_increment_and_remove() {
+    let mut set = CountedSet::new();
+    assert_eq!(0, set.entry(4).count());
+    assert_eq!(true, set.is_empty());
+
+    for (i, value) in [4, -4, 4, 0, 4].iter().enumerate() {
+      let count = set.entry(*value).increment();
+      assert_eq!(set.get_count(*value), count, "iteration {}", i);
+    }
+    assert_eq!(vec![3, 1, 1], counts(&set, &[4, -4, 0]));
+    assert_eq!(3, set.entry(4).count());
+
+    set.entry(4).or_remove();
+    assert_eq!(0, set.entry(4).count());
+    assert_eq!(2, set.len());
+
+    set.entry(4).or_remove();
+    assert_eq!(2, set.len());
+
+    // Incrementing a removed value revives it.
+    assert_eq!(1, set.entry(4).increment());
+    assert_eq!(3, set.len());
+  }
+
+  #[test]
+  fn fallible_set_operations() {
+    let mut set = CountedSet::new();
+    let mut other = CountedSet::new();
+    insert_integers(&mut set, vec![1, 1, 2, 3]);
+    insert_integers(&mut other, vec![1, 3, 3, 4]);
+
+    assert_eq!(Ok(()), set.try_union(&other));
+    assert_eq!(vec![3, 1, 3, 1], counts(&set, &[1, 2, 3, 4]));
+
+    assert_eq!(Ok(()), set.try_minus(&other));
+    assert_eq!(vec![2, 1, 1, 0], counts(&set, &[1, 2, 3, 4]));
+
+    assert_eq!(Ok(()), set.try_intersect(&other));
+    assert_eq!(vec![3, 0, 3, 0], counts(&set, &[1, 2, 3, 4]));
+
+    assert_eq!(Ok(()), set.try_clear());
+    assert_eq!(true, set.is_empty());
+  }
+
+  #[test]
+  fn insert_n_into_counted_set() {
+    let mut set = CountedSet::new();
+    assert_eq!(0, set.insert_n(8, 0));
+    assert_eq!(true, set.is_empty());
+
+    assert_eq!(10_000, set.insert_n(8, 10_000));
+    assert_eq!(10_001, set.insert(8));
+    assert_eq!(4, set.insert_n(-8, 4));
+    assert_eq!(10_001, set.insert_n(8, 0));
+    assert_eq!(2, set.len());
+    assert_eq!(10_005, set.total_count());
+
+    set.remove_all(8);
+    assert_eq!(2, set.insert_n(8, 2));
+    assert_eq!(2, set.len());
+  }
+
+  #[test]
+  fn remove_n_from_counted_set() {
+    let mut set = CountedSet::new();
+    assert_eq!(0, set.remove_n(3, 2));
+    assert_eq!(true, set.is_empty());
+
+    set.insert_n(3, 10);
+    set.insert(-3);
+    assert_eq!(10, set.remove_n(3, 0));
+    assert_eq!(6, set.remove_n(3, 4));
+    assert_eq!(1, set.remove_n(3, 5));
+    assert_eq!(2, set.len());
+
+    assert_eq!(0, set.remove_n(3, usize::MAX));
+    assert_eq!(false, set.contains(3));
+    assert_eq!(1, set.len());
+    assert_eq!(0, set.remove_n(3, 1));
+    assert_eq!(1, set.len());
+
+    assert_eq!(0, set.remove_n(-3, 1));
+    assert_eq!(true, set.is_empty());
+    assert_eq!(2, set.insert_n(3, 2));
+  }
+
+  #[test]
+  fn entries_iter_is_sorted_by_value() {
+    let mut set = CountedSet::new();
+    assert_eq!(None, set.entries_iter().next());
+
+    let mut state: i64 = 7;
+    for _ in 0..2000 {
+      state = state
+        .wrapping_mul(6_364_136_223_846_793_005)
+        .wrapping_add(1);
+      set.insert(state >> 52);
+    }
+    set.insert_n(i64::MIN, 2);
+    set.insert(i64::MAX);
+    for value in (-300..300).step_by(7) {
+      set.remove_all(value);
+    }
+
+    let entries: Vec<(i64, usize)> = set.entries_iter().collect();
+    assert_eq!(set.len(), entries.len());
+    assert_eq!(true, entries.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!((i64::MIN, 2), entries[0]);
+    assert_eq!((i64::MAX, 1), entries[entries.len() - 1]);
+    for (value, count) in &entries {
+      assert_eq!(set.get_count(*value), *count);
+    }
+
+    let mut iter = set.entries_iter();
+    assert_eq!(set.len(), iter.len());
+    iter.next();
+    assert_eq!(set.len() - 1, iter.len());
+    assert_eq!(set.len() - 1, iter.by_ref().count());
+    assert_eq!(None, iter.next());
+
+    // Ascending inserts build a tree that only has right children.
+    let set = CountedSet::from((0..100).collect::<Vec<i64>>());
+    assert_eq!(
+      (0..100)
+        .map(|value| (value, 1))
+        .collect::<Vec<(i64, usize)>>(),
+      set.entries_iter().collect::<Vec<(i64, usize)>>()
+    );
+  }
+
+  #[test]
+  fn iterate_in_both_directions() {
+    let mut set = CountedSet::new();
+    assert_eq!(0, set.iter().len());
+    assert_eq!(None, set.iter_with_counts().next_back());
+
+    set.insert_n(i64::MIN, 2);
+    set.insert_n(-1, 5);
+    set.insert_n(0, 2);
+    set.insert_n(i64::MAX, 1);
+    set.insert_n(9, 3);
+    set.remove_all(9);
+
+    let forward: Vec<(i64, usize)> = set.iter_with_counts().collect();
+    assert_eq!(vec![(-1, 5), (i64::MIN, 2), (0, 2), (i64::MAX, 1)], forward);
+    let backward: Vec<(i64, usize)> = set.iter_with_counts().rev().collect();
+    assert_eq!(forward.iter().rev().copied().collect::<Vec<_>>(), backward);
+    assert_eq!(
+      vec![-1, i64::MIN, 0, i64::MAX],
+      set.iter().collect::<Vec<i64>>()
+    );
+
+    // Both ends can be taken from the same iterator.
+    let mut iter = set.iter();
+    assert_eq!(4, iter.len());
+    assert_eq!(Some(-1), iter.next());
+    assert_eq!(Some(i64::MAX), iter.next_back());
+    assert_eq!(2, iter.len());
+    assert_eq!(Some(0), iter.next_back());
+    assert_eq!(Some(i64::MIN), iter.next());
+    assert_eq!(0, iter.len());
+    assert_eq!(None, iter.next());
+    assert_eq!(None, iter.next_back());
+
+    let mut counts: Vec<usize> =
+      set.iter_with_counts().map(|(_, count)| count).collect();
+    let copy = counts.clone();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    assert_eq!(copy, counts);
+  }
+
+  #[test]
+  fn lazy_difference_and_intersection() {
+    let mut set = CountedSet::new();
+    set.insert_n(i64::MIN, 3);
+    set.insert_n(-1, 1);
+    set.insert_n(0, 2);
+    set.insert_n(7, 1);
+    set.insert_n(i64::MAX, 4);
+    set.remove_all(7);
+    let mut other = CountedSet::new();
+    other.insert_n(i64::MIN, 3);
+    other.insert_n(0, 1);
+    other.insert_n(7, 5);
+    other.insert_n(i64::MAX, 5);
+    other.insert_n(8, 1);
+
+    let sorted = |values: Vec<i64>| {
+      let mut values = values;
+      values.sort_unstable();
+      values
+    };
+    assert_eq!(vec![-1, 0], sorted(set.difference(&other).collect()));
+    assert_eq!(
+      vec![i64::MIN, 0, i64::MAX],
+      sorted(set.intersection(&other).collect())
+    );
+    assert_eq!(
+      vec![7, 8, i64::MAX],
+      sorted(other.difference(&set).collect())
+    );
+    assert_eq!(
+      sorted(set.intersection(&other).collect()),
+      sorted(other.intersection(&set).collect())
+    );
+
+    // They agree with the methods that modify the set.
+    let mut minus = set.clone();
+    minus.minus(&other);
+    assert_eq!(
+      sorted(minus.to_vec()),
+      sorted(set.difference(&other).collect())
+    );
+    let mut intersect = set.clone();
+    intersect.intersect(&other);
+    assert_eq!(
+      sorted(intersect.to_vec()),
+      sorted(set.intersection(&other).collect())
+    );
+
+    let empty = CountedSet::new();
+    assert_eq!(None, empty.difference(&set).next());
+    assert_eq!(None, empty.intersection(&set).next());
+    assert_eq!(None, set.intersection(&empty).next());
+    assert_eq!(
+      sorted(set.to_vec()),
+      sorted(set.difference(&empty).collect())
+    );
+    assert_eq!(None, set.difference(&set).next());
+    assert_eq!((0, Some(5)), set.difference(&other).size_hint());
+  }
+
+  #[test]
+  fn extend_with_counts() {
+    let mut set = CountedSet::new();
+    set.extend(Vec::<(i64, usize)>::new());
+    assert_eq!(true, set.is_empty());
+
+    set.extend(vec![(i64::MIN, 2), (0, 0), (i64::MAX, 1), (i64::MIN, 3)]);
+    assert_eq!(2, set.len());
+    assert_eq!(5, set.get_count(i64::MIN));
+    assert_eq!(1, set.get_count(i64::MAX));
+    assert_eq!(false, set.contains(0));
+
+    let map: HashMap<i64, usize> = (0..1000).map(|v| (v, v as usize)).collect();
+    let mut copy = CountedSet::new();
+    copy.extend(map.iter().map(|(&value, &count)| (value, count)));
+    assert_eq!(CountedSet::from(map), copy);
+
+    set.extend(vec![(7, usize::MAX), (7, 1)]);
+    assert_eq!(usize::MAX, set.get_count(7));
+  }
+
+  #[test]
+  fn insert_n_saturates() {
+    let mut set = CountedSet::new();
+    assert_eq!(usize::MAX - 1, set.insert_n(1, usize::MAX - 1));
+    assert_eq!(usize::MAX, set.insert_n(1, 5));
+    assert_eq!(usize::MAX, set.insert(1));
+  }
+
+  #[test]
+  fn extend_counted_set_from_slice() {
+    let mut set = CountedSet::new();
+    set.extend_from_slice(&[]);
+    assert_eq!(true, set.is_empty());
+
+    set.insert(5);
+    set.extend_from_slice(&[5, -5, 0, 5, 0]);
+    assert_eq!(vec![3, 1, 2], counts(&set, &[5, -5, 0]));
+    assert_eq!(3, set.len());
+
+    let values: Vec<i64> =
+      (0..20_000).map(|int| (int * 7919) % 10_007).collect();
+    let mut expected = CountedSet::new();
+    insert_integers(&mut expected, values.clone());
+    let mut set = CountedSet::with_capacity(4);
+    set.extend_from_slice(&values);
+    assert_eq!(expected, set);
+    assert_eq!(10_007, set.len());
+    assert_eq!(20_000, set.total_count());
+  }
+
+  #[test]
+  fn remove_from_counted_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![0, 0]);
+    assert_eq!(1, set.remove(0));
+    assert_eq!(0, set.remove(0));
+    assert_eq!(0, set.remove(0));
+  }
+
+  #[test]
+  fn remove_checked_from_counted_set() {
+    let mut set = CountedSet::new();
+    assert_eq!(None, set.remove_checked(7));
+
+    insert_integers(&mut set, vec![7, 7, 7, 2]);
+    assert_eq!(Some(2), set.remove_checked(7));
+    assert_eq!(Some(0), set.remove_checked(2));
+    assert_eq!(None, set.remove_checked(2));
+    assert_eq!(1, set.len());
+
+    assert_eq!(Some(1), set.remove_checked(7));
+    assert_eq!(Some(0), set.remove_checked(7));
+    assert_eq!(None, set.remove_checked(7));
+    assert_eq!(true, set.is_empty());
+
+    assert_eq!(1, set.insert(7));
+    assert_eq!(1, set.len());
+  }
+
+  #[test]
+  fn remove_from_value_with_large_count() {
+    let mut set = CountedSet::new();
+    for _ in 0..20_000 {
+      set.insert(3);
+    }
+    set.insert(4);
+
+    for expected in (0..20_000).rev() {
+      assert_eq!(expected, set.remove(3));
+    }
+    assert_eq!(false, set.contains(3));
+    assert_eq!(vec![4], set.to_vec());
+  }
+
+  #[test]
+  fn remove_all_from_counted_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![0, 0]);
+    assert_eq!(true, set.remove_all(0));
+    assert_eq!(false, set.remove_all(0));
+  }
+
+  #[test]
+  fn counted_set_to_vec() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![91, 91, 123456, -1, 91, -1]);
+    let output = set.to_vec();
+    assert_eq!(3, output.len());
+    assert_eq!(91, output[0]);
+    assert_eq!(-1, output[1]);
+    assert_eq!(123456, output[2]);
+  }
+
+  #[test]
+  fn large_counted_set_to_vec() {
+    let mut set = CountedSet::new();
+
+    let integer_map: HashMap<i64, usize> =
+      [(-1, 4), (-9876, 3), (-12345, 1)].iter().cloned().collect();
+    let range_map: HashMap<Range<i64>, usize> =
+      [(0..1000, 2)].iter().cloned().collect();
+    let mut vec: Vec<i64> = vec![];
+    add_integers_from_map_to_vec(&mut vec, integer_map);
+    add_integers_from_range_map_to_vec(&mut vec, range_map);
+    let counted_set_length = vec.len() - 1005; // Duplicates are removed in CountedSet
+
+    insert_integers(&mut set, vec);
+    let output = set.to_vec();
+    assert_eq!(counted_set_length, output.len());
+    assert_eq!(-1, output[0]);
+    assert_eq!(-9876, output[1]);
+    assert_eq!(-12345, output[counted_set_length - 1]);
+  }
+
+  #[test]
+  fn u32_values() {
+    let mut set = CountedSet::new();
+    assert_eq!(Vec::<u32>::new(), set.to_vec_u32());
+
+    let values = [0, 1, i32::MAX as u32, i32::MAX as u32 + 1, u32::MAX];
+    for (i, value) in values.iter().enumerate() {
+      for count in 1..=i + 1 {
+        assert_eq!(count, set.insert_u32(*value));
+      }
+    }
+    for (i, value) in values.iter().enumerate() {
+      assert_eq!(true, set.contains_u32(*value));
+      assert_eq!(i + 1, set.get_count_u32(*value));
+      assert_eq!(i + 1, set.get_count(i64::from(*value)));
+    }
+    assert_eq!(false, set.contains(-1));
+    assert_eq!(false, set.contains(i64::from(i32::MIN)));
+    assert_eq!(false, set.contains_u32(2));
+    assert_eq!(0, set.get_count_u32(2));
+
+    let mut expected = values.to_vec();
+    expected.reverse();
+    assert_eq!(expected, set.to_vec_u32());
+
+    // Values that don't fit in a `u32` are skipped.
+    set.insert_n(-1, 10);
+    set.insert_n(i64::from(u32::MAX) + 1, 10);
+    set.insert_n(i64::MIN, 10);
+    assert_eq!(expected, set.to_vec_u32());
+    assert_eq!(8, set.len());
+  }
+
+  #[test]
+  fn large_counted_set_to_vec_sorted() {
+    let mut set = CountedSet::new();
+    for int in (0..1000).rev() {
+      set.insert_n(int, 2);
+    }
+    set.insert_n(-1, 4);
+    set.insert_n(-9876, 3);
+    set.insert(-12345);
+
+    let mut expected = vec![-1, -9876];
+    expected.extend(0..1000);
+    expected.push(-12345);
+    assert_eq!(expected, set.to_vec_sorted());
+
+    let mut values = set.to_vec();
+    values.sort_unstable();
+    let mut sorted = set.to_vec_sorted();
+    sorted.sort_unstable();
+    assert_eq!(values, sorted);
+
+    let copy = CountedSet::from(HashMap::<i64, usize>::from(set.clone()));
+    assert_eq!(set.to_vec_sorted(), copy.to_vec_sorted());
+    assert_eq!(Vec::<i64>::new(), CountedSet::new().to_vec_sorted());
+  }
+
+  #[test]
+  fn empty_counted_set_to_vec() {
+    let mut set = CountedSet::new();
+    assert_eq!(Vec::<i64>::new(), set.to_vec());
+
+    insert_integers(&mut set, vec![1, 2]);
+    set.clear();
+    assert_eq!(Vec::<i64>::new(), set.to_vec());
+  }
+
+  #[test]
+  fn extreme_values_are_stored_like_any_other() {
+    let extremes = [i64::MIN, 0, i64::MAX];
+    let mut set = CountedSet::new();
+    assert_eq!(false, extremes.iter().any(|&value| set.contains(value)));
+
+    set.insert_n(i64::MIN, 4);
+    set.insert_n(0, 2);
+    set.insert(i64::MAX);
+    for value in -5..5 {
+      set.insert(value);
+    }
+    assert_eq!(12, set.len());
+    assert_eq!(true, extremes.iter().all(|&value| set.contains(value)));
+    assert_eq!(vec![4, 3, 1], set.get_counts(&extremes));
+    assert_eq!(i64::MIN, set.to_vec()[0]);
+    assert_eq!(0, set.to_vec()[1]);
+    assert_eq!(vec![i64::MIN], set.values_in_range(..=i64::MIN).to_vec());
+    assert_eq!(vec![i64::MAX], set.values_in_range(i64::MAX..).to_vec());
+
+    // Removing and re-inserting the values reuses their nodes.
+    set.remove_all(i64::MIN);
+    set.remove_all(0);
+    set.remove(i64::MAX);
+    assert_eq!(false, extremes.iter().any(|&value| set.contains(value)));
+    assert_eq!(vec![0, 0, 0], set.get_counts(&extremes));
+    assert_eq!(
+      false,
+      set.to_vec().iter().any(|value| extremes.contains(value))
+    );
+    set.insert(0);
+    assert_eq!(1, set.get_count(0));
+
+    let copy = CountedSet::from_bytes(&set.to_bytes()).unwrap();
+    assert_eq!(set, copy);
+
+    let mut other = CountedSet::from(vec![i64::MIN, i64::MAX, i64::MAX]);
+    other.union(&set);
+    assert_eq!(vec![1, 1, 2], other.get_counts(&extremes));
+    other.minus(&CountedSet::from(vec![0]));
+    assert_eq!(vec![1, 0, 2], other.get_counts(&extremes));
+  }
+
+  #[test]
+  fn to_vec_into_reuses_buffer() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![91, 91, 123456, -1, 91, -1, 7]);
+    set.remove(7);
+
+    let mut buf = vec![0; 100];
+    let capacity = buf.capacity();
+    let pointer = buf.as_ptr();
+    set.to_vec_into(&mut buf);
+    assert_eq!(vec![91, -1, 123456], buf);
+    assert_eq!(capacity, buf.capacity());
+    assert_eq!(pointer, buf.as_ptr());
+
+    set.clear();
+    set.to_vec_into(&mut buf);
+    assert_eq!(Vec::<i64>::new(), buf);
+
+    for int in 0..1000 {
+      set.insert_n(int, (int % 2 + 1) as usize);
+    }
+    set.to_vec_into(&mut buf);
+    assert_eq!(1000, buf.len());
+    assert_eq!(true, buf[..500].iter().all(|int| int % 2 == 1));
+    buf.sort_unstable();
+    assert_eq!((0..1000).collect::<Vec<i64>>(), buf);
+  }
+
+  #[test]
+  fn copy_ints_into_reports_too_small_buffers() {
+    let set = CountedSet::from(vec![5, 5, 6, 7, 7, 7]);
+    let mut buf = [0i64; 4];
+    let mut count = usize::MAX;
+
+    let result = unsafe {
+      tsearch_countedset_copy_ints_into(
+        set.raw,
+        buf.as_mut_ptr(),
+        2,
+        &mut count,
+      )
+    };
+    assert_eq!(0, result);
+    assert_eq!(3, count);
+    assert_eq!([0, 0, 0, 0], buf);
+
+    let result = unsafe {
+      tsearch_countedset_copy_ints_into(
+        set.raw,
+        buf.as_mut_ptr(),
+        4,
+        &mut count,
+      )
+    };
+    assert_eq!(1, result);
+    assert_eq!(3, count);
+    assert_eq!([7, 5, 6, 0], buf);
+
+    let empty = CountedSet::new();
+    let result = unsafe {
+      tsearch_countedset_copy_ints_into(
+        empty.raw,
+        ptr::null_mut(),
+        0,
+        &mut count,
+      )
+    };
+    assert_eq!(1, result);
+    assert_eq!(0, count);
+  }
+
+  #[test]
+  fn very_large_counted_set_to_vec() {
+    let mut set = CountedSet::new();
+    for int in 0..20_000 {
+      set.insert(int);
+      if int % 10 == 0 {
+        set.insert(int);
+      }
+    }
+
+    // Copy the values out repeatedly so that mismatched allocations or
+    // frees are caught by allocators and sanitizers that check them.
+    for _ in 0..10 {
+      let output = set.to_vec();
+      assert_eq!(20_000, output.len());
+      assert_eq!(true, output[..2_000].iter().all(|int| int % 10 == 0));
+      assert_eq!(true, output[2_000..].iter().all(|int| int % 10 != 0));
+    }
+
+    let mut output = set.to_vec();
+    output.sort_unstable();
+    assert_eq!((0..20_000).collect::<Vec<i64>>(), output);
+  }
+
+  #[test]
+  fn most_common_values() {
+    let mut set = CountedSet::new();
+    assert_eq!(Vec::<(i64, usize)>::new(), set.most_common(3));
+
+    insert_integers(&mut set, vec![5, 5, 5, -2, -2, 7, 7, 3, 3, 1, 0]);
+    assert_eq!(Vec::<(i64, usize)>::new(), set.most_common(0));
+    assert_eq!(vec![(5, 3)], set.most_common(1));
+    assert_eq!(vec![(5, 3), (-2, 2), (3, 2)], set.most_common(3));
+    assert_eq!(
+      vec![(5, 3), (-2, 2), (3, 2), (7, 2), (0, 1), (1, 1)],
+      set.most_common(6)
+    );
+    assert_eq!(set.most_common(6), set.most_common(100));
+  }
+
+  #[test]
+  fn most_common_values_of_large_counted_set() {
+    let mut set = CountedSet::new();
+    for int in 0..1000 {
+      for _ in 0..(int % 10) {
+        set.insert(int);
+      }
+    }
+
+    let most_common = set.most_common(150);
+    assert_eq!(150, most_common.len());
+    assert_eq!((9, 9), most_common[0]);
+    assert_eq!((999, 9), most_common[99]);
+    assert_eq!((8, 8), most_common[100]);
+    assert_eq!((498, 8), most_common[149]);
+  }
+
+  #[test]
+  fn retain_values() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![-3, -3, 0, 5, 5, 5, 8, 9, 9]);
+
+    set.retain(|value, _| value >= 0);
+    assert_eq!(vec![0, 1, 3, 1, 2], counts(&set, &[-3, 0, 5, 8, 9]));
+
+    set.retain(|_, count| count >= 2);
+    assert_eq!(vec![0, 0, 3, 0, 2], counts(&set, &[-3, 0, 5, 8, 9]));
+    assert_eq!(2, set.len());
+    assert_eq!(false, set.contains(0));
+
+    set.insert(0);
+    assert_eq!(1, set.get_count(0));
+  }
+
+  #[test]
+  fn retain_top() {
+    let mut set = CountedSet::from(vec![9, 9, 9, -3, -3, 4, 4, 7, 7, 1, 8]);
+    set.retain_top(4);
+    assert_eq!(vec![(9, 3), (-3, 2), (4, 2), (7, 2)], set.most_common(10));
+    assert_consistent(&set);
+
+    // Ties at the threshold keep the smallest values.
+    set.retain_top(2);
+    assert_eq!(vec![(9, 3), (-3, 2)], set.most_common(10));
+    assert_consistent(&set);
+
+    set.retain_top(2);
+    assert_eq!(2, set.len());
+    set.retain_top(usize::MAX);
+    assert_eq!(2, set.len());
+
+    set.retain_top(0);
+    assert_eq!(true, set.is_empty());
+    set.retain_top(0);
+    assert_eq!(true, set.is_empty());
+
+    let mut set = CountedSet::new();
+    set.extend_from_slice(&(0..1000).rev().collect::<Vec<i64>>());
+    set.insert(500);
+    set.retain_top(10);
+    assert_eq!(vec![500, 0, 1, 2, 3, 4, 5, 6, 7, 8], set.to_vec_sorted());
+    assert_eq!(2, set.get_count(500));
+    assert_consistent(&set);
+  }
+
+  #[test]
+  fn retain_all_or_no_values() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![1, 1, 2, 3, 3, 3]);
+    let copy = set.clone();
+
+    set.retain(|_, _| true);
+    assert_eq!(copy, set);
+
+    let mut calls = 0;
+    set.retain(|_, _| {
+      calls += 1;
+      false
+    });
+    assert_eq!(3, calls);
+    assert_eq!(true, set.is_empty());
+    assert_eq!(Vec::<i64>::new(), set.to_vec());
+  }
+
+  #[test]
+  fn total_count_of_counted_set() {
+    let mut set = CountedSet::new();
+    assert_eq!(0, set.total_count());
+
+    insert_integers(&mut set, vec![4, 4, 4, -9, 0, 0, 12]);
+    assert_eq!(7, set.total_count());
+
+    set.remove(4);
+    set.remove(12);
+    assert_eq!(5, set.total_count());
+
+    set.remove(12);
+    set.insert(12);
+    assert_eq!(6, set.total_count());
+
+    set.clear();
+    assert_eq!(0, set.total_count());
+  }
+
+  #[test]
+  fn total_count_of_large_counted_set() {
+    let mut set = CountedSet::new();
+    for int in 0..20_000 {
+      for _ in 0..(int % 3) {
+        set.insert(int);
+      }
+    }
+
+    let expected: usize = (0..20_000).map(|int| int % 3).sum();
+    assert_eq!(expected, set.total_count());
+    assert_eq!(
+      expected,
+      set
+        .to_vec()
+        .into_iter()
+        .map(|int| set.get_count(int))
+        .sum::<usize>()
+    );
+  }
+
+  #[test]
+  fn clone_counted_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![91, 91, 123456, 91, -1]);
+
+    let copy = set.clone();
+
+    set.clear();
+    assert_eq!(true, set.is_empty());
+
+    assert_eq!(3, copy.get_count(91));
+    assert_eq!(1, copy.get_count(123456));
+    assert_eq!(1, copy.get_count(-1));
+  }
+
+  #[test]
+  fn clone_outlives_original() {
+    let mut set = CountedSet::new();
+    for value in -50..50 {
+      set.insert_n(value, (value + 51) as usize);
+    }
+    set.remove_all(0);
+    let expected_vec = set.to_vec();
+    let expected_counts: Vec<(i64, usize)> = set.iter_with_counts().collect();
+    let expected_bytes = set.to_bytes();
+
+    let copy = set.clone();
+    let mut copy_into = CountedSet::new();
+    copy_into.clone_from(&set);
+
+    // Growing the original's buffer moves it, and clearing it overwrites
+    // its nodes, before the buffer is freed.
+    set.extend_from_slice(&(1000..2000).collect::<Vec<i64>>());
+    set.clear();
+    set.insert_n(7, 1000);
+    drop(set);
+
+    for copy in &[copy, copy_into] {
+      assert_eq!(expected_vec, copy.to_vec());
+      assert_eq!(
+        expected_counts,
+        copy.iter_with_counts().collect::<Vec<(i64, usize)>>()
+      );
+      assert_eq!(expected_bytes, copy.to_bytes());
+      assert_eq!(99, copy.entries_iter().count());
+      assert_consistent(copy);
+    }
+  }
+
+  #[test]
+  fn append_sets() {
+    let mut set = CountedSet::new();
+    let mut partials: Vec<CountedSet> = (0..4)
+      .map(|i| CountedSet::from((i..i * 20).collect::<Vec<i64>>()))
+      .collect();
+    let expected = CountedSet::union_all(&partials);
+    for partial in &mut partials {
+      set.append(partial);
+      assert_eq!(true, partial.is_empty());
+      assert_consistent(partial);
+    }
+    assert_eq!(expected, set);
+    assert_consistent(&set);
+
+    // The emptied sets can be reused.
+    partials[0].insert(-1);
+    set.append(&mut partials[0]);
+    assert_eq!(1, set.get_count(-1));
+    assert_eq!(true, partials[0].is_empty());
+
+    let mut empty = CountedSet::new();
+    let before = set.clone();
+    set.append(&mut empty);
+    assert_eq!(before, set);
+    empty.append(&mut set);
+    assert_eq!(before, empty);
+    assert_eq!(true, set.is_empty());
+
+    let mut saturated = CountedSet::new();
+    saturated.insert_n(5, usize::MAX);
+    let mut other = CountedSet::from(vec![5, 6]);
+    other.append(&mut saturated);
+    assert_eq!(vec![usize::MAX, 1], other.get_counts(&[5, 6]));
+  }
+
+  #[test]
+  fn map_values() {
+    let set = CountedSet::from(vec![-3, 1, 1, 2, 3, 3, 3]);
+
+    let mut calls = 0;
+    let shifted = set.map_values(|value| {
+      calls += 1;
+      value.wrapping_add(i64::MAX)
+    });
+    assert_eq!(4, calls);
+    assert_eq!(4, shifted.len());
+    assert_eq!(
+      vec![1, 2, 1, 3],
+      shifted.get_counts(&[
+        i64::MAX - 3,
+        i64::MAX.wrapping_add(1),
+        i64::MAX.wrapping_add(2),
+        i64::MAX.wrapping_add(3),
+      ])
+    );
+
+    // Values mapped to the same value have their counts added together.
+    let merged = set.map_values(|value| value.abs());
+    assert_eq!(3, merged.len());
+    assert_eq!(vec![2, 1, 4], merged.get_counts(&[1, 2, 3]));
+    assert_eq!(set.total_count(), merged.total_count());
+    assert_consistent(&merged);
+
+    let single = set.map_values(|_| 0);
+    assert_eq!(vec![(0, 7)], single.most_common(2));
+    assert_eq!(4, set.len());
+    assert_eq!(vec![1, 2, 3], set.get_counts(&[-3, 1, 3]));
+
+    let mut saturated = CountedSet::new();
+    saturated.insert_n(1, usize::MAX);
+    saturated.insert_n(2, 5);
+    assert_eq!(usize::MAX, saturated.map_values(|_| 9).get_count(9));
+
+    assert_eq!(true, CountedSet::new().map_values(|value| value).is_empty());
+  }
+
+  #[test]
+  fn try_new_and_try_clone() {
+    let mut set = CountedSet::try_new().unwrap();
+    assert_eq!(true, set.is_empty());
+    assert_eq!(true, set.capacity() > 0);
+
+    let empty = set.try_clone().unwrap();
+    assert_eq!(true, empty.is_empty());
+
+    set.extend_from_slice(&[5, 5, -5]);
+    set.remove_all(-5);
+    let mut copy = set.try_clone().unwrap();
+    assert_eq!(set, copy);
+    assert_eq!(false, copy.contains(-5));
+
+    copy.insert(6);
+    assert_eq!(false, set.contains(6));
+    assert_eq!(true, empty.is_empty());
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn serialize_counted_set() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![91, 91, 123456, 91, -1]);
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(r#"{"-1":1,"91":3,"123456":1}"#, json);
+
+    let empty = CountedSet::new();
+    assert_eq!("{}", serde_json::to_string(&empty).unwrap());
+  }
+
+  #[test]
+  fn contains_all_values() {
+    let mut set = CountedSet::new();
+    for int in (0..1000).step_by(2) {
+      set.insert(int);
+    }
+    set.remove_all(10);
+
+    assert_eq!(true, set.contains_all(&[]));
+    assert_eq!(true, set.contains_all(&[0]));
+    assert_eq!(true, set.contains_all(&[998, 0, 500, 0]));
+    assert_eq!(false, set.contains_all(&[0, 1]));
+    assert_eq!(false, set.contains_all(&[10]));
+    assert_eq!(false, set.contains_all(&[-2, 2]));
+
+    let values: Vec<i64> = (0..1000).step_by(2).filter(|i| *i != 10).collect();
+    assert_eq!(true, set.contains_all(&values));
+
+    assert_eq!(true, CountedSet::new().contains_all(&[]));
+    assert_eq!(false, CountedSet::new().contains_all(&[0]));
+  }
+
+  #[test]
+  fn get_counts_of_values() {
+    let mut set = CountedSet::new();
+    for int in 0..100 {
+      set.insert_n(int, (int % 5) as usize);
+    }
+    set.remove_all(3);
+
+    assert_eq!(Vec::<usize>::new(), set.get_counts(&[]));
+    assert_eq!(
+      vec![0, 1, 2, 0, 4, 4, 1],
+      set.get_counts(&[0, 1, 2, 3, 4, 99, 1])
+    );
+    assert_eq!(vec![0, 0], set.get_counts(&[-1, 100]));
+
+    let values: Vec<i64> = (-10..110).rev().collect();
+    let expected: Vec<usize> =
+      values.iter().map(|value| set.get_count(*value)).collect();
+    assert_eq!(expected, set.get_counts(&values));
+
+    assert_eq!(vec![0], CountedSet::new().get_counts(&[1]));
+  }
+
+  #[test]
+  fn max_and_min_by_count() {
+    let mut set = CountedSet::new();
+    for int in -50i64..50 {
+      set.insert_n(int, (int.abs() % 10 + 1) as usize);
+    }
+    // -49, -39, ..., 49 have the highest count, and -50, -40, ..., 40 the
+    // lowest.
+    assert_eq!(Some((-49, 10)), set.max_by_count());
+    assert_eq!(Some((-50, 1)), set.min_by_count());
+
+    set.remove_all(-49);
+    set.remove_all(-50);
+    assert_eq!(Some((-39, 10)), set.max_by_count());
+    assert_eq!(Some((-40, 1)), set.min_by_count());
+
+    set.insert_n(100, usize::MAX);
+    assert_eq!(Some((100, usize::MAX)), set.max_by_count());
+  }
+
+  #[test]
+  fn max_and_min_by_count_of_small_sets() {
+    let mut set = CountedSet::new();
+    assert_eq!(None, set.max_by_count());
+    assert_eq!(None, set.min_by_count());
+
+    set.insert(5);
+    assert_eq!(Some((5, 1)), set.max_by_count());
+    assert_eq!(Some((5, 1)), set.min_by_count());
+
+    set.remove(5);
+    assert_eq!(None, set.max_by_count());
+    assert_eq!(None, set.min_by_count());
+  }
+
+  #[test]
+  fn filtered_by_min_count() {
+    let mut set = CountedSet::new();
+    for int in 0..100 {
+      set.insert_n(int, (int % 10) as usize);
+    }
+    set.remove_all(9);
+
+    let filtered = set.filtered_by_min_count(8);
+    assert_eq!(19, filtered.len());
+    assert_eq!(vec![8, 9, 0, 0, 0], counts(&filtered, &[8, 19, 9, 7, 0]));
+    assert_eq!(89, set.len());
+
+    assert_eq!(set, set.filtered_by_min_count(0));
+    assert_eq!(set, set.filtered_by_min_count(1));
+    assert_eq!(true, set.filtered_by_min_count(10).is_empty());
+    assert_eq!(true, set.filtered_by_min_count(usize::MAX).is_empty());
+    assert_eq!(true, CountedSet::new().filtered_by_min_count(1).is_empty());
+  }
+
+  #[test]
+  fn merge_with_max_and_min() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![1, 1, 2, 3, 3, 3, 5]);
+    set.remove_all(5);
+    let mut other = CountedSet::new();
+    insert_integers(&mut other, vec![1, 3, 3, 4, 4, 5]);
+
+    let mut max = set.clone();
+    max.merge_with(&other, std::cmp::max);
+    assert_eq!(vec![2, 1, 3, 2, 1], counts(&max, &[1, 2, 3, 4, 5]));
+    assert_eq!(5, max.len());
+
+    let mut min = set.clone();
+    min.merge_with(&other, std::cmp::min);
+    assert_eq!(vec![1, 0, 2, 0, 0], counts(&min, &[1, 2, 3, 4, 5]));
+    assert_eq!(2, min.len());
+
+    let mut cleared = set.clone();
+    cleared.merge_with(&CountedSet::new(), |_, _| 0);
+    assert_eq!(true, cleared.is_empty());
+  }
+
+  #[test]
+  fn merge_with_expresses_set_operations() {
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![-2, 0, 0, 7, 7, 7, 9]);
+    let mut other = CountedSet::new();
+    insert_integers(&mut other, vec![0, 7, 8, 8, 9, 9, 9]);
+
+    let (mut expected, mut merged) = (set.clone(), set.clone());
+    expected.union(&other);
+    merged.merge_with(&other, |a, b| a.saturating_add(b));
+    assert_eq!(expected, merged);
+
+    let (mut expected, mut merged) = (set.clone(), set.clone());
+    expected.intersect(&other);
+    merged.merge_with(&other, |a, b| if a > 0 && b > 0 { a + b } else { 0 });
+    assert_eq!(expected, merged);
+
+    let (mut expected, mut merged) = (set.clone(), set.clone());
+    expected.minus(&other);
+    merged.merge_with(&other, |a, b| a.saturating_sub(b));
+    assert_eq!(expected, merged);
+  }
+
+  #[test]
+  fn debug_counted_set() {
+    assert_eq!("CountedSet {}", format!("{:?}", CountedSet::new()));
+    assert_eq!("CountedSet {}", format!("{:#?}", CountedSet::new()));
+
+    let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![3, -7, 3, 0, 3, 12]);
+    set.remove_all(0);
+    assert_eq!("CountedSet { -7: 1, 3: 3, 12: 1 }", format!("{:?}", set));
+    assert_eq!(
+      "CountedSet {\n    -7: 1,\n    3: 3,\n    12: 1,\n}",
+      format!("{:#?}", set)
+    );
+
+    let mut large = CountedSet::new();
+    for int in (0..250).rev() {
+      large.insert(int);
+    }
+    let debug = format!("{:?}", large);
+    assert_eq!(true, debug.starts_with("CountedSet { 0: 1, 1: 1, 2: 1, "));
+    assert_eq!(true, debug.ends_with(", 98: 1, 99: 1, ... 150 more }"));
+    assert_eq!(100, debug.matches(": 1").count());
+
+    let pretty = format!("{:#?}", large);
+    assert_eq!(true, pretty.ends_with("    99: 1,\n    ... 150 more\n}"));
+  }
+
+  #[test]
+  fn values_in_range() {
+    let mut set = CountedSet::new();
+    for int in -50..50 {
+      set.insert_n(int * 3, (int.rem_euclid(4) + 1) as usize);
+    }
+    set.remove_all(0);
+    set.remove_all(30);
+
+    let expected = |range: &dyn Fn(i64) -> bool| -> CountedSet {
+      let mut expected = CountedSet::new();
+      for (value, count) in set.values_and_counts() {
+        if range(value) {
+          expected.insert_n(value, count);
+        }
+      }
+      expected
+    };
+
+    assert_eq!(
+      expected(&|v| (-10..31).contains(&v)),
+      set.values_in_range(-10..31)
+    );
+    assert_eq!(
+      expected(&|v| (27..=33).contains(&v)),
+      set.values_in_range(27..=33)
+    );
+    assert_eq!(expected(&|v| v < -100), set.values_in_range(..-100));
+    assert_eq!(expected(&|v| v >= 140), set.values_in_range(140..));
+    assert_eq!(set, set.values_in_range(..));
+    assert_eq!(set, set.values_in_range(i64::MIN..=i64::MAX));
+    assert_eq!(
+      expected(&|v| v > 3 && v <= 9),
+      set.values_in_range((Bound::Excluded(3), Bound::Included(9)))
+    );
+    assert_eq!(
+      vec![0, 0, 2],
+      counts(&set.values_in_range(-3..=3), &[0, 30, 3])
+    );
+
+    assert_eq!(true, set.values_in_range(4..4).is_empty());
+    assert_eq!(true, set.values_in_range(1..3).is_empty());
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = set.values_in_range(10..-10);
+    assert_eq!(true, reversed.is_empty());
+    assert_eq!(true, CountedSet::new().values_in_range(..).is_empty());
+  }
+
+  #[test]
+  fn filtered_set_can_be_modified() {
+    let set = CountedSet::from(vec![1, 1, 2, 3, 3]);
+    let mut filtered = set.filtered_by_min_count(2);
+    for int in 10..1000 {
+      filtered.insert(int);
+    }
+    assert_eq!(992, filtered.len());
+    assert_eq!(vec![2, 0, 2, 1], counts(&filtered, &[1, 2, 3, 999]));
+    assert_eq!(3, set.len());
+  }
+
+  #[test]
+  fn union_all_sets() {
+    let sets: Vec<CountedSet> = (0..10)
+      .map(|i| CountedSet::from((i..i + 10).collect::<Vec<i64>>()))
+      .collect();
+    let union = CountedSet::union_all(&sets);
+    assert_eq!(19, union.len());
+    assert_eq!(vec![1, 5, 10, 1], counts(&union, &[0, 4, 9, 18]));
+
+    let pairwise = sets.iter().fold(CountedSet::new(), |mut union, set| {
+      union.union(set);
+      union
+    });
+    assert_eq!(pairwise, union);
+
+    assert_eq!(sets[3], CountedSet::union_all(&sets[3..4]));
+    assert_eq!(true, CountedSet::union_all(&[]).is_empty());
+    assert_eq!(true, CountedSet::union_all(Vec::new()).is_empty());
+  }
+
+  #[test]
+  fn intersect_all_sets() {
+    let sets: Vec<CountedSet> = (0..5)
+      .map(|i| CountedSet::from((i..i + 10).collect::<Vec<i64>>()))
+      .collect();
+    let intersection = CountedSet::intersect_all(sets.iter());
+    assert_eq!(
+      vec![4, 5, 6, 7, 8, 9],
+      intersection
+        .entries_iter()
+        .map(|(value, _)| value)
+        .collect::<Vec<i64>>()
+    );
+    assert_eq!(vec![5; 6], counts(&intersection, &[4, 5, 6, 7, 8, 9]));
+
+    let single = CountedSet::intersect_all(&sets[1..2]);
+    assert_eq!(sets[1], single);
+
+    assert_eq!(true, CountedSet::intersect_all(&[]).is_empty());
+  }
+
+  #[test]
+  fn intersect_all_stops_once_empty() {
+    let disjoint = vec![
+      CountedSet::from(vec![1, 2]),
+      CountedSet::from(vec![3]),
+      CountedSet::from(vec![1, 2, 3]),
+    ];
+    assert_eq!(true, CountedSet::intersect_all(&disjoint).is_empty());
+
+    // No more sets are taken from the iterator once the intersection is
+    // empty.
+    let mut visited = 0;
+    let intersection =
+      CountedSet::intersect_all(disjoint.iter().inspect(|_| {
+        visited += 1;
+      }));
+    assert_eq!(true, intersection.is_empty());
+    assert_eq!(2, visited);
+  }
+
+  #[test]
+  fn capacity_and_reserve() {
+    let mut set = CountedSet::with_capacity(10);
+    assert_eq!(10, set.capacity());
+
+    set.reserve(5);
+    assert_eq!(10, set.capacity());
+
+    set.insert(1);
+    set.reserve(100);
+    assert_eq!(101, set.capacity());
+
+    // Inserting the reserved values doesn't grow the buffer.
+    for value in 2..=101 {
+      set.insert(value);
+      set.insert(value);
+    }
+    assert_eq!(101, set.len());
+    assert_eq!(101, set.capacity());
+
+    set.insert(102);
+    set.insert(103);
+    assert_eq!(true, set.capacity() >= set.len());
+  }
+
+  #[test]
+  fn capacity_after_removals() {
+    let mut set = CountedSet::with_capacity(10);
+    set.extend_from_slice(&[1, 2, 3, 4, 5]);
+    assert_eq!(10, set.capacity());
+
+    // The nodes of removed values can't be reused by new values.
+    set.remove(1);
+    set.remove(2);
+    assert_eq!(8, set.capacity());
+    assert_eq!(3, set.len());
+
+    set.reserve(7);
+    assert_eq!(10, set.capacity());
+
+    set.shrink_to_fit();
+    assert_eq!(3, set.capacity());
+  }
+
+  #[test]
+  #[should_panic]
+  fn reserve_overflow_panics() {
+    let mut set = CountedSet::new();
+    set.insert(1);
+    set.reserve(usize::MAX);
+  }
+
+  #[test]
+  fn capacity_of_new_set_is_at_least_len() {
+    let mut set = CountedSet::new();
+    for value in 0..1000 {
+      set.insert(value);
+      assert_eq!(true, set.capacity() >= set.len());
+    }
+  }
+
+  #[test]
+  fn partial_cmp_by_multiset_containment() {
+    let empty = CountedSet::new();
+    let a = CountedSet::from(vec![1, 2, 2]);
+    let b = CountedSet::from(vec![1, 2, 2, 2]);
+    let c = CountedSet::from(vec![1, 2, 2, 3]);
+
+    assert_eq!(Some(Ordering::Equal), empty.partial_cmp(&CountedSet::new()));
+    assert_eq!(Some(Ordering::Less), empty.partial_cmp(&a));
+    assert_eq!(Some(Ordering::Greater), a.partial_cmp(&empty));
+    assert_eq!(Some(Ordering::Equal), a.partial_cmp(&a.clone()));
+    assert_eq!(Some(Ordering::Less), a.partial_cmp(&b));
+    assert_eq!(Some(Ordering::Less), a.partial_cmp(&c));
+    assert_eq!(Some(Ordering::Greater), c.partial_cmp(&a));
+
+    // b has more 2s, but c has a 3.
+    assert_eq!(None, b.partial_cmp(&c));
+    assert_eq!(None, c.partial_cmp(&b));
+    assert_eq!(false, b < c || b <= c || b > c || b >= c);
+
+    // Counts matter, unlike in is_subset.
+    let once = CountedSet::from(vec![1]);
+    let twice = CountedSet::from(vec![1, 1]);
+    assert_eq!(true, twice.is_subset(&once));
+    assert_eq!(false, twice <= once);
+    assert_eq!(true, once < twice);
+  }
+
+  #[test]
+  fn partial_cmp_ignores_removed_values() {
+    let mut a = CountedSet::from(vec![1, 2, 3]);
+    a.remove(3);
+    let b = CountedSet::from(vec![1, 2]);
+    assert_eq!(Some(Ordering::Equal), a.partial_cmp(&b));
+
+    a.remove(2);
+    assert_eq!(true, a < b);
+  }
+
+  #[test]
+  fn sum_counts_of() {
+    let mut set = CountedSet::new();
+    assert_eq!(0, set.sum_counts_of(&[1, 2, 3]));
+
+    set.insert_n(i64::MIN, 2);
+    set.insert_n(0, 3);
+    set.insert_n(i64::MAX, 5);
+    set.insert(7);
+    set.remove(7);
+    assert_eq!(0, set.sum_counts_of(&[7]));
+    assert_eq!(10, set.sum_counts_of(&[i64::MAX, i64::MIN, 0, 7, 8]));
+    assert_eq!(13, set.sum_counts_of(&[0, 0, i64::MAX, i64::MIN]));
+
+    let values: Vec<i64> = (-100..100).collect();
+    let expected: usize = values.iter().map(|v| set.get_count(*v)).sum();
+    assert_eq!(expected, set.sum_counts_of(&values));
+
+    set.insert_n(1, usize::MAX);
+    assert_eq!(usize::MAX, set.sum_counts_of(&[1, 0]));
+    assert_eq!(usize::MAX, set.sum_counts_of(&[1, 1]));
+  }
+
+  #[test]
+  fn count_distinct_matching() {
+    let mut set = CountedSet::new();
+    let mut calls = 0;
+    let matching = set.count_distinct_matching(|_, _| {
+      calls += 1;
+      true
+    });
+    assert_eq!(0, matching);
+    assert_eq!(0, calls);
+
+    for value in -50..50 {
+      set.insert_n(value, (value + 50) as usize % 4 + 1);
+    }
+    set.insert_n(i64::MAX, usize::MAX);
+    set.insert(1000);
+    set.remove(1000);
+    assert_eq!(set.len(), set.count_distinct_matching(|_, _| true));
+    assert_eq!(0, set.count_distinct_matching(|_, _| false));
+    assert_eq!(0, set.count_distinct_matching(|value, _| value == 1000));
+
+    let mut seen = Vec::new();
+    set.count_distinct_matching(|value, count| {
+      assert_eq!(set.get_count(value), count);
+      seen.push(value);
+      false
+    });
+    seen.sort();
+    assert_eq!(set.to_vec_sorted().len(), seen.len());
+    let mut expected = set.to_vec();
+    expected.sort();
+    assert_eq!(expected, seen);
+
+    assert_eq!(50, set.count_distinct_matching(|value, _| value < 0));
+    assert_eq!(25, set.count_distinct_matching(|_, count| count == 4));
+    assert_eq!(
+      1,
+      set.count_distinct_matching(|_, count| count == usize::MAX)
+    );
+  }
+
+  #[test]
+  fn histogram() {
+    let mut set = CountedSet::new();
+    assert_eq!(BTreeMap::new(), set.histogram());
+
+    for value in 0..100 {
+      set.insert_n(value, value as usize % 5 + 1);
+    }
+    set.insert_n(-1, usize::MAX);
+    set.insert(1000);
+    set.remove(1000);
+    set.remove_all(0);
+
+    let histogram = set.histogram();
+    let expected: BTreeMap<usize, usize> =
+      vec![(1, 19), (2, 20), (3, 20), (4, 20), (5, 20), (usize::MAX, 1)]
+        .into_iter()
+        .collect();
+    assert_eq!(expected, histogram);
+    assert_eq!(set.len(), histogram.values().sum::<usize>());
+
+    let kept: usize = histogram.range(3..).map(|(_, values)| values).sum();
+    assert_eq!(set.filtered_by_min_count(3).len(), kept);
+
+    set.clear();
+    assert_eq!(BTreeMap::new(), set.histogram());
+  }
+
+  #[test]
+  fn union_and_intersect_saturate() {
+    let mut set = CountedSet::new();
+    set.insert_n(1, usize::MAX - 1);
+    set.insert_n(2, 3);
+    let mut other = CountedSet::new();
+    other.insert_n(1, usize::MAX);
+    other.insert_n(2, usize::MAX - 2);
+
+    let mut union = set.clone();
+    union.union(&other);
+    assert_eq!(vec![usize::MAX, usize::MAX], counts(&union, &[1, 2]));
+    union.union(&union.clone());
+    assert_eq!(vec![usize::MAX, usize::MAX], counts(&union, &[1, 2]));
+
+    let mut intersection = set.clone();
+    intersection.intersect(&other);
+    assert_eq!(vec![usize::MAX, usize::MAX], counts(&intersection, &[1, 2]));
+
+    set.insert_n(3, usize::MAX);
+    let merged = CountedSet::union_all(vec![&set, &set, &set]);
+    assert_eq!(usize::MAX, merged.get_count(3));
+    assert_eq!(usize::MAX, merged.total_count());
+  }
+
+  #[test]
+  fn count_of_distinguishes_absent_values() {
+    let mut set = CountedSet::new();
+    assert_eq!(None, set.count_of(0));
+
+    set.insert_n(0, 3);
+    set.insert(i64::MIN);
+    assert_eq!(Some(3), set.count_of(0));
+    assert_eq!(Some(1), set.count_of(i64::MIN));
+    assert_eq!(None, set.count_of(1));
+
+    set.remove(0);
+    assert_eq!(Some(2), set.count_of(0));
+    set.remove_n(0, 2);
+    assert_eq!(None, set.count_of(0));
+    assert_eq!(false, set.contains(0));
+
+    set.clear();
+    assert_eq!(None, set.count_of(i64::MIN));
+  }
+
+  #[test]
+  fn scale_counts() {
+    let mut set = CountedSet::from(vec![1, 2, 2, 3, 3, 3]);
+    set.remove_all(3);
+
+    set.scale(1);
+    assert_eq!(vec![1, 2, 0], counts(&set, &[1, 2, 3]));
+    assert_eq!(2, set.len());
+
+    set.scale(4);
+    assert_eq!(vec![4, 8, 0], counts(&set, &[1, 2, 3]));
+    assert_eq!(2, set.len());
+    assert_eq!(vec![2, 1], set.to_vec());
+
+    set.insert_n(5, usize::MAX / 2 + 1);
+    set.scale(2);
+    assert_eq!(vec![8, 16, usize::MAX], counts(&set, &[1, 2, 5]));
+
+    set.scale(0);
+    assert_eq!(true, set.is_empty());
+    assert_eq!(vec![0, 0, 0], counts(&set, &[1, 2, 5]));
+    set.insert(2);
+    assert_eq!(1, set.get_count(2));
+  }
+
+  #[test]
+  fn scaled_leaves_set_unchanged() {
+    let set = CountedSet::from(vec![-1, 7, 7]);
+    assert_eq!(vec![2, 4], counts(&set.scaled(2), &[-1, 7]));
+    assert_eq!(set, set.scaled(1));
+    assert_eq!(true, set.scaled(0).is_empty());
+    assert_eq!(vec![1, 2], counts(&set, &[-1, 7]));
+    assert_eq!(true, CountedSet::new().scaled(5).is_empty());
+  }
+
+  #[test]
+  fn shrink_to_fit_after_removals() {
+    let mut set = CountedSet::new();
+    for int in 0..10_000 {
+      set.insert_n(int, (int % 3 + 1) as usize);
+    }
+    set.retain(|value, _| value % 100 == 0);
+    let expected: Vec<(i64, usize)> = (0..10_000)
+      .step_by(100)
+      .map(|int| (int, (int % 3 + 1) as usize))
+      .collect();
+
+    set.shrink_to_fit();
+    assert_eq!(100, set.nodes().len());
+    assert_eq!(100, set.len());
+    let mut values_and_counts = set.values_and_counts();
+    values_and_counts.sort_unstable();
+    assert_eq!(expected, values_and_counts);
+
+    // The rebuilt tree is valid and can still be modified.
+    assert_eq!(set, CountedSet::from_bytes(&set.to_bytes()).unwrap());
+    for int in 0..1000 {
+      set.insert(int);
+    }
+    set.remove_all(500);
+    assert_eq!(1089, set.len());
+    assert_eq!(vec![2, 1, 0, 1], counts(&set, &[0, 1, 500, 9_900]));
+  }
+
+  #[test]
+  fn shrink_to_fit_without_removals() {
+    let mut set = CountedSet::with_capacity(10_000);
+    set.extend_from_slice(&[3, 1, 4, 1, 5, 9, 2, 6]);
+    let copy = set.clone();
+
+    set.shrink_to_fit();
+    assert_eq!(copy, set);
+    assert_eq!(copy.to_bytes(), set.to_bytes());
+    set.insert(7);
+    assert_eq!(8, set.len());
+  }
+
+  #[test]
+  fn shrink_to_fit_empty_sets() {
+    let mut set = CountedSet::new();
+    set.shrink_to_fit();
+    assert_eq!(true, set.is_empty());
+
+    set.extend_from_slice(&[1, 2, 3]);
+    set.clear();
+    set.shrink_to_fit();
+    assert_eq!(true, set.is_empty());
+    assert_eq!(0, set.nodes().len());
+
+    set.insert(2);
+    assert_eq!(vec![2], set.to_vec());
+  }
+
+  #[test]
+  fn is_empty_tracks_every_change() {
+    let mut set = CountedSet::new();
+    assert_eq!(true, set.is_empty());
+
+    set.insert(1);
+    assert_eq!(false, set.is_empty());
+    set.remove(1);
+    assert_eq!(true, set.is_empty());
+
+    set.insert_n(2, 2);
+    set.remove_checked(2);
+    assert_eq!(false, set.is_empty());
+    set.remove_checked(2);
+    assert_eq!(true, set.is_empty());
+
+    set.extend_from_slice(&[1, 2, 3]);
+    let other = CountedSet::from(vec![1, 2, 3]);
+    set.minus(&other);
+    assert_eq!(true, set.is_empty());
+
+    set.union(&other);
+    assert_eq!(false, set.is_empty());
+    set.intersect(&CountedSet::from(vec![4]));
+    assert_eq!(true, set.is_empty());
+
+    set.union(&other);
+    set.clear();
+    assert_eq!(true, set.is_empty());
+    assert_eq!(0, set.len());
+  }
+
+  #[test]
+  fn clone_from_smaller_set_reuses_buffer() {
+    let mut set = CountedSet::from((0..1000).collect::<Vec<i64>>());
+    let buffer = set.nodes().as_ptr();
+
+    let mut source = CountedSet::from(vec![5, 5, 7, 9]);
+    source.remove_all(9);
+    set.clone_from(&source);
+    assert_eq!(source, set);
+    assert_eq!(source.to_bytes(), set.to_bytes());
+    assert_eq!(buffer, set.nodes().as_ptr());
+
+    // The copy is independent of its source.
+    set.insert(8);
+    source.insert(6);
+    assert_eq!(vec![2, 1, 1, 0], counts(&set, &[5, 7, 8, 6]));
+    assert_eq!(vec![2, 1, 0, 1], counts(&source, &[5, 7, 8, 6]));
+  }
+
+  #[test]
+  fn clone_from_larger_set() {
+    let source = CountedSet::from((0..1000).collect::<Vec<i64>>());
+    let mut set = CountedSet::from(vec![-1, -2]);
+
+    set.clone_from(&source);
+    assert_eq!(source, set);
+    assert_eq!(false, set.contains(-1));
+
+    for int in 1000..2000 {
+      set.insert(int);
+    }
+    assert_eq!(2000, set.len());
+    assert_eq!(1000, source.len());
+  }
+
+  #[test]
+  fn clone_from_empty_set() {
+    let mut set = CountedSet::from(vec![1, 2, 3]);
+    set.clone_from(&CountedSet::new());
+    assert_eq!(true, set.is_empty());
+    assert_eq!(CountedSet::new(), set);
+
+    set.insert(4);
+    assert_eq!(vec![4], set.to_vec());
+  }
+
+  #[test]
+  fn raw_round_trip() {
+    let set = CountedSet::from(vec![1, 2, 2, 3]);
+    let raw = unsafe { set.as_raw() };
+    assert_eq!(3, unsafe { tsearch_countedset_get_count(raw) });
+
+    assert_eq!(raw, set.into_raw());
+    unsafe {
+      tsearch_countedset_add_int(raw, 4).expect();
+    }
+    let set = unsafe { CountedSet::from_raw(raw) };
+    assert_eq!(vec![1, 2, 1, 1], counts(&set, &[1, 2, 3, 4]));
+  }
+
+  #[test]
+  fn len_reflects_changes_through_raw_pointer() {
+    let mut set = CountedSet::from(vec![1, 2, 2]);
+    assert_eq!(2, set.len());
+
+    let raw = unsafe { set.as_raw() };
+    unsafe {
+      tsearch_countedset_add_int(raw, 3).expect();
+      tsearch_countedset_add_int_count(raw, 4, 2).expect();
+    }
+    assert_eq!(4, set.len());
+    assert_eq!(false, set.is_empty());
+
+    set.intersect(&CountedSet::from(vec![2, 4, 5]));
+    assert_eq!(2, set.len());
+    unsafe {
+      tsearch_countedset_remove_all_ints(set.as_raw());
+    }
+    assert_eq!(0, set.len());
+    assert_eq!(true, set.is_empty());
+  }
+
+  #[test]
+  fn set_is_usable_after_panicking_operations() {
+    let mut set = CountedSet::from(vec![1, 2, 2, 3, 3, 3]);
+
+    // GNETextSearch refuses to reserve this much, so `reserve` panics.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      set.reserve(usize::MAX);
+    }));
+    assert_eq!(true, result.is_err());
+    assert_consistent(&set);
+    assert_eq!(vec![1, 2, 3], set.get_counts(&[1, 2, 3]));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      set.retain(|value, _| {
+        assert_ne!(3, value, "retain");
+        value != 1
+      });
+    }));
+    assert_eq!(true, result.is_err());
+    assert_consistent(&set);
+    assert_eq!(3, set.get_count(3));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      let other = CountedSet::from(vec![2, 4]);
+      set.merge_with(&other, |a, b| {
+        assert_ne!((0, 1), (a, b), "merge_with");
+        a + b
+      });
+    }));
+    assert_eq!(true, result.is_err());
+    assert_consistent(&set);
+    assert_eq!(false, set.contains(4));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      set.extend((10..).map(|value| {
+        assert_ne!(20, value, "extend");
+        (value, 1)
+      }));
+    }));
+    assert_eq!(true, result.is_err());
+    assert_consistent(&set);
+    assert_eq!(true, set.contains(19));
+    assert_eq!(false, set.contains(20));
+
+    let copy = set.clone();
+    set.union(&copy);
+    set.intersect(&copy);
+    set.minus(&CountedSet::from(vec![10]));
+    assert_consistent(&set);
+
+    // `Drain` clears the set when it's dropped while unwinding.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      let mut drain = set.drain();
+      drain.next();
+      panic!("drain");
+    }));
+    assert_eq!(true, result.is_err());
+    assert_consistent(&set);
+    assert_eq!(true, set.is_empty());
+
+    set.insert(5);
+    assert_eq!(vec![5], set.to_vec());
+  }
+
+  #[test]
+  fn set_moved_into_panicking_closure_is_freed() {
+    let set = CountedSet::from(vec![1, 2, 2]);
+    let result = panic::catch_unwind(move || {
+      let mut set = set;
+      set.reserve(usize::MAX);
+      set
+    });
+    assert_eq!(true, result.is_err());
+
+    let sets: Vec<CountedSet> =
+      (0..4).map(|i| CountedSet::from(vec![i; 3])).collect();
+    let result = panic::catch_unwind(move || {
+      let mut union = CountedSet::new();
+      for set in &sets {
+        union.union(set);
+        union.reserve(if set.contains(2) { usize::MAX } else { 1 });
+      }
+      union
+    });
+    assert_eq!(true, result.is_err());
+  }
+
+  #[test]
+  fn build_from_sorted_values() {
+    assert_eq!(CountedSet::new(), CountedSetBuilder::new().build());
+
+    for len in (0..70).chain(vec![1000, 4097]) {
+      let mut builder = CountedSetBuilder::with_capacity(len);
+      let mut expected = CountedSet::new();
+      for i in 0..len as i64 {
+        let value = i * 3 - len as i64;
+        let count = (i % 4) as usize;
+        builder.push_n(value, count);
+        expected.insert_n(value, count);
+      }
+      let set = builder.build();
+      assert_eq!(expected, set);
+      assert_eq!(true, is_valid_tree(set.nodes()));
+      assert_consistent(&set);
+
+      // The tree is as short as a tree holding its values can be.
+      let height = |nodes: &[tsearch_countedset_node]| {
+        let mut stack = vec![(0, 1)];
+        let mut height = 0;
+        while let Some((index, depth)) = stack.pop() {
+          if index == usize::MAX || nodes.is_empty() {
+            continue;
+          }
+          height = height.max(depth);
+          stack.push((nodes[index].left, depth + 1));
+          stack.push((nodes[index].right, depth + 1));
+        }
+        height
+      };
+      let min_height = (usize::BITS - set.len().leading_zeros()) as usize;
+      assert_eq!(min_height, height(set.nodes()));
+    }
+  }
+
+  #[test]
+  fn built_set_can_be_modified() {
+    let mut builder = CountedSetBuilder::new();
+    builder.push(i64::MIN);
+    builder.push_n(-1, usize::MAX);
+    builder.push_n(-1, 1);
+    builder.push(0);
+    builder.push(0);
+    builder.push_n(1, 0);
+    builder.push(i64::MAX);
+    let mut set = builder.build();
+    assert_eq!(4, set.len());
+    assert_eq!(
+      vec![1, usize::MAX, 2, 0, 1],
+      set.get_counts(&[i64::MIN, -1, 0, 1, i64::MAX])
+    );
+
+    for value in -500..500 {
+      set.insert(value * 7);
+    }
+    set.remove_all(i64::MIN);
+    set.remove(0);
+    set.remove(0);
+    assert_eq!(true, is_valid_tree(set.nodes()));
+    assert_consistent(&set);
+    assert_eq!(false, set.contains(i64::MIN));
+    assert_eq!(1, set.get_count(0));
+    assert_eq!(1002, set.len());
+
+    set.shrink_to_fit();
+    assert_eq!(CountedSet::from_bytes(&set.to_bytes()).unwrap(), set);
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "ascending order")]
+  fn build_from_unsorted_values_panics_in_debug_builds() {
+    let mut builder = CountedSetBuilder::new();
+    builder.push(2);
+    builder.push(1);
+  }
+
+  #[test]
+  #[cfg(not(debug_assertions))]
+  fn build_from_unsorted_values() {
+    let mut builder = CountedSetBuilder::new();
+    for value in &[5, 5, 1, 9, 1, 1, -4] {
+      builder.push(*value);
     }
+    let set = builder.build();
+    assert_eq!(CountedSet::from(vec![5, 5, 1, 9, 1, 1, -4]), set);
+    assert_consistent(&set);
   }
-}
 
-impl Clone for CountedSet {
-  /// Returns a copy of the set.
-  ///
-  /// # Examples
-  ///
-  /// ```
-  /// use text_search::counted_set::CountedSet;
-  ///
-  /// let mut set = CountedSet::new();
-  /// set.insert(1);
-  /// set.insert(1);
-  /// set.insert(2);
-  ///
-  /// let mut copy = set.clone();
-  /// copy.insert(3);
-  ///
-  /// assert_eq!(copy.get_count(1), set.get_count(1));
-  /// assert_eq!(copy.get_count(2), set.get_count(2));
-  /// assert_ne!(copy.get_count(3), set.get_count(3));
-  /// ```
-  fn clone(&self) -> CountedSet {
-    let raw = unsafe { tsearch_countedset_copy(self.raw) };
-    assert!(raw.is_null() == false);
-    CountedSet { raw }
+  #[test]
+  fn take_and_swap() {
+    let mut set = CountedSet::from(vec![1, 2, 2]);
+    let raw = unsafe { set.as_raw() };
+
+    let taken = set.take();
+    assert_eq!(raw, unsafe { taken.as_raw() });
+    assert_eq!(vec![1, 2], taken.get_counts(&[1, 2]));
+    assert_eq!(true, set.is_empty());
+    set.insert(3);
+    assert_eq!(vec![3], set.to_vec());
+
+    let mut taken = taken;
+    let other_raw = unsafe { set.as_raw() };
+    set.swap(&mut taken);
+    assert_eq!(raw, unsafe { set.as_raw() });
+    assert_eq!(other_raw, unsafe { taken.as_raw() });
+    assert_eq!(vec![1, 2, 0], set.get_counts(&[1, 2, 3]));
+    assert_eq!(vec![0, 0, 1], taken.get_counts(&[1, 2, 3]));
+
+    set.swap(&mut taken);
+    set.swap(&mut taken);
+    set.insert(4);
+    taken.remove_all(3);
+    assert_eq!(vec![1, 2, 0, 1], set.get_counts(&[1, 2, 3, 4]));
+    assert_eq!(true, taken.is_empty());
+
+    let moved = mem::take(&mut set);
+    assert_eq!(raw, unsafe { moved.as_raw() });
+    assert_eq!(true, set.is_empty());
+    drop(moved);
+    drop(taken);
+    set.insert(5);
+    assert_eq!(vec![5], set.to_vec());
   }
-}
 
-impl Drop for CountedSet {
-  fn drop(&mut self) {
+  #[test]
+  fn set_from_raw_counted_set() {
+    let raw = unsafe { tsearch_countedset_init() };
     unsafe {
-      tsearch_countedset_free(self.raw);
+      tsearch_countedset_add_int_count(raw, 7, 3).expect();
     }
+    let mut set = unsafe { CountedSet::from_raw(raw) };
+    set.insert(7);
+    assert_eq!(4, set.get_count(7));
   }
-}
 
-trait _ResultExt {
-  fn expect(self);
-}
+  #[test]
+  fn from_vec_accumulates_counts() {
+    let set = CountedSet::from(vec![3, 1, 3, -2, 3, 1, 0]);
+    assert_eq!(4, set.len());
+    assert_eq!(7, set.total_count());
+    assert_eq!(vec![1, 2, 3, 1, 0], counts(&set, &[-2, 1, 3, 0, 2]));
 
-impl _ResultExt for _Result {
-  fn expect(self) {
-    match self {
-      1 => {}
-      _ => panic!(),
-    };
+    assert_eq!(true, CountedSet::from(Vec::new()).is_empty());
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use std::collections::HashMap;
-  use std::ops::Range;
+  #[test]
+  fn from_slice() {
+    let values: Vec<i64> = (0..1000).map(|int| int % 10).collect();
+    let set = CountedSet::from(&values[..]);
+    assert_eq!(10, set.len());
+    assert_eq!(vec![100; 10], counts(&set, &(0..10).collect::<Vec<_>>()));
+    assert_eq!(set, CountedSet::from(values));
+  }
 
   #[test]
-  fn length_of_counted_set() {
+  fn from_hash_map() {
+    let mut map = HashMap::new();
+    map.insert(1, 3);
+    map.insert(-7, 1);
+    map.insert(9, 0);
+    map.insert(i64::MAX, usize::MAX);
+
+    let set = CountedSet::from(map);
+    assert_eq!(3, set.len());
+    assert_eq!(
+      vec![3, 1, 0, usize::MAX],
+      counts(&set, &[1, -7, 9, i64::MAX])
+    );
+  }
+
+  #[test]
+  fn into_hash_map() {
+    let mut set = CountedSet::from(vec![1, 1, 2, 3]);
+    set.remove_all(3);
+
+    let map: HashMap<i64, usize> = set.clone().into();
+    let mut expected = HashMap::new();
+    expected.insert(1, 2);
+    expected.insert(2, 1);
+    assert_eq!(expected, map);
+
+    assert_eq!(set, CountedSet::from(map));
+  }
+
+  #[test]
+  fn drain_set() {
     let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![-1, 0, 0, 1, 2]);
-    assert_eq!(4, set.len());
+    for int in 0..1000 {
+      for _ in 0..(int % 4) {
+        set.insert(int);
+      }
+    }
+    set.remove_all(1);
+
+    let drain = set.drain();
+    assert_eq!(749, drain.len());
+    let drained: HashMap<i64, usize> = drain.collect();
+    assert_eq!(749, drained.len());
+    assert_eq!(None, drained.get(&1));
+    assert_eq!(None, drained.get(&4));
+    assert_eq!(Some(&3), drained.get(&999));
+    assert_eq!(true, set.is_empty());
+    assert_eq!(0, set.total_count());
+    assert_eq!(Vec::<i64>::new(), set.to_vec());
   }
 
   #[test]
-  fn is_counted_set_empty() {
+  fn drop_drain_early() {
     let mut set = CountedSet::new();
+    set.extend_from_slice(&[1, 2, 2, 3]);
+
+    let mut drain = set.drain();
+    assert_eq!(true, drain.next().is_some());
+    drop(drain);
     assert_eq!(true, set.is_empty());
+    assert_eq!(vec![0, 0, 0], counts(&set, &[1, 2, 3]));
 
-    insert_integers(&mut set, vec![0, 0, 1, 2]);
-    for int in vec![0, 1, 2] {
-      set.remove(int);
-      assert_eq!(false, set.is_empty());
-    }
-    set.remove(0);
+    set.extend_from_slice(&[1, 2, 2, 3]);
+    set.drain();
     assert_eq!(true, set.is_empty());
   }
 
   #[test]
-  fn clear_counted_set() {
+  fn drain_empty_set() {
     let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![91, 123456, -1]);
-    assert_eq!(false, set.is_empty());
-    set.clear();
+    assert_eq!(None, set.drain().next());
     assert_eq!(true, set.is_empty());
   }
 
   #[test]
-  fn minus_counted_set() {
+  fn fill_drain_and_reuse_set() {
     let mut set = CountedSet::new();
-    let mut other = CountedSet::new();
+    for round in 0..10 {
+      set.extend_from_slice(&[round, round, round + 1]);
+      let mut drained: Vec<(i64, usize)> = set.drain().collect();
+      drained.sort_unstable();
+      assert_eq!(vec![(round, 2), (round + 1, 1)], drained);
+      assert_eq!(true, set.is_empty());
+    }
+  }
 
-    insert_integers(
-      &mut set,
-      vec![-999, 0, 999, -998, 12345, 999, 999, -1000, -12345],
-    );
-    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+  #[test]
+  fn bytes_round_trip() {
+    let mut set = CountedSet::new();
+    for int in -500i64..500 {
+      for _ in 0..(int % 7).abs() {
+        set.insert(int);
+      }
+    }
+    set.remove_all(3);
+    set.remove(4);
 
-    set.minus(&other);
+    let bytes = set.to_bytes();
+    assert_eq!(0, (bytes.len() - BYTES_HEADER_LEN) % NODE_BYTES_LEN);
 
-    assert_eq!(1, set.get_count(-999));
-    assert_eq!(0, set.get_count(0));
-    assert_eq!(2, set.get_count(999));
-    assert_eq!(0, set.get_count(-998));
-    assert_eq!(1, set.get_count(12345));
-    assert_eq!(0, set.get_count(-1000));
-    assert_eq!(1, set.get_count(-12345));
-    assert_eq!(0, set.get_count(1234));
-    assert_eq!(0, set.get_count(-1234));
+    let copy = CountedSet::from_bytes(&bytes).unwrap();
+    assert_eq!(set, copy);
+    assert_eq!(set.len(), copy.len());
+    assert_eq!(false, copy.contains(3));
+    assert_eq!(3, copy.get_count(4));
+    assert_consistent(&copy);
+
+    // The copy's tree is rebuilt without the removed values, and rebuilding
+    // it again lays it out the same way.
+    let bytes = copy.to_bytes();
+    assert_eq!(BYTES_HEADER_LEN + copy.len() * NODE_BYTES_LEN, bytes.len());
+    let again = CountedSet::from_bytes(&bytes).unwrap();
+    assert_eq!(bytes, again.to_bytes());
   }
 
   #[test]
-  fn intersect_counted_set() {
+  fn bytes_round_trip_empty_set() {
+    let set = CountedSet::new();
+    assert_eq!(BYTES_HEADER_LEN, set.to_bytes().len());
+
+    let copy = CountedSet::from_bytes(&set.to_bytes()).unwrap();
+    assert_eq!(true, copy.is_empty());
+    assert_eq!(set, copy);
+  }
+
+  #[test]
+  fn set_from_bytes_can_be_modified() {
     let mut set = CountedSet::new();
-    let mut other = CountedSet::new();
+    set.extend_from_slice(&[5, 3, 8, 3]);
 
-    insert_integers(&mut set, vec![-999, 0, 999, -998, 12345, -1000, -12345]);
-    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+    let mut copy = CountedSet::from_bytes(&set.to_bytes()).unwrap();
+    for int in 0..1000 {
+      copy.insert(int);
+    }
+    copy.remove_all(8);
 
-    set.intersect(&other);
+    assert_eq!(999, copy.len());
+    assert_eq!(vec![3, 2, 0, 1], counts(&copy, &[3, 5, 8, 999]));
+    assert_eq!(vec![2, 1, 1], counts(&set, &[3, 5, 8]));
+  }
 
-    assert_eq!(2, set.get_count(0));
-    assert_eq!(3, set.get_count(-998));
-    assert_eq!(2, set.get_count(999));
-    assert_eq!(2, set.get_count(-1000));
+  #[test]
+  fn from_unaligned_bytes() {
+    let mut set = CountedSet::new();
+    set.extend_from_slice(&[-1, 0, 1, 1, i64::MAX]);
 
-    assert_eq!(0, set.get_count(-999));
-    assert_eq!(0, set.get_count(12345));
-    assert_eq!(0, set.get_count(-12345));
-    assert_eq!(0, set.get_count(1234));
-    assert_eq!(0, set.get_count(-1234));
+    let mut bytes = vec![0u8];
+    bytes.extend_from_slice(&set.to_bytes());
+    assert_eq!(set, CountedSet::from_bytes(&bytes[1..]).unwrap());
   }
 
   #[test]
-  fn union_counted_set() {
+  fn from_invalid_bytes() {
     let mut set = CountedSet::new();
-    let mut other = CountedSet::new();
+    set.extend_from_slice(&[2, 1, 3]);
+    let nodes = set.nodes().to_vec();
+    let invalid = |nodes: &[tsearch_countedset_node]| {
+      from_nodes(nodes) == Err(TextSearchError::InvalidBytes)
+    };
+    assert_eq!(false, invalid(&nodes));
 
-    insert_integers(&mut set, vec![-999, 0, 999, -998, 12345, -1000, -12345]);
-    insert_integers(&mut other, vec![0, -998, -1234, 999, -998, -1000, 1234]);
+    let bytes = set.to_bytes();
+    let header = BYTES_HEADER_LEN;
+    for length in &[0, 1, header - 1, header, header + 1, bytes.len() - 1] {
+      assert_eq!(
+        Err(TextSearchError::InvalidBytes),
+        CountedSet::from_bytes(&bytes[..*length])
+      );
+    }
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert_eq!(
+      Err(TextSearchError::InvalidBytes),
+      CountedSet::from_bytes(&longer)
+    );
 
-    set.union(&other);
+    // A header with the wrong magic bytes, version, or number of nodes.
+    for (index, byte) in &[(0, b'X'), (4, 2), (8, 2), (8, 4)] {
+      let mut broken = bytes.clone();
+      broken[*index] = *byte;
+      assert_eq!(
+        Err(TextSearchError::InvalidBytes),
+        CountedSet::from_bytes(&broken)
+      );
+    }
 
-    assert_eq!(1, set.get_count(-999));
-    assert_eq!(2, set.get_count(0));
-    assert_eq!(3, set.get_count(-998));
-    assert_eq!(2, set.get_count(999));
-    assert_eq!(2, set.get_count(-1000));
-    assert_eq!(1, set.get_count(12345));
-    assert_eq!(1, set.get_count(-12345));
-    assert_eq!(1, set.get_count(1234));
-    assert_eq!(1, set.get_count(-1234));
+    // A child out of bounds.
+    let mut broken = nodes.clone();
+    broken[0].left = 3;
+    assert_eq!(true, invalid(&broken));
+
+    // A cycle back to the root.
+    let mut broken = nodes.clone();
+    broken[1].left = 0;
+    assert_eq!(true, invalid(&broken));
+
+    // A node that isn't reachable from the root.
+    let mut broken = nodes.clone();
+    broken[0].right = usize::MAX;
+    broken[0].left = usize::MAX;
+    assert_eq!(true, invalid(&broken));
+
+    // Children on the wrong side of their parent.
+    let mut broken = nodes.clone();
+    broken.swap(1, 2);
+    assert_eq!(true, invalid(&broken));
+
+    let mut broken = nodes.clone();
+    broken[0].balance = 2;
+    assert_eq!(true, invalid(&broken));
+
+    let mut broken = nodes.clone();
+    broken[0].reserved = 1;
+    assert_eq!(true, invalid(&broken));
   }
 
   #[test]
-  fn contains_value() {
-    let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![91, 123456, -1]);
-    assert_eq!(true, set.contains(-1));
-    assert_eq!(true, set.contains(91));
-    assert_eq!(true, set.contains(123456));
+  fn from_bytes_of_deep_chain() {
+    // Every node only has a left child and claims to be balanced, so the
+    // tree is as deep as it is long.
+    let length = 200_000;
+    let nodes: Vec<tsearch_countedset_node> = (0..length)
+      .map(|index| tsearch_countedset_node {
+        integer: (length - index) as i64,
+        count: 1 + index % 2,
+        balance: 0,
+        reserved: 0,
+        left: if index + 1 < length {
+          index + 1
+        } else {
+          usize::MAX
+        },
+        right: usize::MAX,
+      })
+      .collect();
 
-    set.clear();
-    assert_eq!(false, set.contains(-1));
-    assert_eq!(false, set.contains(91));
-    assert_eq!(false, set.contains(123456));
+    let mut set = from_nodes(&nodes).unwrap();
+    assert_eq!(length, set.len());
+    assert_eq!(true, tree_height(set.nodes()) <= 18);
+    assert_consistent(&set);
+
+    assert_eq!(1, set.insert(0));
+    assert_eq!(3, set.insert(1));
+    assert_eq!(1, set.insert(length as i64 + 1));
+    set.remove_all(length as i64 / 2);
+    assert_eq!(length + 1, set.len());
+    assert_eq!(1, set.get_count(length as i64));
+    assert_eq!(2, set.get_count(length as i64 - 1));
+    assert_consistent(&set);
   }
 
   #[test]
-  fn count_of_value() {
-    let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![-1, -1, 0, 0, 0, 1, 1, 2]);
-    assert_eq!(2, set.get_count(-1));
-    assert_eq!(3, set.get_count(0));
-    assert_eq!(2, set.get_count(1));
-    assert_eq!(1, set.get_count(2));
+  fn from_random_bytes() {
+    // A fixed xorshift generator keeps failures reproducible.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    };
+    let mut accepted = 0;
+
+    for round in 0..20_000 {
+      let mut set = CountedSet::new();
+      for _ in 0..next() % 24 {
+        set.insert_n((next() % 32) as i64 - 16, (next() % 3) as usize);
+      }
+      let mut bytes = set.to_bytes();
+      match round % 4 {
+        // Random nodes after a valid header.
+        0 => {
+          let count = next() % 6;
+          bytes.truncate(BYTES_HEADER_LEN);
+          bytes[8..16].copy_from_slice(&count.to_le_bytes());
+          let len = count as usize * NODE_BYTES_LEN;
+          bytes.extend((0..len).map(|_| next() as u8));
+        },
+        // A few random bytes changed.
+        1 => {
+          for _ in 0..1 + next() % 3 {
+            if !bytes.is_empty() {
+              let index = next() as usize % bytes.len();
+              bytes[index] = next() as u8;
+            }
+          }
+        },
+        // Links redirected to other nodes, which usually stays in bounds.
+        2 => {
+          let mut nodes = set.nodes().to_vec();
+          if !nodes.is_empty() {
+            let count = nodes.len() as u64;
+            let target = match next() % (count + 1) {
+              target if target == count => usize::MAX,
+              target => target as usize,
+            };
+            let node = &mut nodes[(next() % count) as usize];
+            if next() % 2 == 0 {
+              node.left = target;
+            } else {
+              node.right = target;
+            }
+          }
+          bytes = write_nodes(&nodes);
+        },
+        // Bytes cut off or added.
+        _ => {
+          let len = next() as usize % (bytes.len() + NODE_BYTES_LEN + 1);
+          bytes.resize(len, next() as u8);
+        },
+      }
+
+      let mut copy = match CountedSet::from_bytes(&bytes) {
+        Ok(copy) => copy,
+        Err(error) => {
+          assert_eq!(TextSearchError::InvalidBytes, error);
+          continue;
+        },
+      };
+      accepted += 1;
+
+      // A set that was accepted behaves like any other set.
+      let contents = copy.values_and_counts();
+      assert_eq!(contents.len(), copy.len());
+      for (value, count) in &contents {
+        assert_eq!(*count, copy.get_count(*value));
+      }
+      assert_eq!(copy, CountedSet::from_bytes(&copy.to_bytes()).unwrap());
+      for _ in 0..8 {
+        let value = (next() % 48) as i64 - 24;
+        let count = copy.get_count(value);
+        assert_eq!(count + 1, copy.insert(value));
+      }
+      copy.remove_all((next() % 48) as i64 - 24);
+      let contents = copy.values_and_counts();
+      assert_eq!(contents.len(), copy.len());
+      for (value, count) in &contents {
+        assert_eq!(*count, copy.get_count(*value));
+      }
+      copy.shrink_to_fit();
+      assert_eq!(contents.len(), copy.len());
+    }
+    assert_eq!(true, accepted > 1000);
   }
 
   #[test]
-  fn insert_into_counted_set() {
+  fn bytes_are_little_endian() {
     let mut set = CountedSet::new();
-    assert_eq!(1, set.insert(1));
-    assert_eq!(2, set.insert(1));
-    assert_eq!(3, set.insert(1));
-    assert_eq!(1, set.insert(2));
+    set.insert_n(0x0102, 3);
+
+    let mut expected = b"TSCS".to_vec();
+    expected.extend_from_slice(&[1, 0, 0, 0]);
+    expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    expected.extend_from_slice(&[2, 1, 0, 0, 0, 0, 0, 0]);
+    expected.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0]);
+    expected.extend_from_slice(&[0; 8]);
+    expected.extend_from_slice(&[0xff; 16]);
+    assert_eq!(expected, set.to_bytes());
+    assert_eq!(set, CountedSet::from_bytes(&expected).unwrap());
+
+    // The same set written in big-endian byte order isn't misread as a set
+    // holding a different value.
+    let mut swapped = b"TSCS".to_vec();
+    swapped.extend_from_slice(&[0, 0, 0, 1]);
+    swapped.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    swapped.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 2]);
+    swapped.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 3]);
+    swapped.extend_from_slice(&[0; 8]);
+    swapped.extend_from_slice(&[0xff; 16]);
+    assert_eq!(
+      Err(TextSearchError::InvalidBytes),
+      CountedSet::from_bytes(&swapped)
+    );
   }
 
+  #[cfg(feature = "serde")]
   #[test]
-  fn remove_from_counted_set() {
-    let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![0, 0]);
-    assert_eq!(1, set.remove(0));
-    assert_eq!(0, set.remove(0));
-    assert_eq!(0, set.remove(0));
+  fn deserialize_counted_set() {
+    let json = r#"{"91":3,"-1":1,"0":0}"#;
+    let set: CountedSet = serde_json::from_str(json).unwrap();
+    assert_eq!(2, set.len());
+    assert_eq!(3, set.get_count(91));
+    assert_eq!(1, set.get_count(-1));
+    assert_eq!(false, set.contains(0));
+
+    let result: Result<CountedSet, _> = serde_json::from_str(r#"{"1":-1}"#);
+    assert_eq!(true, result.is_err());
   }
 
+  #[cfg(feature = "serde")]
   #[test]
-  fn remove_all_from_counted_set() {
+  fn serde_round_trip_counted_set() {
     let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![0, 0]);
-    assert_eq!(true, set.remove_all(0));
-    assert_eq!(false, set.remove_all(0));
+    for int in -500i64..500 {
+      for _ in 0..(int % 7).abs() {
+        set.insert(int);
+      }
+    }
+    set.remove_all(3);
+
+    let json = serde_json::to_string(&set).unwrap();
+    let copy: CountedSet = serde_json::from_str(&json).unwrap();
+    assert_eq!(set, copy);
   }
 
+  #[cfg(feature = "rayon")]
   #[test]
-  fn counted_set_to_vec() {
-    let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![91, 91, 123456, -1, 91, -1]);
-    let output = set.to_vec();
-    assert_eq!(3, output.len());
-    assert_eq!(91, output[0]);
-    assert_eq!(-1, output[1]);
-    assert_eq!(123456, output[2]);
+  fn par_extend_matches_extend_from_slice() {
+    use rayon::prelude::*;
+
+    let values: Vec<i64> =
+      (0..200_000).map(|i| (i * 7919) % 3001 - 1500).collect();
+    let mut expected = CountedSet::from(vec![5, 5, 9]);
+    expected.extend_from_slice(&values);
+
+    let mut set = CountedSet::from(vec![5, 5, 9]);
+    set.par_extend(values.par_iter().copied());
+    assert_eq!(expected, set);
+
+    let collected: CountedSet = values.par_iter().copied().collect();
+    assert_eq!(CountedSet::from(values), collected);
+
+    let empty: CountedSet = Vec::new().into_par_iter().collect();
+    assert_eq!(true, empty.is_empty());
   }
 
   #[test]
-  fn large_counted_set_to_vec() {
+  fn counted_set_is_send_and_sync() {
+    fn assert_send_and_sync<T: Send + Sync>() {}
+    assert_send_and_sync::<CountedSet>();
+  }
+
+  #[test]
+  fn move_counted_set_to_thread() {
     let mut set = CountedSet::new();
+    insert_integers(&mut set, vec![1, 1, 2]);
 
-    let integer_map: HashMap<i64, usize> =
-      [(-1, 4), (-9876, 3), (-12345, 1)].iter().cloned().collect();
-    let range_map: HashMap<Range<i64>, usize> =
-      [(0..1000, 2)].iter().cloned().collect();
-    let mut vec: Vec<i64> = vec![];
-    add_integers_from_map_to_vec(&mut vec, integer_map);
-    add_integers_from_range_map_to_vec(&mut vec, range_map);
-    let counted_set_length = vec.len() - 1005; // Duplicates are removed in CountedSet
+    let set = thread::spawn(move || {
+      set.insert(3);
+      set
+    })
+    .join()
+    .unwrap();
 
-    insert_integers(&mut set, vec);
-    let output = set.to_vec();
-    assert_eq!(counted_set_length, output.len());
-    assert_eq!(-1, output[0]);
-    assert_eq!(-9876, output[1]);
-    assert_eq!(-12345, output[counted_set_length - 1]);
+    assert_eq!(vec![2, 1, 1], counts(&set, &[1, 2, 3]));
   }
 
   #[test]
-  fn clone_counted_set() {
+  fn read_counted_set_from_threads() {
     let mut set = CountedSet::new();
-    insert_integers(&mut set, vec![91, 91, 123456, 91, -1]);
-
-    let copy = set.clone();
+    for int in 0..1000 {
+      for _ in 0..(int % 3) {
+        set.insert(int);
+      }
+    }
+    let set = Arc::new(set);
 
-    set.clear();
-    assert_eq!(true, set.is_empty());
+    let handles: Vec<_> = (0..4)
+      .map(|_| {
+        let set = Arc::clone(&set);
+        thread::spawn(move || {
+          for int in 0..1000 {
+            assert_eq!((int % 3) as usize, set.get_count(int));
+          }
+          set.to_vec().len()
+        })
+      })
+      .collect();
 
-    assert_eq!(3, copy.get_count(91));
-    assert_eq!(1, copy.get_count(123456));
-    assert_eq!(1, copy.get_count(-1));
+    for handle in handles {
+      assert_eq!(666, handle.join().unwrap());
+    }
   }
 
   fn add_integers_from_map_to_vec(
@@ -667,6 +6569,50 @@ mod tests {
     }
   }
 
+  /// Panics unless the set's nodes form a valid tree holding `len` values.
+  pub(crate) fn assert_consistent(set: &CountedSet) {
+    assert_eq!(true, is_valid_tree(set.nodes()));
+    let used = set.nodes().iter().filter(|node| node.count > 0).count();
+    assert_eq!(set.len(), used);
+    assert_eq!(set.len(), set.values_and_counts().len());
+  }
+
+  /// Returns the number of nodes on the longest path from the root, without
+  /// recursing.
+  fn tree_height(nodes: &[tsearch_countedset_node]) -> usize {
+    let mut height = 0;
+    let mut stack = vec![(0, 1)];
+    while let Some((index, depth)) = stack.pop() {
+      let node = match nodes.get(index) {
+        Some(node) => node,
+        None => continue,
+      };
+      height = height.max(depth);
+      stack.push((node.left, depth + 1));
+      stack.push((node.right, depth + 1));
+    }
+    height
+  }
+
+  fn counts(counted_set: &CountedSet, integers: &[i64]) -> Vec<usize> {
+    integers
+      .iter()
+      .map(|int| counted_set.get_count(*int))
+      .collect()
+  }
+
+  fn from_nodes(
+    nodes: &[tsearch_countedset_node],
+  ) -> Result<CountedSet, TextSearchError> {
+    CountedSet::from_bytes(&write_nodes(nodes))
+  }
+
+  fn hash(counted_set: &CountedSet) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    counted_set.hash(&mut hasher);
+    hasher.finish()
+  }
+
   fn insert_integers(counted_set: &mut CountedSet, integers: Vec<i64>) {
     for int in integers {
       counted_set.insert(int);
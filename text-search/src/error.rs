@@ -0,0 +1,59 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// An error returned when an operation on one of GNETextSearch's data
+/// structures fails.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "std")]
+/// # {
+/// use text_search::{counted_set::CountedSet, error::TextSearchError};
+///
+/// fn count(values: &[i64]) -> Result<CountedSet, TextSearchError> {
+///   let mut set = CountedSet::new();
+///   for value in values {
+///     set.try_insert(*value)?;
+///   }
+///   Ok(set)
+/// }
+///
+/// assert_eq!(2, count(&[1, 1, 2]).unwrap().get_count(1));
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TextSearchError {
+  /// Memory for a data structure could not be allocated.
+  AllocationFailed,
+  /// GNETextSearch reported that an operation failed, which usually means
+  /// that it could not grow one of its buffers.
+  OperationFailed,
+  /// Bytes passed to
+  /// [`CountedSet::from_bytes`](../counted_set/struct.CountedSet.html#method.from_bytes)
+  /// or
+  /// [`TernaryTree::from_bytes`](../ternary_tree/struct.TernaryTree.html#method.from_bytes)
+  /// don't describe a valid counted set or tree.
+  InvalidBytes,
+  /// A string passed to GNETextSearch contains a NUL character. C strings
+  /// end at their first NUL character, so the rest of the string would
+  /// silently be ignored.
+  ContainsNul,
+}
+
+impl fmt::Display for TextSearchError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TextSearchError::AllocationFailed => f.write_str("allocation failed"),
+      TextSearchError::OperationFailed => f.write_str("operation failed"),
+      TextSearchError::InvalidBytes => f.write_str("invalid bytes"),
+      TextSearchError::ContainsNul => {
+        f.write_str("string contains a NUL character")
+      },
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl Error for TextSearchError {}
@@ -0,0 +1,885 @@
+//! A generic counted set backed by a `HashMap`.
+//!
+//! Design
+//! ======
+//!
+//! [`CountedSet`] wraps GNETextSearch's `tsearch_countedset`, which can only
+//! hold 64-bit integers. GNETextSearch's search structures hand document ids
+//! to Rust as `CountedSet`s, so the FFI-backed set has to stay.
+//!
+//! Rust doesn't support specialization, so a single `CountedSet<T>` can't
+//! use the C tree for `i64` and a `HashMap` for everything else without
+//! checking which storage it holds in every method. Instead, the two paths
+//! are separate types with the same methods and the same semantics:
+//!
+//! - [`CountedSet`] holds `i64`s in GNETextSearch's balanced binary tree.
+//! - [`HashCountedSet<T>`] holds any `T: Hash + Eq` in a `HashMap<T, usize>`
+//!   that maps each value to its count.
+//!
+//! A value is only ever stored in the map while its count is greater than
+//! zero, so the map's length is the number of distinct values in the set.
+//!
+//! `no_std`
+//! ========
+//!
+//! With the `no_std` feature and without the default `std` feature,
+//! `HashCountedSet` only needs `core` and `alloc`. It's backed by
+//! `hashbrown`'s `HashMap` instead of the standard library's, and its
+//! default hasher is `hashbrown`'s `DefaultHashBuilder` instead of
+//! `RandomState`. `CountedSet` and the other types that wrap GNETextSearch
+//! aren't available, so the C library isn't built or linked.
+//!
+//! [`CountedSet`]: ../counted_set/struct.CountedSet.html
+//! [`HashCountedSet<T>`]: struct.HashCountedSet.html
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::{
+  borrow::Borrow,
+  cmp::Reverse,
+  fmt,
+  hash::{BuildHasher, Hash},
+  iter::FromIterator,
+};
+#[cfg(not(feature = "std"))]
+use hashbrown::{DefaultHashBuilder as RandomState, HashMap};
+#[cfg(feature = "std")]
+use std::collections::{hash_map::RandomState, HashMap};
+
+/// A counted set of any values that implement `Hash` and `Eq`, backed by a
+/// `HashMap` that maps each value to its count.
+///
+/// `HashCountedSet`, like a standard set, only holds a single copy of each
+/// value added to it. However, unlike a standard set, `HashCountedSet` keeps
+/// track of the number of times each value has been added to it.
+///
+/// `HashCountedSet` has the same methods and semantics as
+/// [`CountedSet`], which only holds 64-bit integers.
+///
+/// Like `HashMap`, `HashCountedSet` hashes values with `RandomState` by
+/// default, which resists HashDoS attacks. A faster hasher can be chosen
+/// with the `S` type parameter and [`with_hasher`](#method.with_hasher)
+/// when the values don't come from untrusted input.
+///
+/// [`CountedSet`]: ../counted_set/struct.CountedSet.html
+///
+/// # Examples
+///
+/// ```
+/// use text_search::hash_counted_set::HashCountedSet;
+///
+/// let mut set = HashCountedSet::new();
+///
+/// // Add some words.
+/// set.insert("apple");
+/// set.insert("apple");
+/// set.insert("pear");
+///
+/// // Check if the set contains a specific word.
+/// println!("{}", set.contains("apple")); // prints "true"
+/// println!("{}", set.contains("plum")); // prints "false"
+///
+/// // Check how many times a specific word has been inserted.
+/// println!("{}", set.get_count("apple")); // prints "2"
+/// println!("{}", set.get_count("pear")); // prints "1"
+/// println!("{}", set.get_count("plum")); // prints "0"
+/// ```
+pub struct HashCountedSet<T, S = RandomState> {
+  counts: HashMap<T, usize, S>,
+}
+
+impl<T: Hash + Eq> HashCountedSet<T> {
+  /// Creates an empty `HashCountedSet`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  /// let set: HashCountedSet<String> = HashCountedSet::new();
+  /// ```
+  pub fn new() -> HashCountedSet<T> {
+    HashCountedSet {
+      counts: HashMap::new(),
+    }
+  }
+
+  /// Creates an empty `HashCountedSet` with space for at least `capacity`
+  /// distinct values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::with_capacity(10);
+  /// assert_eq!(true, set.is_empty());
+  /// for value in 0..20 {
+  ///   set.insert(value);
+  /// }
+  /// assert_eq!(20, set.len());
+  /// ```
+  pub fn with_capacity(capacity: usize) -> HashCountedSet<T> {
+    HashCountedSet {
+      counts: HashMap::with_capacity(capacity),
+    }
+  }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> HashCountedSet<T, S> {
+  /// Creates an empty `HashCountedSet` which will use the specified hash
+  /// builder to hash values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::hash_map::RandomState;
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::with_hasher(RandomState::new());
+  /// set.insert(1);
+  /// assert_eq!(1, set.get_count(&1));
+  /// ```
+  pub fn with_hasher(hash_builder: S) -> HashCountedSet<T, S> {
+    HashCountedSet {
+      counts: HashMap::with_hasher(hash_builder),
+    }
+  }
+
+  /// Creates an empty `HashCountedSet` with space for at least `capacity`
+  /// distinct values, which will use the specified hash builder to hash
+  /// values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set: HashCountedSet<u32, BuildHasherDefault<DefaultHasher>> =
+  ///   HashCountedSet::with_capacity_and_hasher(10, Default::default());
+  /// set.insert(1);
+  /// assert_eq!(1, set.get_count(&1));
+  /// ```
+  pub fn with_capacity_and_hasher(
+    capacity: usize,
+    hash_builder: S,
+  ) -> HashCountedSet<T, S> {
+    HashCountedSet {
+      counts: HashMap::with_capacity_and_hasher(capacity, hash_builder),
+    }
+  }
+
+  /// Returns a reference to the set's hash builder.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::collections::hash_map::RandomState;
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let set: HashCountedSet<i32, RandomState> =
+  ///   HashCountedSet::with_hasher(RandomState::new());
+  /// let hasher: &RandomState = set.hasher();
+  /// ```
+  pub fn hasher(&self) -> &S {
+    self.counts.hasher()
+  }
+
+  /// Returns the number of elements in the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// assert_eq!(0, set.len());
+  /// set.insert('a');
+  /// assert_eq!(1, set.len());
+  /// set.insert('a');
+  /// assert_eq!(1, set.len());
+  /// set.insert('b');
+  /// assert_eq!(2, set.len());
+  /// ```
+  pub fn len(&self) -> usize {
+    self.counts.len()
+  }
+
+  /// Returns `true` if the set contains no elements.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// assert_eq!(true, set.is_empty());
+  /// set.insert('a');
+  /// assert_eq!(false, set.is_empty());
+  /// ```
+  pub fn is_empty(&self) -> bool {
+    self.counts.is_empty()
+  }
+
+  /// Clears the set, removing all values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.clear();
+  /// assert_eq!(true, set.is_empty());
+  /// ```
+  pub fn clear(&mut self) {
+    self.counts.clear()
+  }
+
+  /// Substracts the values in `other` from the set.
+  ///
+  /// If values in `other` have been added multiple times, the counts for
+  /// equivalent values in the set will be subtracted by that amount.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('b');
+  ///
+  /// let mut other = HashCountedSet::new();
+  /// other.insert('a');
+  /// other.insert('a');
+  /// other.insert('b');
+  ///
+  /// set.minus(&other);
+  /// assert_eq!(1, set.get_count(&'a'));
+  /// assert_eq!(0, set.get_count(&'b'));
+  /// ```
+  pub fn minus<S2: BuildHasher>(&mut self, other: &HashCountedSet<T, S2>) {
+    for (value, other_count) in &other.counts {
+      if let Some(count) = self.counts.get_mut(value) {
+        if *other_count >= *count {
+          self.counts.remove(value);
+        } else {
+          *count -= other_count;
+        }
+      }
+    }
+  }
+
+  /// Adds the counts of the values in `other` to the set and removes from the
+  /// set all values not also contained in `other`.
+  ///
+  /// If matching values in `other` have been added multiple times, the
+  /// counts for equivanent values in the set will be increased by that
+  /// amount. Counts saturate at `usize::MAX` instead of overflowing.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('b');
+  /// set.insert('c');
+  ///
+  /// let mut other = HashCountedSet::new();
+  /// other.insert('a');
+  /// other.insert('a');
+  /// other.insert('b');
+  /// other.insert('d');
+  ///
+  /// set.intersect(&other);
+  /// assert_eq!(5, set.get_count(&'a'));
+  /// assert_eq!(2, set.get_count(&'b'));
+  /// assert_eq!(false, set.contains(&'c'));
+  /// assert_eq!(false, set.contains(&'d'));
+  /// ```
+  pub fn intersect<S2: BuildHasher>(&mut self, other: &HashCountedSet<T, S2>) {
+    self
+      .counts
+      .retain(|value, count| match other.counts.get(value) {
+        Some(other_count) => {
+          *count = count.saturating_add(*other_count);
+          true
+        },
+        None => false,
+      })
+  }
+
+  /// Adds each value in `other` to the set.
+  ///
+  /// If matching values in `other` have been added multiple times, the
+  /// counts for equivanent values in `self` will be increased by that
+  /// amount. Counts saturate at `usize::MAX` instead of overflowing.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('b');
+  /// set.insert('c');
+  ///
+  /// let mut other = HashCountedSet::new();
+  /// other.insert('a');
+  /// other.insert('a');
+  /// other.insert('b');
+  /// other.insert('d');
+  /// other.insert('d');
+  ///
+  /// set.union(&other);
+  /// assert_eq!(5, set.get_count(&'a'));
+  /// assert_eq!(2, set.get_count(&'b'));
+  /// assert_eq!(1, set.get_count(&'c'));
+  /// assert_eq!(2, set.get_count(&'d'));
+  /// ```
+  pub fn union<S2: BuildHasher>(&mut self, other: &HashCountedSet<T, S2>)
+  where
+    T: Clone,
+  {
+    for (value, other_count) in &other.counts {
+      let count = self.counts.entry(value.clone()).or_insert(0);
+      *count = count.saturating_add(*other_count);
+    }
+  }
+
+  /// Returns `true` if the set contains the specified valued,
+  /// otherwise `false`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert(String::from("a"));
+  /// set.insert(String::from("b"));
+  /// set.insert(String::from("b"));
+  /// assert_eq!(true, set.contains("a"));
+  /// assert_eq!(true, set.contains("b"));
+  /// assert_eq!(false, set.contains("c"));
+  /// ```
+  pub fn contains<Q>(&self, value: &Q) -> bool
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.counts.contains_key(value)
+  }
+
+  /// Returns the number of times the specified value has been added to the
+  /// set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert((1, 2));
+  /// set.insert((1, 2));
+  /// set.insert((2, 1));
+  /// assert_eq!(2, set.get_count(&(1, 2)));
+  /// assert_eq!(1, set.get_count(&(2, 1)));
+  /// assert_eq!(0, set.get_count(&(3, 3)));
+  /// ```
+  pub fn get_count<Q>(&self, value: &Q) -> usize
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.counts.get(value).copied().unwrap_or(0)
+  }
+
+  /// Increments by one the count of the specified value in the set.
+  /// Returns the new count of the specified value in the set.
+  ///
+  /// The value's count saturates at `usize::MAX` instead of overflowing.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// assert_eq!(1, set.insert('a'));
+  /// assert_eq!(2, set.insert('a'));
+  /// assert_eq!(1, set.insert('b'));
+  /// ```
+  pub fn insert(&mut self, value: T) -> usize {
+    let count = self.counts.entry(value).or_insert(0);
+    *count = count.saturating_add(1);
+    *count
+  }
+
+  /// Adds a value to the set `count` times, returning the number of times
+  /// the specified value has now been added to the set.
+  ///
+  /// If `count` is `0`, the set isn't changed. The value's count saturates
+  /// at `usize::MAX`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// assert_eq!(3, set.insert_n('a', 3));
+  /// assert_eq!(5, set.insert_n('a', 2));
+  /// assert_eq!(5, set.insert_n('a', 0));
+  /// assert_eq!(0, set.insert_n('b', 0));
+  /// assert_eq!(false, set.contains(&'b'));
+  /// ```
+  pub fn insert_n(&mut self, value: T, count: usize) -> usize {
+    if count == 0 {
+      return self.get_count(&value);
+    }
+    let total = self.counts.entry(value).or_insert(0);
+    *total = total.saturating_add(count);
+    *total
+  }
+
+  /// Adds each value in the slice to the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.extend_from_slice(&['c', 'a', 'c', 'b', 'c']);
+  /// assert_eq!(3, set.len());
+  /// assert_eq!(3, set.get_count(&'c'));
+  /// assert_eq!(1, set.get_count(&'a'));
+  /// ```
+  pub fn extend_from_slice(&mut self, values: &[T])
+  where
+    T: Clone,
+  {
+    self.counts.reserve(values.len());
+    for value in values {
+      self.insert(value.clone());
+    }
+  }
+
+  /// Decrements by one the count of the specified value in the
+  /// set. Returns the new count of the specified value in the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  ///
+  /// assert_eq!(1, set.remove(&'a'));
+  /// assert_eq!(0, set.remove(&'a'));
+  /// assert_eq!(0, set.remove(&'a'));
+  /// ```
+  pub fn remove<Q>(&mut self, value: &Q) -> usize
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.remove_checked(value).unwrap_or(0)
+  }
+
+  /// Decrements by one the count of the specified value in the set.
+  ///
+  /// Returns the new count of the specified value in the set, or `None` if
+  /// the set didn't contain the value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  ///
+  /// assert_eq!(Some(1), set.remove_checked(&'a'));
+  /// assert_eq!(Some(0), set.remove_checked(&'a'));
+  /// assert_eq!(None, set.remove_checked(&'a'));
+  /// ```
+  pub fn remove_checked<Q>(&mut self, value: &Q) -> Option<usize>
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    let count = self.counts.get_mut(value)?;
+    if *count > 1 {
+      *count -= 1;
+      Some(*count)
+    } else {
+      self.counts.remove(value);
+      Some(0)
+    }
+  }
+
+  /// Removes the specified value from the set, regardless of how
+  /// many times it had been added to the set.
+  ///
+  /// Returns `true` if the value had been contained in the set,
+  /// otherwise `false`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  ///
+  /// assert_eq!(true, set.remove_all(&'a'));
+  /// assert_eq!(false, set.remove_all(&'a'));
+  /// ```
+  pub fn remove_all<Q>(&mut self, value: &Q) -> bool
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.counts.remove(value).is_some()
+  }
+
+  /// Copies the values contained in the set into a new `Vec`.
+  ///
+  /// The values in the returned `Vec` are sorted in decending order
+  /// according to how many times the values were added to the set.
+  ///
+  /// The order of values added the same number of times to the set
+  /// is undefined. So, if both `'a'` and `'b'` were each added three times
+  /// to the set, the returned `Vec` could be either `vec!['a', 'b']` or
+  /// `vec!['b', 'a']`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::hash_counted_set::HashCountedSet;
+  ///
+  /// let mut set = HashCountedSet::new();
+  /// set.insert('a');
+  /// set.insert('a');
+  /// set.insert('b');
+  /// set.insert('b');
+  /// set.insert('b');
+  /// set.insert('d');
+  ///
+  /// assert_eq!(vec!['b', 'a', 'd'], set.to_vec());
+  /// ```
+  pub fn to_vec(&self) -> Vec<T>
+  where
+    T: Clone,
+  {
+    let mut values: Vec<(&T, usize)> = self
+      .counts
+      .iter()
+      .map(|(value, count)| (value, *count))
+      .collect();
+    values.sort_by_key(|&(_, count)| Reverse(count));
+    values.into_iter().map(|(value, _)| value.clone()).collect()
+  }
+}
+
+impl<T: Clone, S: Clone> Clone for HashCountedSet<T, S> {
+  fn clone(&self) -> Self {
+    HashCountedSet {
+      counts: self.counts.clone(),
+    }
+  }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for HashCountedSet<T, S> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_map().entries(self.counts.iter()).finish()
+  }
+}
+
+impl<T, S: Default> Default for HashCountedSet<T, S> {
+  fn default() -> Self {
+    HashCountedSet {
+      counts: HashMap::default(),
+    }
+  }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> PartialEq for HashCountedSet<T, S> {
+  fn eq(&self, other: &Self) -> bool {
+    self.counts == other.counts
+  }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> Eq for HashCountedSet<T, S> {}
+
+impl<T: Hash + Eq, S: BuildHasher> Extend<T> for HashCountedSet<T, S> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    for value in iter {
+      self.insert(value);
+    }
+  }
+}
+
+impl<T: Hash + Eq, S: BuildHasher + Default> FromIterator<T>
+  for HashCountedSet<T, S>
+{
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    let mut set = HashCountedSet::default();
+    set.extend(iter);
+    set
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+  #[cfg(not(feature = "std"))]
+  use alloc::{
+    string::{String, ToString},
+    vec,
+  };
+
+  #[test]
+  fn insert_and_remove_strings() {
+    let mut set = HashCountedSet::new();
+    assert_eq!(true, set.is_empty());
+
+    assert_eq!(1, set.insert(String::from("one")));
+    assert_eq!(1, set.insert(String::from("two")));
+    assert_eq!(2, set.insert(String::from("two")));
+    assert_eq!(2, set.len());
+    assert_eq!(true, set.contains("one"));
+    assert_eq!(2, set.get_count("two"));
+    assert_eq!(0, set.get_count("three"));
+
+    assert_eq!(1, set.remove("two"));
+    assert_eq!(0, set.remove("one"));
+    assert_eq!(0, set.remove("one"));
+    assert_eq!(false, set.contains("one"));
+    assert_eq!(1, set.len());
+
+    assert_eq!(None, set.remove_checked("one"));
+    assert_eq!(1, set.insert(String::from("one")));
+    assert_eq!(Some(0), set.remove_checked("one"));
+
+    assert_eq!(true, set.remove_all("two"));
+    assert_eq!(false, set.remove_all("two"));
+    assert_eq!(true, set.is_empty());
+  }
+
+  #[test]
+  fn counts_saturate() {
+    let mut set = HashCountedSet::new();
+    assert_eq!(usize::MAX, set.insert_n('a', usize::MAX));
+    assert_eq!(usize::MAX, set.insert('a'));
+    set.insert_n('b', usize::MAX - 1);
+
+    let mut other = HashCountedSet::new();
+    other.insert_n('a', 2);
+    other.insert_n('b', 2);
+
+    let mut union = set.clone();
+    union.union(&other);
+    assert_eq!(usize::MAX, union.get_count(&'a'));
+    assert_eq!(usize::MAX, union.get_count(&'b'));
+
+    set.intersect(&other);
+    assert_eq!(usize::MAX, set.get_count(&'a'));
+    assert_eq!(usize::MAX, set.get_count(&'b'));
+  }
+
+  #[test]
+  fn insert_n_values() {
+    let mut set = HashCountedSet::new();
+    assert_eq!(0, set.insert_n("x", 0));
+    assert_eq!(true, set.is_empty());
+
+    assert_eq!(4, set.insert_n("x", 4));
+    assert_eq!(5, set.insert("x"));
+    assert_eq!(usize::MAX, set.insert_n("x", usize::MAX));
+    assert_eq!(1, set.len());
+  }
+
+  #[test]
+  fn count_tuples() {
+    let set: HashCountedSet<(u32, u32)> =
+      vec![(1, 2), (2, 1), (1, 2), (0, 0)].into_iter().collect();
+    assert_eq!(3, set.len());
+    assert_eq!(2, set.get_count(&(1, 2)));
+    assert_eq!(1, set.get_count(&(2, 1)));
+    assert_eq!(1, set.get_count(&(0, 0)));
+    assert_eq!((1, 2), set.to_vec()[0]);
+  }
+
+  #[test]
+  fn minus_intersect_and_union() {
+    let set: HashCountedSet<&str> =
+      vec!["a", "a", "a", "b", "c"].into_iter().collect();
+    let other: HashCountedSet<&str> =
+      vec!["a", "a", "b", "d", "d"].into_iter().collect();
+
+    let mut difference = set.clone();
+    difference.minus(&other);
+    assert_eq!(vec![1, 0, 1, 0], counts(&difference, &["a", "b", "c", "d"]));
+
+    let mut intersection = set.clone();
+    intersection.intersect(&other);
+    assert_eq!(
+      vec![5, 2, 0, 0],
+      counts(&intersection, &["a", "b", "c", "d"])
+    );
+    assert_eq!(2, intersection.len());
+
+    let mut union = set.clone();
+    union.union(&other);
+    assert_eq!(vec![5, 2, 1, 2], counts(&union, &["a", "b", "c", "d"]));
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn matches_ffi_counted_set() {
+    use crate::counted_set::CountedSet;
+
+    let values = vec![4, -1, 4, 9, 9, 9, 0, -1, 4, 4, 12];
+    let mut ffi_set = CountedSet::new();
+    let mut hash_set = HashCountedSet::new();
+    for value in &values {
+      assert_eq!(ffi_set.insert(*value), hash_set.insert(*value));
+    }
+
+    let other_values = vec![9, 9, 9, 9, 0, 4, 100];
+    let mut ffi_other = CountedSet::new();
+    let mut hash_other = HashCountedSet::new();
+    for value in &other_values {
+      ffi_other.insert(*value);
+      hash_other.insert(*value);
+    }
+
+    let all: Vec<i64> = vec![-1, 0, 4, 9, 12, 100];
+    let ffi_counts = |set: &CountedSet| -> Vec<usize> {
+      all.iter().map(|v| set.get_count(*v)).collect()
+    };
+
+    let (mut ffi, mut hash) = (ffi_set.clone(), hash_set.clone());
+    ffi.minus(&ffi_other);
+    hash.minus(&hash_other);
+    assert_eq!(ffi_counts(&ffi), counts(&hash, &all));
+
+    let (mut ffi, mut hash) = (ffi_set.clone(), hash_set.clone());
+    ffi.intersect(&ffi_other);
+    hash.intersect(&hash_other);
+    assert_eq!(ffi_counts(&ffi), counts(&hash, &all));
+
+    let (mut ffi, mut hash) = (ffi_set.clone(), hash_set.clone());
+    ffi.union(&ffi_other);
+    hash.union(&hash_other);
+    assert_eq!(ffi_counts(&ffi), counts(&hash, &all));
+    assert_eq!(ffi.len(), hash.len());
+  }
+
+  #[test]
+  fn custom_hasher() {
+    use core::hash::{BuildHasherDefault, Hasher};
+
+    /// A 64-bit FNV-1a hasher, standing in for a fast, non-random hasher.
+    struct Fnv(u64);
+
+    impl Default for Fnv {
+      fn default() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+      }
+    }
+
+    impl Hasher for Fnv {
+      fn finish(&self) -> u64 {
+        self.0
+      }
+
+      fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+          self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+      }
+    }
+
+    type FnvSet<T> = HashCountedSet<T, BuildHasherDefault<Fnv>>;
+
+    let mut set: FnvSet<&str> = FnvSet::with_hasher(Default::default());
+    set.insert("a");
+    set.insert_n("b", 3);
+    let mut other: FnvSet<&str> =
+      FnvSet::with_capacity_and_hasher(4, Default::default());
+    other.extend(vec!["a", "c"]);
+    assert_eq!(
+      Fnv::default().finish(),
+      set.hasher().build_hasher().finish()
+    );
+
+    set.union(&other);
+    let mut expected: HashCountedSet<&str> = HashCountedSet::new();
+    expected.union(&set);
+    assert_eq!(vec![2, 3, 1], counts(&expected, &["a", "b", "c"]));
+
+    set.minus(&expected);
+    assert_eq!(true, set.is_empty());
+
+    let collected: FnvSet<char> = "hello".chars().collect();
+    assert_eq!(2, collected.get_count(&'l'));
+    assert_eq!(
+      FnvSet::default(),
+      FnvSet::<char>::with_hasher(Default::default())
+    );
+  }
+
+  #[test]
+  fn to_vec_is_sorted_by_descending_count() {
+    let mut set = HashCountedSet::new();
+    assert_eq!(Vec::<String>::new(), set.to_vec());
+
+    for (word, count) in &[("x", 1), ("y", 4), ("z", 2)] {
+      for _ in 0..*count {
+        set.insert(word.to_string());
+      }
+    }
+    assert_eq!(vec!["y", "z", "x"], set.to_vec());
+  }
+
+  #[test]
+  fn equality_and_clear() {
+    let mut set: HashCountedSet<char> = "hello".chars().collect();
+    let mut other: HashCountedSet<char> = "olleh".chars().collect();
+    assert_eq!(set, other);
+
+    other.insert('h');
+    assert_ne!(set, other);
+
+    set.extend(vec!['h']);
+    assert_eq!(set, other);
+
+    set.clear();
+    assert_eq!(true, set.is_empty());
+    assert_eq!(HashCountedSet::default(), set);
+  }
+
+  fn counts<T: Hash + Eq>(set: &HashCountedSet<T>, values: &[T]) -> Vec<usize> {
+    values.iter().map(|value| set.get_count(value)).collect()
+  }
+}
@@ -0,0 +1,1187 @@
+use crate::{
+  counted_set::CountedSet,
+  ternary_tree::TernaryTree,
+  tokenize::{self, TokenizeOptions},
+};
+use std::{
+  cmp::Ordering,
+  collections::{HashMap, HashSet},
+};
+
+/// A full-text search index that maps the words in documents to the
+/// documents' 64-bit integer ids.
+///
+/// Documents are split into words by GNETextSearch's tokenizer and
+/// normalized with the index's
+/// [`TokenizeOptions`](../tokenize/struct.TokenizeOptions.html) (see
+/// [`tokenize_with`](../tokenize/fn.tokenize_with.html)), and each word is
+/// stored in a [`TernaryTree`](../ternary_tree/struct.TernaryTree.html)
+/// along with the ids of the documents that contain it. Queries are
+/// normalized with the same options, so they always match the words the way
+/// they were indexed. An index created with [`new`](#method.new) lowercases
+/// words, so searches aren't case sensitive. Otherwise, words are matched
+/// exactly, so punctuation is part of a word.
+///
+/// The index also records the position of each word in each document, so
+/// [`search_phrase`](#method.search_phrase) can find documents containing
+/// words next to each other. The positions take roughly four bytes per word
+/// added, in addition to the tree. The number of words in each document is
+/// recorded, too, for ranking documents with
+/// [`search_ranked`](#method.search_ranked).
+///
+/// Documents with several fields, such as a title and a body, can be added
+/// with [`add_document_fields`](#method.add_document_fields). The words in
+/// each field are also stored in a separate tree for that field, so a query
+/// can restrict a word to a field by writing it as `field:word`.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::index::Index;
+///
+/// let mut index = Index::new();
+/// index.add_document(1, "the quick brown fox");
+/// index.add_document(2, "the lazy dog");
+/// index.add_document(3, "a quick dog");
+///
+/// // Documents matching any of the words, best matches first.
+/// assert_eq!(vec![(3, 2), (1, 1), (2, 1)], index.search("quick dog"));
+///
+/// // Documents matching all of the words.
+/// assert_eq!(vec![(3, 2)], index.search_all("quick dog"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Index {
+  /// How the words in documents and queries are normalized.
+  options: TokenizeOptions,
+  tree: TernaryTree,
+  /// The positions at which each word occurs in each document, in
+  /// ascending order.
+  positions: HashMap<String, HashMap<i64, Vec<u32>>>,
+  /// The position of the next word added to each document.
+  next_positions: HashMap<i64, u32>,
+  /// The distinct words in each document, so that a document can be removed
+  /// without searching the whole tree.
+  terms: HashMap<i64, Vec<String>>,
+  /// The number of words in each document containing any words.
+  lengths: HashMap<i64, u32>,
+  /// The sum of `lengths`.
+  total_length: u64,
+  /// The words in each named field, along with the ids of the documents
+  /// containing them in that field.
+  fields: HashMap<String, TernaryTree>,
+  /// The distinct field names and words in each document added with fields.
+  field_terms: HashMap<i64, HashSet<(String, String)>>,
+}
+
+impl Index {
+  /// The `k1` parameter of [`search_ranked`](#method.search_ranked), which
+  /// limits how much repeating a word in a document raises its score.
+  pub const BM25_K1: f64 = 1.2;
+
+  /// The `b` parameter of [`search_ranked`](#method.search_ranked), which
+  /// controls how much longer documents are penalized, from `0.0` for not
+  /// at all to `1.0` for in proportion to their length.
+  pub const BM25_B: f64 = 0.75;
+
+  /// Creates an empty `Index` that lowercases the words in documents and
+  /// queries.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "Hello World");
+  ///
+  /// assert_eq!(vec![(1, 1)], index.search("hello"));
+  /// assert_eq!(vec![1], index.search_phrase("HELLO world"));
+  /// ```
+  pub fn new() -> Index {
+    Index::with_options(TokenizeOptions {
+      lowercase: true,
+      ..TokenizeOptions::default()
+    })
+  }
+
+  /// Creates an empty `Index` that normalizes the words in documents and
+  /// queries with the specified options.
+  ///
+  /// The same options are applied when adding documents and when searching,
+  /// so a query matches a document whenever their words are the same after
+  /// normalization. Words shorter than `min_token_len` are dropped from both,
+  /// so they neither match nor count as a position in a phrase. Passing
+  /// `TokenizeOptions::default()` matches words exactly.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::{index::Index, tokenize::TokenizeOptions};
+  ///
+  /// let mut index = Index::with_options(TokenizeOptions {
+  ///   lowercase: true,
+  ///   strip_diacritics: true,
+  ///   ..TokenizeOptions::default()
+  /// });
+  /// index.add_document(1, "Café Rust");
+  ///
+  /// assert_eq!(vec![(1, 2)], index.search("cafe rust"));
+  /// assert_eq!(vec![1], index.search_phrase("CAFÉ RUST"));
+  ///
+  /// let mut exact = Index::with_options(TokenizeOptions::default());
+  /// exact.add_document(1, "Café Rust");
+  /// assert_eq!(Vec::<(i64, usize)>::new(), exact.search("rust"));
+  /// ```
+  pub fn with_options(options: TokenizeOptions) -> Index {
+    Index {
+      options,
+      tree: TernaryTree::new(),
+      positions: HashMap::new(),
+      next_positions: HashMap::new(),
+      terms: HashMap::new(),
+      lengths: HashMap::new(),
+      total_length: 0,
+      fields: HashMap::new(),
+      field_terms: HashMap::new(),
+    }
+  }
+
+  /// Returns the options used to normalize the words in documents and
+  /// queries.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// assert_eq!(true, Index::new().options().lowercase);
+  /// ```
+  pub fn options(&self) -> TokenizeOptions {
+    self.options
+  }
+
+  /// Adds each word in the text to the index, associated with the
+  /// specified document id.
+  ///
+  /// Adding several texts with the same id adds their words to the same
+  /// document. Phrases never span two texts added separately.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the text contains a NUL character or if the document would
+  /// contain more than `u32::MAX` words.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "hello world");
+  /// index.add_document(1, "goodbye");
+  ///
+  /// assert_eq!(vec![(1, 1)], index.search("goodbye"));
+  /// ```
+  pub fn add_document(&mut self, id: i64, text: &str) {
+    self.add_text(id, None, text);
+  }
+
+  /// Adds the words in each field to the index, associated with the
+  /// specified document id.
+  ///
+  /// Each field is a pair of a field name, such as `"title"`, and the
+  /// field's text. The texts are added as if by calling
+  /// [`add_document`](#method.add_document) with each of them in turn, so
+  /// words without a field in a query match the words in any field, and
+  /// phrases never span two fields. In addition, a word in a query written
+  /// as `field:word` only matches documents containing the word in the
+  /// field with that name. A prefix that isn't the name of a field added to
+  /// the index is treated as part of the word.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a field name is empty or contains a colon or whitespace, if a
+  /// text contains a NUL character, or if the document would contain more
+  /// than `u32::MAX` words.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document_fields(
+  ///   1,
+  ///   &[("title", "Rust"), ("body", "Safe memory management")],
+  /// );
+  /// index.add_document_fields(
+  ///   2,
+  ///   &[("title", "Memory"), ("body", "How Rust manages memory")],
+  /// );
+  ///
+  /// assert_eq!(vec![(1, 2), (2, 2)], index.search("rust memory"));
+  /// assert_eq!(vec![(1, 2), (2, 1)], index.search("title:rust memory"));
+  /// assert_eq!(vec![(1, 2)], index.search_all("title:rust memory"));
+  /// ```
+  pub fn add_document_fields(&mut self, id: i64, fields: &[(&str, &str)]) {
+    for (field, _) in fields {
+      assert!(
+        !field.is_empty()
+          && !field.contains(|c: char| c == ':' || c.is_whitespace()),
+        "field names must not be empty or contain colons or whitespace"
+      );
+    }
+    for (field, text) in fields {
+      self.add_text(id, Some(field), text);
+    }
+  }
+
+  /// Adds the words in the text to the document with the specified id and,
+  /// if a field is specified, to that field's tree.
+  fn add_text(&mut self, id: i64, field: Option<&str>, text: &str) {
+    let words = self.words(text);
+    let next_position = self.next_positions.entry(id).or_insert(0);
+    let mut position = *next_position;
+    let mut length = 0;
+    for token in &words {
+      self.tree.insert(token, id);
+      let positions = self
+        .positions
+        .entry(token.clone())
+        .or_default()
+        .entry(id)
+        .or_default();
+      if positions.is_empty() {
+        self.terms.entry(id).or_default().push(token.clone());
+      }
+      positions.push(position);
+      length += 1;
+      position = position
+        .checked_add(1)
+        .expect("documents must contain fewer than u32::MAX words");
+    }
+    // Skipping a position keeps the last word of this text and the first
+    // word of the next one from matching a phrase.
+    *next_position = position.saturating_add(1);
+    if length > 0 {
+      *self.lengths.entry(id).or_insert(0) += length;
+      self.total_length += u64::from(length);
+    }
+
+    if let Some(field) = field {
+      let tree = self.fields.entry(field.to_owned()).or_default();
+      let terms = self.field_terms.entry(id).or_default();
+      for token in words {
+        tree.insert(&token, id);
+        terms.insert((field.to_owned(), token));
+      }
+    }
+  }
+
+  /// Removes the document with the specified id from the index, so that it
+  /// no longer matches any search. Returns `true` if the index contained the
+  /// document.
+  ///
+  /// Adding a document with the same id afterwards creates a new document
+  /// with only the new words. The index remembers which words each document
+  /// contained, so this takes time proportional to the number of distinct
+  /// words in the document rather than the size of the index.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "red apple");
+  /// index.add_document(2, "green apple");
+  ///
+  /// assert_eq!(true, index.remove_document(1));
+  /// assert_eq!(false, index.remove_document(1));
+  /// assert_eq!(vec![(2, 1)], index.search("red apple"));
+  ///
+  /// index.add_document(1, "red cherry");
+  /// assert_eq!(vec![1], index.search_phrase("red cherry"));
+  /// assert_eq!(Vec::<i64>::new(), index.search_phrase("red apple"));
+  /// ```
+  pub fn remove_document(&mut self, id: i64) -> bool {
+    self.next_positions.remove(&id);
+    if let Some(length) = self.lengths.remove(&id) {
+      self.total_length -= u64::from(length);
+    }
+    for (field, term) in self.field_terms.remove(&id).unwrap_or_default() {
+      if let Some(tree) = self.fields.get_mut(&field) {
+        tree.remove_id(&term, id);
+      }
+    }
+    let terms = match self.terms.remove(&id) {
+      Some(terms) => terms,
+      None => return false,
+    };
+    for term in terms {
+      self.tree.remove_id(&term, id);
+      if let Some(postings) = self.positions.get_mut(&term) {
+        postings.remove(&id);
+        if postings.is_empty() {
+          self.positions.remove(&term);
+        }
+      }
+    }
+    true
+  }
+
+  /// Returns the ids of the documents containing any of the words in the
+  /// query, paired with the number of distinct query words each document
+  /// contains.
+  ///
+  /// The documents matching the most words are returned first. Documents
+  /// matching the same number of words are sorted by ascending id. If the
+  /// query has no words, no documents are returned.
+  ///
+  /// A word written as `field:word` only matches the documents containing
+  /// the word in that field (see
+  /// [`add_document_fields`](#method.add_document_fields)).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the query contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "red apple");
+  /// index.add_document(2, "green apple");
+  /// index.add_document(3, "red cherry");
+  ///
+  /// assert_eq!(vec![(1, 2), (2, 1), (3, 1)], index.search("red apple"));
+  /// assert_eq!(Vec::<(i64, usize)>::new(), index.search("plum"));
+  /// ```
+  pub fn search(&self, query: &str) -> Vec<(i64, usize)> {
+    let matches = self.matches(query).0;
+    matches.most_common(matches.len())
+  }
+
+  /// Returns the ids of the documents containing any of the words in the
+  /// query, paired with their [BM25](https://en.wikipedia.org/wiki/Okapi_BM25)
+  /// scores.
+  ///
+  /// Unlike [`search`](#method.search), which counts the query words each
+  /// document contains, the score rewards words that are repeated in a
+  /// document and words that few documents contain, and it favors short
+  /// documents over long ones. Each distinct word in the query adds
+  ///
+  /// ```text
+  /// idf × tf × (k1 + 1) / (tf + k1 × (1 − b + b × length / average length))
+  /// ```
+  ///
+  /// to the score of each document containing it, where `tf` is the number
+  /// of times the word occurs in the document, `idf` is
+  /// `ln(1 + (n − df + 0.5) / (df + 0.5))`, `n` is the number of documents
+  /// in the index, and `df` is the number of documents containing the word.
+  /// `k1` is [`BM25_K1`](#associatedconstant.BM25_K1) and `b` is
+  /// [`BM25_B`](#associatedconstant.BM25_B).
+  ///
+  /// The documents with the highest scores are returned first. Documents
+  /// with the same score are sorted by ascending id. If the query has no
+  /// words, no documents are returned.
+  ///
+  /// For a word written as `field:word`, `tf` and `df` only count the
+  /// occurrences of the word in that field (see
+  /// [`add_document_fields`](#method.add_document_fields)), while the
+  /// document's length still counts the words in all of its fields.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the query contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "apple pie with apple sauce and apple juice");
+  /// index.add_document(2, "apple juice");
+  /// index.add_document(3, "cherry pie");
+  ///
+  /// let ids: Vec<i64> = index
+  ///   .search_ranked("apple cherry")
+  ///   .into_iter()
+  ///   .map(|(id, _)| id)
+  ///   .collect();
+  /// assert_eq!(vec![3, 1, 2], ids);
+  ///
+  /// let scores = index.search_ranked("juice");
+  /// assert_eq!(2, scores[0].0);
+  /// assert_eq!(true, scores[0].1 > scores[1].1);
+  /// ```
+  pub fn search_ranked(&self, query: &str) -> Vec<(i64, f64)> {
+    let terms = self.query_terms(query);
+
+    let document_count = self.lengths.len() as f64;
+    let average_length = self.total_length as f64 / document_count;
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+    for (field, term) in terms {
+      let postings = self.postings(field, &term);
+      if postings.is_empty() {
+        continue;
+      }
+      let frequency = postings.len() as f64;
+      let idf =
+        (1.0 + (document_count - frequency + 0.5) / (frequency + 0.5)).ln();
+      for (id, count) in postings.values_and_counts() {
+        let tf = count as f64;
+        let length = f64::from(self.lengths[&id]);
+        let norm = 1.0 - Self::BM25_B + Self::BM25_B * length / average_length;
+        let score =
+          idf * tf * (Self::BM25_K1 + 1.0) / (tf + Self::BM25_K1 * norm);
+        *scores.entry(id).or_insert(0.0) += score;
+      }
+    }
+
+    let mut scores: Vec<(i64, f64)> = scores.into_iter().collect();
+    scores.sort_unstable_by(|(a_id, a_score), (b_id, b_score)| {
+      b_score
+        .partial_cmp(a_score)
+        .unwrap_or(Ordering::Equal)
+        .then(a_id.cmp(b_id))
+    });
+    scores
+  }
+
+  /// Returns the ids of the documents containing every word in the query,
+  /// paired with the number of distinct words in the query.
+  ///
+  /// The documents are sorted by ascending id. If the query has no words, no
+  /// documents are returned. A word written as `field:word` only matches the
+  /// documents containing the word in that field (see
+  /// [`add_document_fields`](#method.add_document_fields)).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the query contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "red apple");
+  /// index.add_document(2, "green apple");
+  /// index.add_document(3, "red apple pie");
+  ///
+  /// assert_eq!(vec![(1, 2), (3, 2)], index.search_all("apple red"));
+  /// assert_eq!(Vec::<(i64, usize)>::new(), index.search_all("green pie"));
+  /// ```
+  pub fn search_all(&self, query: &str) -> Vec<(i64, usize)> {
+    let (matches, term_count) = self.matches(query);
+    if term_count == 0 {
+      return Vec::new();
+    }
+    let mut ids: Vec<(i64, usize)> = matches
+      .values_and_counts()
+      .into_iter()
+      .filter(|(_, count)| *count == term_count)
+      .collect();
+    ids.sort_unstable();
+    ids
+  }
+
+  /// Returns the ids of the documents containing the words in the phrase
+  /// next to each other and in the same order, sorted by ascending id.
+  ///
+  /// The phrase is split into words the same way as documents. A phrase of
+  /// a single word returns the documents containing that word, and a phrase
+  /// without any words returns no documents.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the phrase contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "the quick brown fox");
+  /// index.add_document(2, "the brown quick fox");
+  /// index.add_document(3, "quick brown");
+  ///
+  /// assert_eq!(vec![1, 3], index.search_phrase("quick brown"));
+  /// assert_eq!(vec![2], index.search_phrase("quick fox"));
+  /// assert_eq!(vec![1, 2, 3], index.search_phrase("quick"));
+  /// ```
+  pub fn search_phrase(&self, phrase: &str) -> Vec<i64> {
+    let terms = self.words(phrase);
+    match terms.len() {
+      0 => return Vec::new(),
+      1 => {
+        let mut ids = self.tree.search(&terms[0]).to_vec();
+        ids.sort_unstable();
+        return ids;
+      },
+      _ => {},
+    }
+
+    let postings: Option<Vec<&HashMap<i64, Vec<u32>>>> =
+      terms.iter().map(|term| self.positions.get(term)).collect();
+    let postings = match postings {
+      Some(postings) => postings,
+      None => return Vec::new(),
+    };
+
+    let mut ids: Vec<i64> = postings[0]
+      .iter()
+      .filter(|(id, starts)| {
+        starts
+          .iter()
+          .any(|&start| phrase_starts_at(&postings[1..], **id, start))
+      })
+      .map(|(id, _)| *id)
+      .collect();
+    ids.sort_unstable();
+    ids
+  }
+
+  /// Returns up to `limit` of the indexed words that start with the prefix,
+  /// paired with the number of documents containing each word.
+  ///
+  /// The words contained in the most documents are returned first. Words
+  /// contained in the same number of documents are sorted alphabetically.
+  /// The prefix is normalized with the index's options, apart from
+  /// `min_token_len`, so `"Ca"` suggests `"cat"` in an index created with
+  /// [`new`](#method.new). An empty prefix matches every word, returning
+  /// the most common words in the index. Words never contain whitespace, so
+  /// a prefix containing any, even a single leading or trailing space,
+  /// doesn't match any words.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the prefix contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::index::Index;
+  ///
+  /// let mut index = Index::new();
+  /// index.add_document(1, "rust runs fast");
+  /// index.add_document(2, "rust is rusty");
+  /// index.add_document(3, "rusty nails");
+  /// index.add_document(4, "rust");
+  ///
+  /// assert_eq!(
+  ///   vec![("rust".to_string(), 3), ("rusty".to_string(), 2)],
+  ///   index.suggest("Rus", 2)
+  /// );
+  /// assert_eq!(("rust".to_string(), 3), index.suggest("", 1)[0]);
+  /// ```
+  pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<(String, usize)> {
+    if limit == 0 {
+      return Vec::new();
+    }
+    let options = TokenizeOptions {
+      min_token_len: 0,
+      ..self.options
+    };
+    // The tokenizer decides what counts as whitespace, so the prefix is only
+    // used if it's a single token with nothing around it.
+    let prefix = if prefix.is_empty() {
+      String::new()
+    } else {
+      match tokenize::tokens(prefix).next() {
+        Some((token, _)) if token.len() == prefix.len() => {
+          tokenize::tokenize_with(token, &options)
+            .pop()
+            .unwrap_or_default()
+        },
+        _ => return Vec::new(),
+      }
+    };
+
+    let mut suggestions: Vec<(String, usize)> = self
+      .tree
+      .keys_with_prefix(&prefix)
+      .into_iter()
+      .filter_map(|word| {
+        let count = self.positions.get(&word)?.len();
+        Some((word, count))
+      })
+      .collect();
+    suggestions.sort_unstable_by(|(a_word, a_count), (b_word, b_count)| {
+      b_count.cmp(a_count).then_with(|| a_word.cmp(b_word))
+    });
+    suggestions.truncate(limit);
+    suggestions
+  }
+
+  /// Returns a set containing the ids of the documents matching any of the
+  /// distinct words in the query, where each id's count is the number of
+  /// words its document matched, along with the number of distinct words.
+  fn matches(&self, query: &str) -> (CountedSet, usize) {
+    let terms = self.query_terms(query);
+
+    let mut matches = CountedSet::new();
+    for (field, term) in &terms {
+      for id in self.postings(*field, term).to_vec() {
+        matches.insert(id);
+      }
+    }
+    (matches, terms.len())
+  }
+
+  /// Splits the query into its distinct normalized words, each paired with
+  /// the name of the field it's restricted to, if any.
+  fn query_terms<'q>(&self, query: &'q str) -> Vec<(Option<&'q str>, String)> {
+    let mut terms = Vec::new();
+    for (token, _) in tokenize::tokens(query) {
+      let (field, word) = match token.split_once(':') {
+        Some((field, word)) if self.fields.contains_key(field) => {
+          (Some(field), word)
+        },
+        _ => (None, token),
+      };
+      terms.extend(self.words(word).into_iter().map(|word| (field, word)));
+    }
+    terms.sort_unstable();
+    terms.dedup();
+    terms
+  }
+
+  /// Returns the ids of the documents containing the word, in the field if
+  /// one is specified, where each id's count is the number of times its
+  /// document contains the word there.
+  fn postings(&self, field: Option<&str>, word: &str) -> CountedSet {
+    match field {
+      Some(field) => self.fields[field].search(word),
+      None => self.tree.search(word),
+    }
+  }
+
+  /// Splits the text into words and normalizes them with the index's
+  /// options.
+  fn words(&self, text: &str) -> Vec<String> {
+    tokenize::tokenize_with(text, &self.options)
+  }
+}
+
+impl Default for Index {
+  fn default() -> Self {
+    Index::new()
+  }
+}
+
+/// Returns true if the document contains the words whose postings are
+/// specified at the positions following `start`, in order.
+fn phrase_starts_at(
+  postings: &[&HashMap<i64, Vec<u32>>],
+  id: i64,
+  start: u32,
+) -> bool {
+  let mut position = start;
+  for posting in postings {
+    position = match position.checked_add(1) {
+      Some(position) => position,
+      None => return false,
+    };
+    match posting.get(&id) {
+      Some(positions) if positions.binary_search(&position).is_ok() => {},
+      _ => return false,
+    }
+  }
+  true
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn search_any_word() {
+    let index = example_index();
+    assert_eq!(
+      vec![(1, 2), (4, 2), (3, 1), (5, 1)],
+      index.search("quick fox")
+    );
+    assert_eq!(vec![(2, 1), (3, 1)], index.search("lazy"));
+    assert_eq!(vec![(5, 1)], index.search("東京"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("cat"));
+  }
+
+  #[test]
+  fn search_all_words() {
+    let index = example_index();
+    assert_eq!(vec![(1, 2), (4, 2)], index.search_all("quick fox"));
+    assert_eq!(vec![(3, 2)], index.search_all("lazy quick"));
+    assert_eq!(
+      Vec::<(i64, usize)>::new(),
+      index.search_all("quick fox cat")
+    );
+  }
+
+  #[test]
+  fn repeated_words_count_once() {
+    let mut index = Index::new();
+    index.add_document(1, "spam spam spam eggs");
+    index.add_document(2, "eggs");
+
+    assert_eq!(vec![(1, 1)], index.search("spam"));
+    assert_eq!(vec![(1, 2), (2, 1)], index.search("spam eggs spam"));
+    assert_eq!(vec![(1, 2)], index.search_all("spam eggs spam"));
+  }
+
+  #[test]
+  fn search_with_empty_query() {
+    let index = example_index();
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search(""));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search(" \t\n"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search_all(""));
+  }
+
+  #[test]
+  fn search_empty_index() {
+    let index = Index::new();
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("quick"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search_all("quick"));
+  }
+
+  #[test]
+  fn words_are_matched_exactly() {
+    let mut index = Index::with_options(TokenizeOptions::default());
+    index.add_document(1, "The quick brown fox");
+    index.add_document(4, "quick fox. quick fox");
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("Quick"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("the"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("qui"));
+    assert_eq!(vec![(4, 1)], index.search("fox."));
+
+    let index = example_index();
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("qui"));
+    assert_eq!(vec![(4, 1)], index.search("fox."));
+  }
+
+  #[test]
+  fn queries_are_lowercased_by_default() {
+    let index = example_index();
+    let lowercase = TokenizeOptions {
+      lowercase: true,
+      ..TokenizeOptions::default()
+    };
+    assert_eq!(lowercase, index.options());
+    assert_eq!(vec![(1, 1), (3, 1), (4, 1)], index.search("Quick"));
+    assert_eq!(vec![(1, 1), (2, 1)], index.search("THE"));
+    assert_eq!(vec![1], index.search_phrase("the QUICK brown"));
+    assert_eq!(vec![(2, 2)], index.search_all("The LAZY"));
+    assert_eq!(
+      index.search_ranked("quick fox"),
+      index.search_ranked("QUICK Fox")
+    );
+    assert_eq!(true, Index::default().options().lowercase);
+  }
+
+  #[test]
+  fn queries_are_normalized_like_documents() {
+    let options = TokenizeOptions {
+      lowercase: true,
+      strip_diacritics: true,
+      min_token_len: 2,
+    };
+    let mut index = Index::with_options(options);
+    assert_eq!(options, index.options());
+    index.add_document(1, "Café Rust");
+    index.add_document(2, "a naïve café in Ærøskøbing");
+
+    assert_eq!(vec![(1, 2), (2, 1)], index.search("cafe rust"));
+    assert_eq!(vec![(1, 2)], index.search_all("CAFÉ RUST"));
+    assert_eq!(vec![1], index.search_phrase("cafe\u{301} rust"));
+    assert_eq!(vec![2], index.search_phrase("NAIVE cafe"));
+    assert_eq!(vec![(2, 1)], index.search("ærøskøbing"));
+
+    // Words that are too short are dropped from documents and queries alike,
+    // so they don't break up phrases.
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("a"));
+    assert_eq!(vec![2], index.search_phrase("cafe a in"));
+    assert_eq!(vec![(1, 2), (2, 1)], index.search("a cafe rust"));
+
+    assert_eq!(true, index.remove_document(1));
+    assert_eq!(vec![(2, 1)], index.search("Cafe RUST"));
+  }
+
+  #[test]
+  fn clone_index() {
+    let index = example_index();
+    let mut copy = index.clone();
+    copy.add_document(6, "quick cat");
+
+    assert_eq!(vec![(6, 1)], copy.search("cat"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("cat"));
+  }
+
+  #[test]
+  fn search_phrase_in_order() {
+    let index = example_index();
+    assert_eq!(vec![4], index.search_phrase("quick fox"));
+    assert_eq!(vec![1], index.search_phrase("quick brown"));
+    assert_eq!(vec![4], index.search_phrase("quick fox. quick"));
+    assert_eq!(vec![2], index.search_phrase("over the lazy dog"));
+    assert_eq!(vec![3], index.search_phrase("lazy but quick"));
+    assert_eq!(vec![5], index.search_phrase("fox in\t東京"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("fox quick"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("lazy quick"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("quick cat"));
+  }
+
+  #[test]
+  fn search_phrase_with_one_or_no_words() {
+    let index = example_index();
+    assert_eq!(vec![1, 3, 4], index.search_phrase("quick"));
+    assert_eq!(vec![1, 3, 4], index.search_phrase("  quick\n"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("cat"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase(""));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase(" \t"));
+  }
+
+  #[test]
+  fn search_phrase_with_repeated_words() {
+    let mut index = Index::new();
+    index.add_document(1, "spam spam eggs");
+    index.add_document(2, "spam eggs spam");
+
+    assert_eq!(vec![1], index.search_phrase("spam spam"));
+    assert_eq!(vec![1, 2], index.search_phrase("spam eggs"));
+    assert_eq!(vec![1], index.search_phrase("spam spam eggs"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("spam spam spam"));
+  }
+
+  #[test]
+  fn search_phrase_does_not_span_texts() {
+    let mut index = Index::new();
+    index.add_document(1, "hello");
+    index.add_document(1, "world");
+    index.add_document(1, "hello world");
+    index.add_document(2, "hello");
+    index.add_document(2, "world");
+
+    assert_eq!(vec![1], index.search_phrase("hello world"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("world hello"));
+    assert_eq!(
+      vec![1, 2],
+      index
+        .search("hello world")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect::<Vec<i64>>()
+    );
+  }
+
+  #[test]
+  fn remove_document() {
+    let mut index = example_index();
+    assert_eq!(true, index.remove_document(4));
+
+    assert_eq!(vec![(1, 2), (3, 1), (5, 1)], index.search("quick fox"));
+    assert_eq!(vec![(1, 2)], index.search_all("quick fox"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("fox."));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("quick fox"));
+    assert_eq!(vec![1, 3], index.search_phrase("quick"));
+
+    assert_eq!(false, index.remove_document(4));
+    assert_eq!(false, index.remove_document(42));
+    assert_eq!(vec![(1, 1), (3, 1)], index.search("quick"));
+  }
+
+  #[test]
+  fn remove_every_document() {
+    let mut index = example_index();
+    for id in 1..=5 {
+      assert_eq!(true, index.remove_document(id));
+    }
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("quick lazy fox"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("lazy dog"));
+    assert_eq!(true, index.positions.is_empty());
+    assert_eq!(true, index.terms.is_empty());
+    assert_eq!(true, index.next_positions.is_empty());
+    assert_eq!(true, index.lengths.is_empty());
+    assert_eq!(0, index.total_length);
+    assert_eq!(Vec::<(i64, f64)>::new(), index.search_ranked("quick"));
+  }
+
+  #[test]
+  fn search_ranked_matches_any_word() {
+    let index = example_index();
+    let mut ids: Vec<i64> = index
+      .search_ranked("quick fox cat")
+      .into_iter()
+      .map(|(id, _)| id)
+      .collect();
+    ids.sort_unstable();
+    assert_eq!(vec![1, 3, 4, 5], ids);
+
+    assert_eq!(Vec::<(i64, f64)>::new(), index.search_ranked("cat"));
+    assert_eq!(Vec::<(i64, f64)>::new(), index.search_ranked(""));
+    assert_eq!(Vec::<(i64, f64)>::new(), index.search_ranked(" \t"));
+    assert_eq!(Vec::<(i64, f64)>::new(), Index::new().search_ranked("fox"));
+  }
+
+  #[test]
+  fn search_ranked_scores() {
+    let mut index = Index::new();
+    index.add_document(1, "a b c d");
+    index.add_document(2, "a a");
+    index.add_document(2, "b e");
+
+    // Both documents are as long as the average document.
+    let bm25 = |tf: f64, df: f64| {
+      let idf = (1.0 + (2.0 - df + 0.5) / (df + 0.5)).ln();
+      idf * tf * (Index::BM25_K1 + 1.0) / (tf + Index::BM25_K1)
+    };
+    let scores = index.search_ranked("a c a");
+    assert_eq!(2, scores.len());
+    assert_eq!(1, scores[0].0);
+    assert_eq!(
+      true,
+      (scores[0].1 - bm25(1.0, 2.0) - bm25(1.0, 1.0)).abs() < 1e-9
+    );
+    assert_eq!(2, scores[1].0);
+    assert_eq!(true, (scores[1].1 - bm25(2.0, 2.0)).abs() < 1e-9);
+  }
+
+  #[test]
+  fn search_ranked_favors_rare_words_and_short_documents() {
+    let mut index = Index::new();
+    index.add_document(1, "common rare");
+    index.add_document(2, "common common");
+    index.add_document(3, "common");
+    index.add_document(4, "common filler filler filler filler filler");
+
+    let scores = index.search_ranked("common rare");
+    assert_eq!(1, scores[0].0);
+    let ids: Vec<i64> = scores.iter().map(|(id, _)| *id).collect();
+    assert_eq!(vec![1, 2, 3, 4], ids);
+
+    // Ties are broken by ascending id.
+    index.add_document(0, "common");
+    let ids: Vec<i64> = index
+      .search_ranked("common")
+      .into_iter()
+      .map(|(id, _)| id)
+      .collect();
+    assert_eq!(vec![2, 0, 3, 1, 4], ids);
+  }
+
+  #[test]
+  fn search_ranked_after_removing_documents() {
+    let mut index = example_index();
+    index.add_document(6, "quick quick quick");
+    index.remove_document(6);
+    index.remove_document(2);
+
+    let mut expected = Index::new();
+    expected.add_document(1, "The quick brown fox");
+    expected.add_document(3, "a lazy but quick dog");
+    expected.add_document(4, "quick fox. quick fox");
+    expected.add_document(5, "a fox\u{3000}in 東京");
+    assert_eq!(expected.total_length, index.total_length);
+    assert_eq!(
+      expected.search_ranked("quick fox lazy"),
+      index.search_ranked("quick fox lazy")
+    );
+  }
+
+  #[test]
+  fn add_document_after_removing_it() {
+    let mut index = Index::new();
+    index.add_document(1, "one two");
+    index.add_document(1, "three");
+    index.remove_document(1);
+
+    index.add_document(1, "three one");
+    assert_eq!(vec![(1, 1)], index.search("two three"));
+    assert_eq!(vec![1], index.search_phrase("three one"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("one two"));
+    assert_eq!(vec![0], index.positions["three"][&1]);
+    assert_eq!(vec![1], index.positions["one"][&1]);
+  }
+
+  #[test]
+  fn search_fields() {
+    let mut index = Index::new();
+    index.add_document_fields(
+      1,
+      &[("title", "Rust"), ("body", "Safe memory management")],
+    );
+    index.add_document_fields(
+      2,
+      &[("title", "Memory"), ("body", "How Rust manages memory")],
+    );
+    index.add_document(3, "rust memory");
+
+    assert_eq!(vec![(1, 2), (2, 2), (3, 2)], index.search("rust memory"));
+    assert_eq!(
+      vec![(1, 2), (2, 1), (3, 1)],
+      index.search("title:rust memory")
+    );
+    assert_eq!(vec![(1, 2)], index.search_all("title:rust memory"));
+    assert_eq!(
+      vec![(1, 1), (2, 1)],
+      index.search("body:rust title:rust body:rust")
+    );
+    assert_eq!(
+      Vec::<(i64, usize)>::new(),
+      index.search_all("body:rust title:rust")
+    );
+    // Field names are matched exactly, but the words are normalized.
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("TITLE:rust"));
+    assert_eq!(vec![(2, 1)], index.search("title:MEMORY"));
+    assert_eq!(vec![1, 2, 3], index.search_phrase("rust"));
+    assert_eq!(Vec::<i64>::new(), index.search_phrase("rust safe"));
+  }
+
+  #[test]
+  fn unknown_field_prefixes_are_part_of_the_word() {
+    let mut index = Index::new();
+    index.add_document_fields(1, &[("title", "rust")]);
+    index.add_document(2, "tags:rust title: :rust");
+
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("tags:memory"));
+    assert_eq!(vec![(2, 1)], index.search("tags:rust"));
+    assert_eq!(vec![(2, 1)], index.search(":rust"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("title:"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("title:tags"));
+  }
+
+  #[test]
+  fn search_ranked_in_field() {
+    let mut index = Index::new();
+    index.add_document_fields(1, &[("title", "apple"), ("body", "cherry")]);
+    index.add_document_fields(
+      2,
+      &[("title", "cherry"), ("body", "apple apple apple")],
+    );
+    index.add_document_fields(3, &[("body", "plum")]);
+
+    let ids: Vec<i64> = index
+      .search_ranked("apple")
+      .into_iter()
+      .map(|(id, _)| id)
+      .collect();
+    assert_eq!(vec![2, 1], ids);
+    let scores = index.search_ranked("title:apple");
+    assert_eq!(1, scores.len());
+    assert_eq!(1, scores[0].0);
+    assert_eq!(true, scores[0].1 > 0.0);
+  }
+
+  #[test]
+  fn remove_document_with_fields() {
+    let mut index = Index::new();
+    index.add_document_fields(1, &[("title", "rust"), ("body", "rust rust")]);
+    index.add_document_fields(2, &[("title", "rust")]);
+    index.add_document_fields(1, &[("title", "memory")]);
+
+    assert_eq!(true, index.remove_document(1));
+    assert_eq!(false, index.field_terms.contains_key(&1));
+    assert_eq!(vec![(2, 1)], index.search("title:rust"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("title:memory"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("body:rust"));
+
+    index.add_document_fields(1, &[("body", "memory")]);
+    assert_eq!(vec![(1, 1)], index.search("body:memory"));
+    assert_eq!(Vec::<(i64, usize)>::new(), index.search("title:memory"));
+  }
+
+  #[test]
+  fn invalid_field_names_panic() {
+    for field in &["", "a:b", "a b", "a\tb"] {
+      let mut index = Index::new();
+      let result = std::panic::catch_unwind(move || {
+        index.add_document_fields(1, &[("title", "rust"), (field, "text")]);
+        index
+      });
+      assert_eq!(true, result.is_err(), "{:?}", field);
+    }
+  }
+
+  #[test]
+  fn suggest_words_by_document_count() {
+    let index = example_index();
+    assert_eq!(
+      vec![
+        ("fox".to_string(), 3),
+        ("quick".to_string(), 3),
+        ("a".to_string(), 2),
+      ],
+      index.suggest("", 3)
+    );
+    assert_eq!(
+      vec![("fox".to_string(), 3), ("fox.".to_string(), 1)],
+      index.suggest("FO", 10)
+    );
+    assert_eq!(vec![("東京".to_string(), 1)], index.suggest("東", 10));
+    assert_eq!(13, index.suggest("", usize::MAX).len());
+    assert_eq!(
+      Vec::<(String, usize)>::new(),
+      index.suggest("quick fox", 10)
+    );
+    assert_eq!(Vec::<(String, usize)>::new(), index.suggest("cat", 10));
+    assert_eq!(Vec::<(String, usize)>::new(), index.suggest("q", 0));
+  }
+
+  #[test]
+  fn suggest_with_whitespace_in_prefix() {
+    let mut index = Index::new();
+    index.add_document(1, "rust rusty");
+
+    assert_eq!(2, index.suggest("rus", 10).len());
+    for prefix in &["rus ", " rus", "rus\t", "rus\u{3000}", " ", "\n", "r us"] {
+      assert_eq!(
+        Vec::<(String, usize)>::new(),
+        index.suggest(prefix, 10),
+        "{:?}",
+        prefix
+      );
+    }
+  }
+
+  #[test]
+  fn suggest_ignores_min_token_len_and_removed_documents() {
+    let mut index = Index::with_options(TokenizeOptions {
+      lowercase: true,
+      strip_diacritics: true,
+      min_token_len: 3,
+    });
+    index.add_document(1, "Café cafeteria");
+    index.add_document(2, "cafe");
+    index.add_document(3, "cafeteria");
+    assert_eq!(
+      vec![("cafe".to_string(), 2), ("cafeteria".to_string(), 2)],
+      index.suggest("Cá", 5)
+    );
+
+    index.remove_document(3);
+    index.remove_document(2);
+    assert_eq!(
+      vec![("cafe".to_string(), 1), ("cafeteria".to_string(), 1)],
+      index.suggest("c", 5)
+    );
+    index.remove_document(1);
+    assert_eq!(Vec::<(String, usize)>::new(), index.suggest("", 5));
+  }
+
+  fn example_index() -> Index {
+    let mut index = Index::new();
+    index.add_document(1, "The quick brown fox");
+    index.add_document(2, "jumps over the lazy dog");
+    index.add_document(3, "a lazy but quick dog");
+    index.add_document(4, "quick fox. quick fox");
+    index.add_document(5, "a fox\u{3000}in 東京");
+    index
+  }
+}
@@ -1,4 +1,25 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "no_std")))]
+compile_error!("text-search requires either the `std` or the `no_std` feature");
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod counted_set;
+pub mod error;
+pub mod hash_counted_set;
+#[cfg(feature = "std")]
+pub mod index;
+#[cfg(feature = "std")]
+pub mod shared_counted_set;
+#[cfg(feature = "std")]
+pub mod string_buf;
+#[cfg(feature = "std")]
+pub mod ternary_tree;
+#[cfg(feature = "std")]
+pub mod tokenize;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,320 @@
+use crate::counted_set::CountedSet;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A [`CountedSet`] that can be shared between threads and modified through
+/// a shared reference.
+///
+/// `SharedCountedSet` wraps a `CountedSet` in a `RwLock`, so every method
+/// takes `&self`. Any number of threads can read the set at once, while
+/// inserting a value waits for the readers to finish and blocks other
+/// threads until the value has been added. Wrap the set in an `Arc` to give
+/// each thread its own handle to it.
+///
+/// The lock is only held inside this type's own methods, and the only one
+/// of them that changes the set is [`insert`], which either adds the value
+/// or panics because GNETextSearch couldn't grow the set's buffer, before
+/// the set has been changed. A thread that panics while holding the lock
+/// therefore can't leave the set half-modified, so the set keeps working
+/// after such a panic instead of passing the lock's poisoning on to other
+/// threads.
+///
+/// [`CountedSet`]: ../counted_set/struct.CountedSet.html
+/// [`insert`]: #method.insert
+///
+/// # Examples
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use text_search::shared_counted_set::SharedCountedSet;
+///
+/// let set = Arc::new(SharedCountedSet::new());
+/// let workers: Vec<_> = (1..=4)
+///   .map(|worker| {
+///     let set = Arc::clone(&set);
+///     thread::spawn(move || {
+///       set.insert(0);
+///       set.insert(worker);
+///     })
+///   })
+///   .collect();
+/// for worker in workers {
+///   worker.join().unwrap();
+/// }
+///
+/// assert_eq!(4, set.get_count(0));
+/// assert_eq!(true, set.contains(4));
+/// assert_eq!(5, set.snapshot().len());
+/// ```
+#[derive(Debug, Default)]
+pub struct SharedCountedSet {
+  set: RwLock<CountedSet>,
+}
+
+impl SharedCountedSet {
+  /// Creates an empty `SharedCountedSet`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the set could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  /// let set = SharedCountedSet::new();
+  /// ```
+  pub fn new() -> SharedCountedSet {
+    SharedCountedSet::from(CountedSet::new())
+  }
+
+  /// Adds a value to the set, returning the value's new count. Waits until
+  /// no other thread is reading or modifying the set.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the set's buffer could not grow to fit the value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// assert_eq!(1, set.insert(7));
+  /// assert_eq!(2, set.insert(7));
+  /// ```
+  pub fn insert(&self, value: i64) -> usize {
+    self.write().insert(value)
+  }
+
+  /// Returns `true` if the set contains the value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// set.insert(1);
+  /// assert_eq!(true, set.contains(1));
+  /// assert_eq!(false, set.contains(2));
+  /// ```
+  pub fn contains(&self, value: i64) -> bool {
+    self.read().contains(value)
+  }
+
+  /// Returns the number of times the value has been added to the set, or
+  /// `0` if the set doesn't contain the value.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  /// assert_eq!(2, set.get_count(1));
+  /// assert_eq!(0, set.get_count(2));
+  /// ```
+  pub fn get_count(&self, value: i64) -> usize {
+    self.read().get_count(value)
+  }
+
+  /// Returns the number of distinct values in the set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// set.insert(1);
+  /// set.insert(1);
+  /// set.insert(2);
+  /// assert_eq!(2, set.len());
+  /// ```
+  pub fn len(&self) -> usize {
+    self.read().len()
+  }
+
+  /// Returns `true` if the set contains no values.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// assert_eq!(true, set.is_empty());
+  /// set.insert(1);
+  /// assert_eq!(false, set.is_empty());
+  /// ```
+  pub fn is_empty(&self) -> bool {
+    self.read().is_empty()
+  }
+
+  /// Returns a copy of the set as it is now.
+  ///
+  /// The copy is made while holding the read lock, so other threads can
+  /// keep reading the set, but inserting values waits until the copy is
+  /// finished. Copying a large set holds the lock for as long as it takes to
+  /// copy the set's buffer.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the copy could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// set.insert(1);
+  /// let snapshot = set.snapshot();
+  /// set.insert(2);
+  ///
+  /// assert_eq!(vec![1], snapshot.to_vec());
+  /// assert_eq!(2, set.len());
+  /// ```
+  pub fn snapshot(&self) -> CountedSet {
+    self.read().clone()
+  }
+
+  /// Consumes the `SharedCountedSet`, returning the wrapped set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::shared_counted_set::SharedCountedSet;
+  ///
+  /// let set = SharedCountedSet::new();
+  /// set.insert(5);
+  /// assert_eq!(vec![5], set.into_inner().to_vec());
+  /// ```
+  pub fn into_inner(self) -> CountedSet {
+    self
+      .set
+      .into_inner()
+      .unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn read(&self) -> RwLockReadGuard<'_, CountedSet> {
+    self.set.read().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn write(&self) -> RwLockWriteGuard<'_, CountedSet> {
+    self.set.write().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl From<CountedSet> for SharedCountedSet {
+  /// Wraps the set so that it can be shared between threads.
+  fn from(set: CountedSet) -> SharedCountedSet {
+    SharedCountedSet {
+      set: RwLock::new(set),
+    }
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+  use crate::counted_set::tests::assert_consistent;
+  use std::{panic, sync::Arc, thread};
+
+  #[test]
+  fn is_send_and_sync() {
+    fn assert_send_and_sync<T: Send + Sync>() {}
+    assert_send_and_sync::<SharedCountedSet>();
+  }
+
+  #[test]
+  fn insert_from_many_threads() {
+    let set = Arc::new(SharedCountedSet::new());
+    let threads: Vec<_> = (0..8)
+      .map(|thread| {
+        let set = Arc::clone(&set);
+        thread::spawn(move || {
+          for value in 0..500 {
+            set.insert(value);
+            assert_eq!(true, set.contains(value));
+          }
+          set.insert(1000 + thread);
+        })
+      })
+      .collect();
+    for thread in threads {
+      thread.join().unwrap();
+    }
+
+    let set = Arc::try_unwrap(set).unwrap().into_inner();
+    assert_eq!(508, set.len());
+    assert_eq!(true, (0..500).all(|value| set.get_count(value) == 8));
+    assert_eq!(true, (1000..1008).all(|value| set.get_count(value) == 1));
+  }
+
+  #[test]
+  fn snapshot_is_independent() {
+    let set = SharedCountedSet::from(CountedSet::from(vec![1, 1, 2]));
+    let snapshot = set.snapshot();
+    set.insert(3);
+    set.insert(1);
+
+    assert_eq!(vec![2, 1], snapshot.get_counts(&[1, 2]));
+    assert_eq!(false, snapshot.contains(3));
+    assert_eq!(3, set.get_count(1));
+    assert_eq!(3, set.len());
+  }
+
+  #[test]
+  fn usable_after_panic_while_locked() {
+    let set = Arc::new(SharedCountedSet::new());
+    set.insert(1);
+
+    let other = Arc::clone(&set);
+    let result = thread::spawn(move || {
+      let _guard = other.write();
+      panic::panic_any("panicked while holding the lock");
+    })
+    .join();
+    assert_eq!(true, result.is_err());
+    assert_eq!(true, set.set.is_poisoned());
+
+    assert_eq!(2, set.insert(1));
+    assert_eq!(2, set.get_count(1));
+    assert_eq!(vec![1], set.snapshot().to_vec());
+  }
+
+  #[test]
+  fn consistent_after_panic_while_writing() {
+    let set = Arc::new(SharedCountedSet::from(CountedSet::from(
+      (0..100).collect::<Vec<i64>>(),
+    )));
+
+    let other = Arc::clone(&set);
+    let result = thread::spawn(move || {
+      let mut guard = other.write();
+      for value in 100..200 {
+        guard.insert(value);
+      }
+      guard.retain(|value, _| {
+        if value == 150 {
+          panic::panic_any("panicked while writing");
+        }
+        value % 2 == 0
+      });
+    })
+    .join();
+    assert_eq!(true, result.is_err());
+    assert_eq!(true, set.set.is_poisoned());
+
+    assert_consistent(&set.read());
+    assert_eq!(1, set.get_count(150));
+    assert_eq!(1, set.insert(1000));
+    assert_consistent(&set.read());
+    assert_eq!(set.len(), set.snapshot().len());
+  }
+}
@@ -0,0 +1,416 @@
+use std::{io, os::raw::c_char, slice, str};
+use text_search_sys::{
+  tsearch_stringbuf_append_cstring, tsearch_stringbuf_free,
+  tsearch_stringbuf_get_buffer, tsearch_stringbuf_get_len,
+  tsearch_stringbuf_init, tsearch_stringbuf_ptr,
+};
+
+/// A growable UTF-8 string, implemented by wrapping GNETextSearch's
+/// `tsearch_stringbuf`.
+///
+/// `StringBuf` implements [`std::io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html),
+/// so it can be filled with the `write!` and `writeln!` macros.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use text_search::string_buf::StringBuf;
+///
+/// let mut buf = StringBuf::new();
+/// buf.push_str("hello");
+/// write!(buf, ", {}!", "world").unwrap();
+///
+/// assert_eq!("hello, world!", buf.as_str());
+/// ```
+#[derive(Debug)]
+pub struct StringBuf {
+  raw: tsearch_stringbuf_ptr,
+  /// The first bytes of a character whose remaining bytes haven't been
+  /// written yet.
+  pending: [u8; 4],
+  pending_len: usize,
+}
+
+impl StringBuf {
+  /// Creates an empty `StringBuf`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the underlying `tsearch_stringbuf` could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::string_buf::StringBuf;
+  /// let buf = StringBuf::new();
+  /// ```
+  pub fn new() -> StringBuf {
+    let raw = unsafe { tsearch_stringbuf_init() };
+    assert!(!raw.is_null(), "failed to allocate StringBuf");
+    StringBuf {
+      raw,
+      pending: [0; 4],
+      pending_len: 0,
+    }
+  }
+
+  /// Appends the string to the end of the buffer.
+  ///
+  /// If the bytes last passed to `write` ended in the middle of a character,
+  /// those bytes are discarded, because the string can't complete the
+  /// character.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the buffer could not grow to fit the string.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::string_buf::StringBuf;
+  ///
+  /// let mut buf = StringBuf::new();
+  /// buf.push_str("東京");
+  /// buf.push_str(" 🦀");
+  ///
+  /// assert_eq!("東京 🦀", buf.as_str());
+  /// ```
+  pub fn push_str(&mut self, s: &str) {
+    self.pending_len = 0;
+    let result = unsafe {
+      tsearch_stringbuf_append_cstring(
+        self.raw,
+        s.as_ptr() as *const c_char,
+        s.len(),
+      )
+    };
+    assert_eq!(1, result, "failed to grow StringBuf");
+  }
+
+  /// Returns the contents of the buffer as a string slice.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::string_buf::StringBuf;
+  ///
+  /// let mut buf = StringBuf::new();
+  /// assert_eq!("", buf.as_str());
+  ///
+  /// buf.push_str("café");
+  /// assert_eq!("café", buf.as_str());
+  /// ```
+  pub fn as_str(&self) -> &str {
+    let length = self.len();
+    if length == 0 {
+      return "";
+    }
+    unsafe {
+      let buffer = tsearch_stringbuf_get_buffer(self.raw) as *const u8;
+      // The buffer only ever has whole `&str`s appended to it, so its
+      // contents are always valid UTF-8.
+      str::from_utf8_unchecked(slice::from_raw_parts(buffer, length))
+    }
+  }
+
+  /// Returns the length of the buffer's contents in bytes.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::string_buf::StringBuf;
+  ///
+  /// let mut buf = StringBuf::new();
+  /// buf.push_str("über");
+  ///
+  /// assert_eq!(5, buf.len());
+  /// ```
+  pub fn len(&self) -> usize {
+    unsafe { tsearch_stringbuf_get_len(self.raw) }
+  }
+
+  /// Returns true if the buffer is empty.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::string_buf::StringBuf;
+  ///
+  /// let mut buf = StringBuf::new();
+  /// assert_eq!(true, buf.is_empty());
+  ///
+  /// buf.push_str("a");
+  /// assert_eq!(false, buf.is_empty());
+  /// ```
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl io::Write for StringBuf {
+  /// Appends the bytes to the buffer, which must contain valid UTF-8.
+  ///
+  /// A character can be split across several writes, such as when the bytes
+  /// are copied from a reader in chunks. If the bytes end in the middle of a
+  /// character, the character's first bytes are held back and appended once
+  /// the next write completes the character. Until then, they aren't part of
+  /// [`as_str`](#method.as_str).
+  ///
+  /// If the bytes contain an invalid UTF-8 sequence, only the valid bytes
+  /// before it are written and their count is returned. If the bytes start
+  /// with an invalid UTF-8 sequence, or don't complete a held back character,
+  /// nothing is written and an error of kind `InvalidData` is returned, so
+  /// the buffer always contains valid UTF-8.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use std::io::{ErrorKind, Write};
+  /// use text_search::string_buf::StringBuf;
+  ///
+  /// let mut buf = StringBuf::new();
+  /// assert_eq!(3, buf.write(b"abc").unwrap());
+  /// assert_eq!(1, buf.write(b"d\xFF").unwrap());
+  ///
+  /// let error = buf.write(b"\xFF").unwrap_err();
+  /// assert_eq!(ErrorKind::InvalidData, error.kind());
+  /// assert_eq!("abcd", buf.as_str());
+  ///
+  /// let crab = "🦀".as_bytes();
+  /// assert_eq!(2, buf.write(&crab[..2]).unwrap());
+  /// assert_eq!("abcd", buf.as_str());
+  /// assert_eq!(2, buf.write(&crab[2..]).unwrap());
+  /// assert_eq!("abcd🦀", buf.as_str());
+  /// ```
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if self.pending_len > 0 {
+      return self.write_pending(buf);
+    }
+    match str::from_utf8(buf) {
+      Ok(s) => {
+        self.push_str(s);
+        Ok(buf.len())
+      },
+      Err(error) => {
+        let (valid, rest) = buf.split_at(error.valid_up_to());
+        // `valid` is the longest prefix of `buf` that is valid UTF-8.
+        self.push_str(unsafe { str::from_utf8_unchecked(valid) });
+        if error.error_len().is_none() {
+          // The bytes end with the start of a character, which is at most
+          // three bytes long.
+          self.pending[..rest.len()].copy_from_slice(rest);
+          self.pending_len = rest.len();
+          Ok(buf.len())
+        } else if valid.is_empty() {
+          Err(invalid_utf8())
+        } else {
+          Ok(valid.len())
+        }
+      },
+    }
+  }
+
+  /// Bytes are appended to the buffer as they're written, so there is
+  /// nothing to flush.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error of kind `InvalidData` if the bytes last written ended
+  /// in the middle of a character.
+  fn flush(&mut self) -> io::Result<()> {
+    if self.pending_len > 0 {
+      Err(invalid_utf8())
+    } else {
+      Ok(())
+    }
+  }
+}
+
+impl StringBuf {
+  /// Adds bytes from the start of `buf` to the held back bytes until they
+  /// form a complete character, which is then appended to the buffer.
+  fn write_pending(&mut self, buf: &[u8]) -> io::Result<usize> {
+    for (index, byte) in buf.iter().enumerate() {
+      let mut pending = self.pending;
+      pending[self.pending_len] = *byte;
+      let len = self.pending_len + 1;
+      match str::from_utf8(&pending[..len]) {
+        Ok(c) => {
+          self.push_str(c);
+          return Ok(index + 1);
+        },
+        Err(error) if error.error_len().is_some() => {
+          self.pending_len = 0;
+          return Err(invalid_utf8());
+        },
+        Err(_) => {
+          self.pending = pending;
+          self.pending_len = len;
+        },
+      }
+    }
+    Ok(buf.len())
+  }
+}
+
+fn invalid_utf8() -> io::Error {
+  io::Error::new(
+    io::ErrorKind::InvalidData,
+    "StringBuf can only contain valid UTF-8",
+  )
+}
+
+impl Default for StringBuf {
+  fn default() -> Self {
+    StringBuf::new()
+  }
+}
+
+// SAFETY: Each `StringBuf` exclusively owns its `tsearch_stringbuf`, and
+// GNETextSearch's string buffer keeps no global or thread-local state, so it
+// can be freed or modified from any thread.
+unsafe impl Send for StringBuf {}
+
+// SAFETY: The C functions called by methods taking `&self` only read the
+// buffer.
+unsafe impl Sync for StringBuf {}
+
+impl Drop for StringBuf {
+  fn drop(&mut self) {
+    unsafe {
+      tsearch_stringbuf_free(self.raw);
+    }
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  #[test]
+  fn push_str_and_as_str() {
+    let mut buf = StringBuf::new();
+    assert_eq!("", buf.as_str());
+    assert_eq!(0, buf.len());
+    assert_eq!(true, buf.is_empty());
+
+    buf.push_str("");
+    assert_eq!("", buf.as_str());
+
+    buf.push_str("Ærøskøbing");
+    buf.push_str(" ");
+    buf.push_str("日本語 👍🏽");
+    assert_eq!("Ærøskøbing 日本語 👍🏽", buf.as_str());
+    assert_eq!("Ærøskøbing 日本語 👍🏽".len(), buf.len());
+    assert_eq!(false, buf.is_empty());
+  }
+
+  #[test]
+  fn push_str_grows_buffer() {
+    let mut buf = StringBuf::new();
+    let mut expected = String::new();
+    for i in 0..10_000 {
+      let s = format!("{}ü", i);
+      buf.push_str(&s);
+      expected.push_str(&s);
+    }
+    assert_eq!(expected, buf.as_str());
+  }
+
+  #[test]
+  fn write_macros() {
+    let mut buf = StringBuf::new();
+    write!(buf, "{} + {} = {}", 1, 2, 3).unwrap();
+    writeln!(buf).unwrap();
+    writeln!(buf, "{:?}", "東京").unwrap();
+    buf.flush().unwrap();
+    assert_eq!("1 + 2 = 3\n\"東京\"\n", buf.as_str());
+  }
+
+  #[test]
+  fn write_all_valid_utf8() {
+    let mut buf = StringBuf::new();
+    buf.write_all("café 🦀".as_bytes()).unwrap();
+    assert_eq!("café 🦀", buf.as_str());
+  }
+
+  #[test]
+  fn write_rejects_invalid_utf8() {
+    let mut buf = StringBuf::new();
+
+    let error = buf.write(&[0xFF, b'a']).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    assert_eq!("", buf.as_str());
+
+    // A lone continuation byte.
+    assert_eq!(1, buf.write(&[b'a', 0x80, b'b']).unwrap());
+    assert_eq!("a", buf.as_str());
+
+    // A character that isn't completed by the next write.
+    let crab = "🦀".as_bytes();
+    assert_eq!(3, buf.write(&[b'b', crab[0], crab[1]]).unwrap());
+    assert_eq!("ab", buf.as_str());
+    assert_eq!(true, buf.flush().is_err());
+    let error = buf.write(b"c").unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    assert_eq!("ab", buf.as_str());
+    buf.flush().unwrap();
+
+    assert_eq!(1, buf.write(b"c\xC3\xC3").unwrap());
+    assert_eq!("abc", buf.as_str());
+  }
+
+  #[test]
+  fn write_characters_split_across_writes() {
+    let mut buf = StringBuf::new();
+
+    let crab = "🦀".as_bytes();
+    assert_eq!(3, buf.write(&[b'a', crab[0], crab[1]]).unwrap());
+    assert_eq!("a", buf.as_str());
+    assert_eq!(1, buf.write(&crab[2..3]).unwrap());
+    assert_eq!("a", buf.as_str());
+    assert_eq!(1, buf.write(&crab[3..]).unwrap());
+    assert_eq!("a🦀", buf.as_str());
+    buf.flush().unwrap();
+
+    // Every chunk size splits some of the characters.
+    let text = "Ærøskøbing 日本語 👍🏽 café 🦀";
+    for size in 1..=5 {
+      let mut buf = StringBuf::new();
+      for chunk in text.as_bytes().chunks(size) {
+        buf.write_all(chunk).unwrap();
+      }
+      buf.flush().unwrap();
+      assert_eq!(text, buf.as_str());
+
+      let mut buf = StringBuf::new();
+      let mut reader = io::BufReader::with_capacity(size, text.as_bytes());
+      io::copy(&mut reader, &mut buf).unwrap();
+      assert_eq!(text, buf.as_str());
+    }
+
+    // `push_str` discards the start of an incomplete character.
+    let mut buf = StringBuf::new();
+    assert_eq!(2, buf.write(&crab[..2]).unwrap());
+    buf.push_str("b");
+    buf.flush().unwrap();
+    assert_eq!(true, buf.write(&crab[2..]).is_err());
+    assert_eq!("b", buf.as_str());
+  }
+
+  #[test]
+  fn send_to_another_thread() {
+    let mut buf = StringBuf::new();
+    buf.push_str("moved");
+    let buf = std::thread::spawn(move || {
+      buf.push_str(" across threads");
+      buf
+    })
+    .join()
+    .unwrap();
+    assert_eq!("moved across threads", buf.as_str());
+  }
+}
@@ -0,0 +1,1573 @@
+use crate::{counted_set::CountedSet, error::TextSearchError};
+use std::{
+  convert::{TryFrom, TryInto},
+  ffi::CString,
+  os::raw::{c_char, c_void},
+  slice, str,
+};
+use text_search_sys::{
+  tsearch_ternarytree_contains, tsearch_ternarytree_copy,
+  tsearch_ternarytree_copy_prefix_search_results,
+  tsearch_ternarytree_copy_search_results, tsearch_ternarytree_free,
+  tsearch_ternarytree_init, tsearch_ternarytree_insert,
+  tsearch_ternarytree_insert_count, tsearch_ternarytree_ptr,
+  tsearch_ternarytree_remove_word, tsearch_ternarytree_remove_word_id,
+  tsearch_ternarytree_visit_prefix_words,
+  tsearch_ternarytree_visit_words_within_distance,
+};
+
+/// A ternary search tree that maps string keys to 64-bit integer document ids,
+/// implemented by wrapping GNETextSearch's `tsearch_ternarytree`.
+///
+/// Each key can be associated with any number of ids, and the same id can be
+/// associated with any number of keys. The tree stores the ids associated
+/// with each key in a counted set, so inserting the same key and id several
+/// times is recorded.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::ternary_tree::TernaryTree;
+///
+/// let mut tree = TernaryTree::new();
+///
+/// // Associate some words with the documents containing them.
+/// tree.insert("apple", 1);
+/// tree.insert("apple", 2);
+/// tree.insert("apricot", 2);
+///
+/// // Check if the tree contains a specific word.
+/// println!("{}", tree.contains("apple")); // prints "true"
+/// println!("{}", tree.contains("ap")); // prints "false"
+/// ```
+///
+/// # Thread Safety
+///
+/// `TernaryTree` is both `Send` and `Sync`. A tree can be moved to another
+/// thread, and a shared `&TernaryTree` can be read from several threads at
+/// once, because none of the methods taking `&self` modify the underlying
+/// `tsearch_ternarytree`. Mutating a tree requires `&mut TernaryTree`, so
+/// writes need external synchronization, such as a `Mutex`.
+#[derive(Debug)]
+pub struct TernaryTree {
+  raw: tsearch_ternarytree_ptr,
+  /// The number of keys in the tree, which GNETextSearch doesn't keep track
+  /// of.
+  len: usize,
+}
+
+impl TernaryTree {
+  /// The version of the byte format returned by
+  /// [`to_bytes`](#method.to_bytes) and read by
+  /// [`from_bytes`](#method.from_bytes).
+  pub const BYTES_FORMAT_VERSION: u32 = 1;
+
+  /// Creates an empty `TernaryTree`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  /// let tree = TernaryTree::new();
+  /// ```
+  pub fn new() -> TernaryTree {
+    TernaryTree {
+      raw: unsafe { tsearch_ternarytree_init() },
+      len: 0,
+    }
+  }
+
+  /// Returns the number of keys in the tree.
+  ///
+  /// This counts distinct keys, not the ids associated with them, so a key
+  /// associated with several ids is only counted once, and removed keys
+  /// aren't counted even though their nodes stay in the tree until it's
+  /// [compacted](#method.compact). The number is kept up to date as keys are
+  /// inserted and removed, so this takes constant time.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apple", 2);
+  /// tree.insert("apricot", 2);
+  /// assert_eq!(2, tree.len());
+  ///
+  /// tree.remove("apple");
+  /// assert_eq!(1, tree.len());
+  /// ```
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns `true` if the tree contains no keys.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// assert_eq!(true, tree.is_empty());
+  ///
+  /// tree.insert("apple", 1);
+  /// assert_eq!(false, tree.is_empty());
+  /// ```
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Associates the specified key with the specified document id.
+  ///
+  /// Inserting an empty key does nothing, because GNETextSearch can't store
+  /// empty keys.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the key contains a NUL character. Use
+  /// [`try_insert`](#method.try_insert) to handle that case instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("café", 7);
+  /// tree.insert("", 8);
+  ///
+  /// assert_eq!(true, tree.contains("café"));
+  /// assert_eq!(false, tree.contains(""));
+  /// ```
+  pub fn insert(&mut self, key: &str, id: i64) {
+    self.try_insert(key, id).expect(NUL_MESSAGE)
+  }
+
+  /// Associates the specified key with the specified document id, returning
+  /// an error instead of panicking if the key contains a NUL character.
+  ///
+  /// GNETextSearch stores keys as C strings, which end at their first NUL
+  /// character, so such a key is rejected and the tree isn't changed,
+  /// rather than a truncated key being inserted.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`TextSearchError::ContainsNul`] if the key contains a NUL
+  /// character.
+  ///
+  /// [`TextSearchError::ContainsNul`]: ../error/enum.TextSearchError.html#variant.ContainsNul
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::{error::TextSearchError, ternary_tree::TernaryTree};
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// assert_eq!(Ok(()), tree.try_insert("ab", 1));
+  /// assert_eq!(Err(TextSearchError::ContainsNul), tree.try_insert("a\0b", 2));
+  /// assert_eq!(false, tree.contains("a"));
+  /// assert_eq!(1, tree.len());
+  /// ```
+  pub fn try_insert(
+    &mut self,
+    key: &str,
+    id: i64,
+  ) -> Result<(), TextSearchError> {
+    if key.is_empty() {
+      return Ok(());
+    }
+    let key = try_c_string(key)?;
+    unsafe {
+      if !tsearch_ternarytree_contains(self.raw, key.as_ptr()) {
+        self.len += 1;
+      }
+      self.raw = tsearch_ternarytree_insert(self.raw, key.as_ptr(), id);
+    }
+    Ok(())
+  }
+
+  /// Removes the specified key and all of the ids associated with it.
+  /// Returns `true` if the tree contained the key.
+  ///
+  /// The key's nodes stay in the tree as tombstones, because other keys may
+  /// share them, so removing keys doesn't free memory. Call
+  /// [`compact`](#method.compact) after removing many keys to rebuild the
+  /// tree without them.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the key contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apples", 2);
+  ///
+  /// assert_eq!(true, tree.remove("apple"));
+  /// assert_eq!(false, tree.remove("apple"));
+  /// assert_eq!(false, tree.contains("apple"));
+  /// assert_eq!(vec!["apples"], tree.keys_with_prefix("app"));
+  /// ```
+  pub fn remove(&mut self, key: &str) -> bool {
+    if key.is_empty() {
+      return false;
+    }
+    let key = c_string(key);
+    let mut did_remove = false;
+    let result = unsafe {
+      tsearch_ternarytree_remove_word(self.raw, key.as_ptr(), &mut did_remove)
+    };
+    assert_eq!(1, result, "failed to remove key from TernaryTree");
+    if did_remove {
+      self.len -= 1;
+    }
+    did_remove
+  }
+
+  /// Removes the specified id from the ids associated with the specified
+  /// key, however many times it was inserted. Returns `true` if the id was
+  /// associated with the key.
+  ///
+  /// The key stays in the tree as long as other ids are associated with it.
+  /// Removing its last id removes the key, as if by calling
+  /// [`remove`](#method.remove).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the key contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apple", 2);
+  ///
+  /// assert_eq!(true, tree.remove_id("apple", 1));
+  /// assert_eq!(false, tree.remove_id("apple", 1));
+  /// assert_eq!(vec![2], tree.search("apple").to_vec());
+  ///
+  /// assert_eq!(true, tree.remove_id("apple", 2));
+  /// assert_eq!(false, tree.contains("apple"));
+  /// ```
+  pub fn remove_id(&mut self, key: &str, id: i64) -> bool {
+    if key.is_empty() {
+      return false;
+    }
+    let key = c_string(key);
+    let mut did_remove = false;
+    let result = unsafe {
+      tsearch_ternarytree_remove_word_id(
+        self.raw,
+        key.as_ptr(),
+        id,
+        &mut did_remove,
+      )
+    };
+    assert_eq!(1, result, "failed to remove id from TernaryTree");
+    if did_remove
+      && !unsafe { tsearch_ternarytree_contains(self.raw, key.as_ptr()) }
+    {
+      self.len -= 1;
+    }
+    did_remove
+  }
+
+  /// Rebuilds the tree without the nodes left behind by removed keys.
+  ///
+  /// Removing keys only marks them as removed, so a tree whose keys change
+  /// often keeps growing and its searches pass through nodes that no longer
+  /// lead to any key. Compacting copies every key and its ids into a new,
+  /// balanced tree, which takes time proportional to the size of the tree,
+  /// so it's best done periodically, after many removals, rather than after
+  /// each one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("banana", 2);
+  /// tree.remove("apple");
+  ///
+  /// tree.compact();
+  /// assert_eq!(vec!["banana"], tree.keys_with_prefix(""));
+  /// ```
+  pub fn compact(&mut self) {
+    let mut compacted = TernaryTree::new();
+    compacted.insert_balanced(&self.entries());
+    *self = compacted;
+  }
+
+  /// Returns `true` if the specified key has been inserted into the tree,
+  /// otherwise `false`.
+  ///
+  /// Only whole keys match. Use a prefix search to find keys that start
+  /// with a string.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the key contains a NUL character. Use
+  /// [`try_contains`](#method.try_contains) to handle that case instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  ///
+  /// assert_eq!(true, tree.contains("apple"));
+  /// assert_eq!(false, tree.contains("app"));
+  /// assert_eq!(false, tree.contains("apples"));
+  /// ```
+  pub fn contains(&self, key: &str) -> bool {
+    self.try_contains(key).expect(NUL_MESSAGE)
+  }
+
+  /// Returns `true` if the tree contains the specified key, or an error
+  /// instead of panicking if the key contains a NUL character.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`TextSearchError::ContainsNul`] if the key contains a NUL
+  /// character.
+  ///
+  /// [`TextSearchError::ContainsNul`]: ../error/enum.TextSearchError.html#variant.ContainsNul
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::{error::TextSearchError, ternary_tree::TernaryTree};
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("a", 1);
+  ///
+  /// assert_eq!(Ok(true), tree.try_contains("a"));
+  /// assert_eq!(Err(TextSearchError::ContainsNul), tree.try_contains("a\0"));
+  /// ```
+  pub fn try_contains(&self, key: &str) -> Result<bool, TextSearchError> {
+    if key.is_empty() {
+      return Ok(false);
+    }
+    let key = try_c_string(key)?;
+    Ok(unsafe { tsearch_ternarytree_contains(self.raw, key.as_ptr()) })
+  }
+
+  /// Returns the ids associated with the specified key.
+  ///
+  /// Each id's count in the returned set is the number of times the id was
+  /// inserted under the key. If the key isn't in the tree, the returned set
+  /// is empty.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the key contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apple", 1);
+  /// tree.insert("apple", 2);
+  /// tree.insert("apples", 3);
+  ///
+  /// let ids = tree.search("apple");
+  /// assert_eq!(2, ids.get_count(1));
+  /// assert_eq!(1, ids.get_count(2));
+  /// assert_eq!(false, ids.contains(3));
+  /// assert_eq!(true, tree.search("app").is_empty());
+  /// ```
+  pub fn search(&self, key: &str) -> CountedSet {
+    if key.is_empty() {
+      return CountedSet::new();
+    }
+    let key = c_string(key);
+    unsafe {
+      let raw = tsearch_ternarytree_copy_search_results(self.raw, key.as_ptr());
+      if raw.is_null() {
+        CountedSet::new()
+      } else {
+        CountedSet::from_raw(raw)
+      }
+    }
+  }
+
+  /// Returns the ids associated with every key that starts with the specified
+  /// prefix. An empty prefix matches every key in the tree.
+  ///
+  /// Each id's count in the returned set is the number of times the id was
+  /// inserted under a matching key. So, an id inserted once under each of
+  /// three matching keys has a count of `3`. If no keys match the prefix, the
+  /// returned set is empty.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the prefix contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apricot", 1);
+  /// tree.insert("apricot", 2);
+  /// tree.insert("banana", 3);
+  ///
+  /// let ids = tree.search_prefix("ap");
+  /// assert_eq!(2, ids.get_count(1));
+  /// assert_eq!(1, ids.get_count(2));
+  /// assert_eq!(false, ids.contains(3));
+  ///
+  /// assert_eq!(3, tree.search_prefix("").len());
+  /// assert_eq!(true, tree.search_prefix("cherry").is_empty());
+  /// ```
+  pub fn search_prefix(&self, prefix: &str) -> CountedSet {
+    let prefix = c_string(prefix);
+    unsafe {
+      let raw = tsearch_ternarytree_copy_prefix_search_results(
+        self.raw,
+        prefix.as_ptr(),
+      );
+      if raw.is_null() {
+        CountedSet::new()
+      } else {
+        CountedSet::from_raw(raw)
+      }
+    }
+  }
+
+  /// Returns every key in the tree that starts with the specified prefix, in
+  /// ascending order. An empty prefix returns every key in the tree.
+  ///
+  /// Every matching key is copied into the returned `Vec`, so a short prefix
+  /// in a large tree can return a very large number of keys. When only the
+  /// first few matches are needed, such as for autocompletion, prefer
+  /// [`keys_with_prefix_limited`](#method.keys_with_prefix_limited).
+  ///
+  /// # Panics
+  ///
+  /// Panics if the prefix contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apricot", 1);
+  /// tree.insert("apple", 2);
+  /// tree.insert("app", 3);
+  /// tree.insert("banana", 4);
+  ///
+  /// assert_eq!(vec!["app", "apple", "apricot"], tree.keys_with_prefix("ap"));
+  /// assert_eq!(4, tree.keys_with_prefix("").len());
+  /// assert_eq!(true, tree.keys_with_prefix("c").is_empty());
+  /// ```
+  pub fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+    self.keys_with_prefix_limited(prefix, usize::MAX)
+  }
+
+  /// Returns up to `limit` of the keys in the tree that start with the
+  /// specified prefix, in ascending order. An empty prefix matches every key
+  /// in the tree.
+  ///
+  /// The tree stops being searched as soon as `limit` keys have been found,
+  /// so the returned keys are the first `limit` keys that
+  /// [`keys_with_prefix`](#method.keys_with_prefix) would return.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the prefix contains a NUL character.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// for (id, key) in ["cat", "car", "cart", "care", "dog"].iter().enumerate() {
+  ///   tree.insert(key, id as i64);
+  /// }
+  ///
+  /// assert_eq!(vec!["car", "care"], tree.keys_with_prefix_limited("ca", 2));
+  /// assert_eq!(true, tree.keys_with_prefix_limited("ca", 0).is_empty());
+  /// ```
+  pub fn keys_with_prefix_limited(
+    &self,
+    prefix: &str,
+    limit: usize,
+  ) -> Vec<String> {
+    let mut keys = KeysWithPrefix {
+      keys: Vec::new(),
+      limit,
+    };
+    if limit == 0 {
+      return keys.keys;
+    }
+    let prefix = c_string(prefix);
+    let result = unsafe {
+      tsearch_ternarytree_visit_prefix_words(
+        self.raw,
+        prefix.as_ptr(),
+        Some(push_key),
+        &mut keys as *mut KeysWithPrefix as *mut c_void,
+      )
+    };
+    assert_eq!(1, result, "failed to allocate the buffer for keys");
+    keys.keys
+  }
+
+  /// Returns every key in the tree within `max_edits` edits of the specified
+  /// key, paired with each id associated with it, for typo-tolerant search.
+  ///
+  /// The distance between two keys is their Levenshtein distance: the number
+  /// of characters that have to be inserted, deleted, or substituted to turn
+  /// one into the other. Characters are Unicode code points, so a multi-byte
+  /// character counts as a single edit. The tree is searched once, skipping
+  /// branches whose keys are all too far from the key, so searches with a
+  /// `max_edits` of 1 or 2 stay fast in large trees.
+  ///
+  /// The keys are returned in ascending order, and the ids of each key in
+  /// ascending order. The key itself is returned if the tree contains it.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the key contains a NUL character or if the search's buffers
+  /// could not be allocated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("color", 1);
+  /// tree.insert("colour", 2);
+  /// tree.insert("cooler", 3);
+  /// tree.insert("café", 4);
+  ///
+  /// assert_eq!(
+  ///   vec![("color".to_string(), 1)],
+  ///   tree.search_within_distance("colr", 1)
+  /// );
+  /// assert_eq!(3, tree.search_within_distance("colr", 2).len());
+  /// assert_eq!(
+  ///   vec![("café".to_string(), 4)],
+  ///   tree.search_within_distance("cafe", 1)
+  /// );
+  /// ```
+  pub fn search_within_distance(
+    &self,
+    key: &str,
+    max_edits: u32,
+  ) -> Vec<(String, i64)> {
+    let mut keys = KeysWithPrefix {
+      keys: Vec::new(),
+      limit: usize::MAX,
+    };
+    let target = c_string(key);
+    let result = unsafe {
+      tsearch_ternarytree_visit_words_within_distance(
+        self.raw,
+        target.as_ptr(),
+        max_edits as usize,
+        Some(push_key),
+        &mut keys as *mut KeysWithPrefix as *mut c_void,
+      )
+    };
+    assert_eq!(1, result, "failed to allocate the buffers for the search");
+
+    let mut matches = Vec::new();
+    for key in keys.keys {
+      let mut ids = self.search(&key).to_vec();
+      ids.sort_unstable();
+      matches.extend(ids.into_iter().map(|id| (key.clone(), id)));
+    }
+    matches
+  }
+}
+
+impl TernaryTree {
+  /// Serializes the tree's keys and the ids associated with them, so the
+  /// tree can be written to a file and later rebuilt with
+  /// [`from_bytes`](#method.from_bytes).
+  ///
+  /// GNETextSearch allocates each of the tree's nodes separately, so the
+  /// bytes are a copy of the tree's contents rather than of its memory.
+  ///
+  /// # Format
+  ///
+  /// Every integer is stored in little-endian byte order, so the bytes can be
+  /// read on any platform. The bytes start with a header:
+  ///
+  /// | Offset | Type     | Field                                        |
+  /// |--------|----------|----------------------------------------------|
+  /// | 0      | `[u8; 4]`| The magic bytes `b"TSTT"`                    |
+  /// | 4      | `u32`    | The format version, currently `1`            |
+  /// | 8      | `u64`    | The number of keys                           |
+  ///
+  /// The header is followed by each key in ascending order:
+  ///
+  /// | Type             | Field                                        |
+  /// |------------------|----------------------------------------------|
+  /// | `u64`            | The key's length in bytes                    |
+  /// | `[u8]`           | The key, in UTF-8                            |
+  /// | `u64`            | The number of ids associated with the key    |
+  /// | `[(i64, u64)]`   | Each id and its count, in ascending order    |
+  ///
+  /// # Versioning
+  ///
+  /// The format version is
+  /// [`BYTES_FORMAT_VERSION`](#associatedconstant.BYTES_FORMAT_VERSION). If
+  /// the format ever changes, the version will be incremented, and
+  /// `from_bytes` will keep reading version 1 bytes.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apple", 2);
+  ///
+  /// let bytes = tree.to_bytes();
+  /// assert_eq!(b"TSTT", &bytes[..4]);
+  /// assert_eq!(16 + 8 + 5 + 8 + 2 * 16, bytes.len());
+  /// ```
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let entries = self.entries();
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(BYTES_MAGIC);
+    bytes.extend_from_slice(&TernaryTree::BYTES_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (key, ids) in entries {
+      let key = key.as_bytes();
+      bytes.extend_from_slice(&(key.len() as u64).to_le_bytes());
+      bytes.extend_from_slice(key);
+      bytes.extend_from_slice(&(ids.len() as u64).to_le_bytes());
+      for (id, count) in ids {
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&(count as u64).to_le_bytes());
+      }
+    }
+    bytes
+  }
+
+  /// Rebuilds a tree from bytes returned by [`to_bytes`](#method.to_bytes).
+  ///
+  /// The rebuilt tree contains the same keys, each associated with the same
+  /// ids and counts, so it answers every query exactly like the original
+  /// tree. The keys are inserted starting from the middle one, so the tree
+  /// is well balanced even though the keys are stored in ascending order.
+  ///
+  /// # Errors
+  ///
+  /// Returns `TextSearchError::InvalidBytes` if the bytes don't start with
+  /// the format's header, have an unsupported version, are truncated or
+  /// followed by extra bytes, or describe an empty key, a key that isn't
+  /// valid UTF-8 or contains a NUL character, a key without ids, or an id
+  /// with a count of zero.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("apple", 1);
+  /// tree.insert("apricot", 2);
+  ///
+  /// let copy = TernaryTree::from_bytes(&tree.to_bytes()).unwrap();
+  /// assert_eq!(true, copy.contains("apple"));
+  /// assert_eq!(2, copy.search_prefix("ap").len());
+  ///
+  /// assert_eq!(true, TernaryTree::from_bytes(b"TSTT").is_err());
+  /// ```
+  pub fn from_bytes(bytes: &[u8]) -> Result<TernaryTree, TextSearchError> {
+    let entries = read_entries(bytes).ok_or(TextSearchError::InvalidBytes)?;
+    let mut tree = TernaryTree::new();
+    tree.insert_balanced(&entries);
+    Ok(tree)
+  }
+
+  /// Returns every key in the tree, in ascending order, with its ids and
+  /// their counts sorted by id.
+  fn entries(&self) -> Vec<KeyEntry> {
+    self
+      .keys_with_prefix("")
+      .into_iter()
+      .map(|key| {
+        let mut ids = self.search(&key).values_and_counts();
+        ids.sort_unstable();
+        (c_string(&key), ids)
+      })
+      .collect()
+  }
+
+  /// Inserts the middle entry first and then each half the same way, so
+  /// that sorted entries don't turn the tree's levels into linked lists.
+  fn insert_balanced(&mut self, entries: &[KeyEntry]) {
+    if entries.is_empty() {
+      return;
+    }
+    let middle = entries.len() / 2;
+    let (key, ids) = &entries[middle];
+    // The same key may appear more than once in bytes passed to
+    // `from_bytes`, in which case its ids are merged.
+    if !unsafe { tsearch_ternarytree_contains(self.raw, key.as_ptr()) } {
+      self.len += 1;
+    }
+    for (id, count) in ids {
+      unsafe {
+        self.raw =
+          tsearch_ternarytree_insert_count(self.raw, key.as_ptr(), *id, *count);
+      }
+    }
+    self.insert_balanced(&entries[..middle]);
+    self.insert_balanced(&entries[middle + 1..]);
+  }
+}
+
+/// A key in a `TernaryTree` and its ids and their counts.
+type KeyEntry = (CString, Vec<(i64, usize)>);
+
+/// The first bytes of every serialized `TernaryTree`.
+const BYTES_MAGIC: &[u8; 4] = b"TSTT";
+
+/// Reads the keys and ids from bytes returned by `TernaryTree::to_bytes`.
+/// Returns `None` if the bytes are invalid.
+fn read_entries(mut bytes: &[u8]) -> Option<Vec<KeyEntry>> {
+  if take(&mut bytes, 4)? != BYTES_MAGIC {
+    return None;
+  }
+  let version = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().ok()?);
+  if version != TernaryTree::BYTES_FORMAT_VERSION {
+    return None;
+  }
+
+  let key_count = read_u64(&mut bytes)?;
+  let mut entries = Vec::new();
+  for _ in 0..key_count {
+    let key_len = usize::try_from(read_u64(&mut bytes)?).ok()?;
+    let key = str::from_utf8(take(&mut bytes, key_len)?).ok()?;
+    if key.is_empty() {
+      return None;
+    }
+    let key = CString::new(key).ok()?;
+
+    let id_count = read_u64(&mut bytes)?;
+    if id_count == 0 {
+      return None;
+    }
+    let mut ids = Vec::new();
+    for _ in 0..id_count {
+      let id = read_u64(&mut bytes)? as i64;
+      let count = usize::try_from(read_u64(&mut bytes)?).ok()?;
+      if count == 0 {
+        return None;
+      }
+      ids.push((id, count));
+    }
+    entries.push((key, ids));
+  }
+
+  if bytes.is_empty() {
+    Some(entries)
+  } else {
+    None
+  }
+}
+
+/// Removes the first `len` bytes from `bytes` and returns them, or returns
+/// `None` if there are fewer than `len` bytes.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+  if bytes.len() < len {
+    return None;
+  }
+  let (taken, rest) = bytes.split_at(len);
+  *bytes = rest;
+  Some(taken)
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Option<u64> {
+  Some(u64::from_le_bytes(take(bytes, 8)?.try_into().ok()?))
+}
+
+/// The keys found by a search and the number of keys to stop at.
+struct KeysWithPrefix {
+  keys: Vec<String>,
+  limit: usize,
+}
+
+/// Appends the key, which is `length` bytes long, to the `KeysWithPrefix`
+/// pointed to by `context`. Returns `false` to stop the search once the limit
+/// has been reached.
+unsafe extern "C" fn push_key(
+  key: *const c_char,
+  length: usize,
+  context: *mut c_void,
+) -> bool {
+  let keys = &mut *(context as *mut KeysWithPrefix);
+  let bytes = slice::from_raw_parts(key as *const u8, length);
+  // Keys are only inserted as `&str`s, so they're valid UTF-8.
+  keys.keys.push(String::from_utf8_lossy(bytes).into_owned());
+  keys.keys.len() < keys.limit
+}
+
+impl Clone for TernaryTree {
+  /// Returns a copy of the tree.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::ternary_tree::TernaryTree;
+  ///
+  /// let mut tree = TernaryTree::new();
+  /// tree.insert("one", 1);
+  ///
+  /// let mut copy = tree.clone();
+  /// copy.insert("two", 2);
+  ///
+  /// assert_eq!(true, copy.contains("one"));
+  /// assert_eq!(true, copy.contains("two"));
+  /// assert_eq!(false, tree.contains("two"));
+  /// ```
+  fn clone(&self) -> TernaryTree {
+    let raw = unsafe { tsearch_ternarytree_copy(self.raw) };
+    assert!(!raw.is_null(), "failed to copy TernaryTree");
+    TernaryTree { raw, len: self.len }
+  }
+}
+
+impl Default for TernaryTree {
+  fn default() -> Self {
+    TernaryTree::new()
+  }
+}
+
+// SAFETY: Each `TernaryTree` exclusively owns its `tsearch_ternarytree` and
+// the counted sets of ids stored in it, and GNETextSearch's ternary tree keeps
+// no global or thread-local state, so it can be freed or modified from any
+// thread.
+unsafe impl Send for TernaryTree {}
+
+// SAFETY: The C functions called by methods taking `&self` only read the
+// tree's nodes and the counted sets of ids stored in them.
+unsafe impl Sync for TernaryTree {}
+
+impl Drop for TernaryTree {
+  fn drop(&mut self) {
+    unsafe {
+      tsearch_ternarytree_free(self.raw);
+    }
+  }
+}
+
+const NUL_MESSAGE: &str = "TernaryTree keys must not contain NUL characters";
+
+fn c_string(key: &str) -> CString {
+  try_c_string(key).expect(NUL_MESSAGE)
+}
+
+fn try_c_string(key: &str) -> Result<CString, TextSearchError> {
+  CString::new(key).map_err(|_| TextSearchError::ContainsNul)
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+  use std::{sync::Arc, thread};
+
+  #[test]
+  fn insert_and_contains() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(false, tree.contains("a"));
+
+    tree.insert("banana", 1);
+    tree.insert("band", 2);
+    tree.insert("ban", 3);
+    tree.insert("apple", 4);
+    tree.insert("cherry", 5);
+
+    for key in &["banana", "band", "ban", "apple", "cherry"] {
+      assert_eq!(true, tree.contains(key), "{}", key);
+    }
+    for key in &["b", "ba", "bana", "bandana", "apples", "c", "zebra"] {
+      assert_eq!(false, tree.contains(key), "{}", key);
+    }
+  }
+
+  #[test]
+  fn insert_same_key_many_times() {
+    let mut tree = TernaryTree::new();
+    for id in 0..100 {
+      tree.insert("word", id);
+      tree.insert("word", id);
+    }
+    assert_eq!(true, tree.contains("word"));
+    assert_eq!(false, tree.contains("words"));
+  }
+
+  #[test]
+  fn insert_multi_byte_keys() {
+    let mut tree = TernaryTree::new();
+    let keys = ["café", "über", "東京", "🦀", "cafe"];
+    for (id, key) in keys.iter().enumerate() {
+      tree.insert(key, id as i64);
+    }
+    for key in &keys {
+      assert_eq!(true, tree.contains(key), "{}", key);
+    }
+    assert_eq!(false, tree.contains("caf"));
+    assert_eq!(false, tree.contains("東"));
+    assert_eq!(false, tree.contains("über alles"));
+  }
+
+  #[test]
+  fn search_key() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(true, tree.search("a").is_empty());
+
+    tree.insert("ban", 1);
+    tree.insert("banana", 1);
+    tree.insert("banana", 2);
+    tree.insert("banana", 2);
+    tree.insert("東京", 3);
+
+    assert_eq!(vec![1, 0, 0], counts(&tree.search("ban"), &[1, 2, 3]));
+    assert_eq!(vec![1, 2, 0], counts(&tree.search("banana"), &[1, 2, 3]));
+    assert_eq!(vec![0, 0, 1], counts(&tree.search("東京"), &[1, 2, 3]));
+    assert_eq!(true, tree.search("bana").is_empty());
+    assert_eq!(true, tree.search("bananas").is_empty());
+    assert_eq!(true, tree.search("").is_empty());
+  }
+
+  #[test]
+  fn search_prefix() {
+    let mut tree = TernaryTree::new();
+    tree.insert("ban", 1);
+    tree.insert("banana", 1);
+    tree.insert("band", 2);
+    tree.insert("bandana", 2);
+    tree.insert("bandana", 3);
+    tree.insert("apple", 4);
+
+    let ids = tree.search_prefix("ban");
+    assert_eq!(vec![2, 2, 1, 0], counts(&ids, &[1, 2, 3, 4]));
+
+    let ids = tree.search_prefix("band");
+    assert_eq!(vec![0, 2, 1, 0], counts(&ids, &[1, 2, 3, 4]));
+
+    let ids = tree.search_prefix("bandana");
+    assert_eq!(vec![0, 1, 1, 0], counts(&ids, &[1, 2, 3, 4]));
+
+    let ids = tree.search_prefix("a");
+    assert_eq!(vec![0, 0, 0, 1], counts(&ids, &[1, 2, 3, 4]));
+
+    assert_eq!(true, tree.search_prefix("bandanas").is_empty());
+    assert_eq!(true, tree.search_prefix("c").is_empty());
+    assert_eq!(true, tree.search_prefix("0").is_empty());
+  }
+
+  #[test]
+  fn search_empty_prefix() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(true, tree.search_prefix("").is_empty());
+
+    tree.insert("z", 10);
+    tree.insert("東京", 10);
+    tree.insert("a", 20);
+    let ids = tree.search_prefix("");
+    assert_eq!(vec![2, 1], counts(&ids, &[10, 20]));
+    assert_eq!(2, ids.len());
+  }
+
+  #[test]
+  fn search_prefix_of_empty_tree() {
+    let tree = TernaryTree::new();
+    assert_eq!(true, tree.search_prefix("a").is_empty());
+    assert_eq!(true, tree.search_prefix("東").is_empty());
+  }
+
+  #[test]
+  fn search_multi_byte_prefix() {
+    let mut tree = TernaryTree::new();
+    tree.insert("東京", 1);
+    tree.insert("東北", 2);
+    tree.insert("京都", 3);
+
+    let ids = tree.search_prefix("東");
+    assert_eq!(vec![1, 1, 0], counts(&ids, &[1, 2, 3]));
+
+    let ids = tree.search_prefix("東京");
+    assert_eq!(vec![1, 0, 0], counts(&ids, &[1, 2, 3]));
+  }
+
+  #[test]
+  fn keys_with_prefix() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(Vec::<String>::new(), tree.keys_with_prefix(""));
+    assert_eq!(Vec::<String>::new(), tree.keys_with_prefix("a"));
+
+    let mut keys = vec![
+      "band",
+      "ban",
+      "banana",
+      "bandana",
+      "apple",
+      "b",
+      "cherry",
+      "Zebra",
+      "über",
+      "ubiquitous",
+      "東京",
+      "東",
+      "café",
+      "cafe",
+      "caf",
+      "🦀",
+    ];
+    for (id, key) in keys.iter().enumerate() {
+      tree.insert(key, id as i64);
+    }
+    keys.sort();
+
+    assert_eq!(keys, tree.keys_with_prefix(""));
+    assert_eq!(
+      vec!["b", "ban", "banana", "band", "bandana"],
+      tree.keys_with_prefix("b")
+    );
+    assert_eq!(vec!["band", "bandana"], tree.keys_with_prefix("band"));
+    assert_eq!(vec!["caf", "cafe", "café"], tree.keys_with_prefix("caf"));
+    assert_eq!(vec!["東", "東京"], tree.keys_with_prefix("東"));
+    assert_eq!(vec!["über"], tree.keys_with_prefix("ü"));
+    assert_eq!(Vec::<String>::new(), tree.keys_with_prefix("bandanas"));
+    assert_eq!(Vec::<String>::new(), tree.keys_with_prefix("d"));
+    assert_eq!(Vec::<String>::new(), tree.keys_with_prefix("a\u{E9}"));
+  }
+
+  #[test]
+  fn keys_with_prefix_limited() {
+    let mut tree = TernaryTree::new();
+    let mut keys: Vec<String> = (0..500).map(|i| format!("k{}ü", i)).collect();
+    for (id, key) in keys.iter().enumerate() {
+      tree.insert(key, id as i64);
+    }
+    keys.sort();
+
+    assert_eq!(keys[..10].to_vec(), tree.keys_with_prefix_limited("k", 10));
+    assert_eq!(keys, tree.keys_with_prefix_limited("", 1000));
+    assert_eq!(
+      vec!["k100ü", "k101ü", "k102ü"],
+      tree.keys_with_prefix_limited("k1", 3)
+    );
+    assert_eq!(vec!["k499ü"], tree.keys_with_prefix_limited("k499", 5));
+    assert_eq!(Vec::<String>::new(), tree.keys_with_prefix_limited("k", 0));
+  }
+
+  #[test]
+  fn keys_with_prefix_of_long_keys() {
+    let mut tree = TernaryTree::new();
+    let long = "ü".repeat(300);
+    tree.insert(&long, 1);
+    tree.insert(&format!("{}x", long), 2);
+    tree.insert("ü", 3);
+
+    assert_eq!(
+      vec!["ü".to_string(), long.clone(), format!("{}x", long)],
+      tree.keys_with_prefix("ü")
+    );
+    assert_eq!(
+      vec![format!("{}x", long)],
+      tree.keys_with_prefix(&format!("{}x", long))
+    );
+  }
+
+  #[test]
+  fn empty_keys_are_ignored() {
+    let mut tree = TernaryTree::new();
+    tree.insert("", 1);
+    assert_eq!(false, tree.contains(""));
+
+    tree.insert("a", 1);
+    tree.insert("", 2);
+    assert_eq!(false, tree.contains(""));
+    assert_eq!(true, tree.contains("a"));
+  }
+
+  #[test]
+  #[should_panic(expected = "NUL")]
+  fn insert_key_containing_nul() {
+    let mut tree = TernaryTree::new();
+    tree.insert("a\0b", 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "NUL")]
+  fn contains_key_containing_nul() {
+    TernaryTree::new().contains("ab\0");
+  }
+
+  #[test]
+  fn try_insert_and_try_contains_reject_nul() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(Ok(()), tree.try_insert("a", 1));
+    for key in &["a\0b", "ab\0", "\0", "\0a"] {
+      assert_eq!(Err(TextSearchError::ContainsNul), tree.try_insert(key, 2));
+      assert_eq!(Err(TextSearchError::ContainsNul), tree.try_contains(key));
+    }
+    assert_eq!(1, tree.len());
+    assert_eq!(vec!["a"], tree.keys_with_prefix(""));
+    assert_eq!(vec![1], tree.search("a").to_vec());
+
+    assert_eq!(Ok(()), tree.try_insert("", 3));
+    assert_eq!(Ok(false), tree.try_contains(""));
+    assert_eq!(Ok(true), tree.try_contains("a"));
+    assert_eq!(Ok(false), tree.try_contains("ab"));
+  }
+
+  #[test]
+  fn clone_ternary_tree() {
+    let mut tree = TernaryTree::new();
+    for (id, key) in ["one", "two", "three", "thirty"].iter().enumerate() {
+      tree.insert(key, id as i64);
+    }
+
+    let copy = tree.clone();
+    drop(tree);
+
+    for key in &["one", "two", "three", "thirty"] {
+      assert_eq!(true, copy.contains(key), "{}", key);
+    }
+    assert_eq!(false, copy.contains("thirt"));
+
+    let empty = TernaryTree::new().clone();
+    assert_eq!(false, empty.contains("one"));
+  }
+
+  #[test]
+  fn read_ternary_tree_from_many_threads() {
+    let mut tree = TernaryTree::new();
+    for id in 0..500 {
+      tree.insert(&format!("key{}", id), id);
+    }
+
+    let tree = Arc::new(tree);
+    let handles: Vec<_> = (0..4)
+      .map(|thread| {
+        let tree = Arc::clone(&tree);
+        thread::spawn(move || {
+          (0..500)
+            .filter(|id| id % 4 == thread)
+            .all(|id| tree.contains(&format!("key{}", id)))
+        })
+      })
+      .collect();
+
+    for handle in handles {
+      assert_eq!(true, handle.join().unwrap());
+    }
+  }
+
+  fn counts(counted_set: &CountedSet, integers: &[i64]) -> Vec<usize> {
+    integers
+      .iter()
+      .map(|int| counted_set.get_count(*int))
+      .collect()
+  }
+
+  #[test]
+  fn remove_keys() {
+    let mut tree = TernaryTree::new();
+    for (id, key) in ["ban", "band", "banana", "bandana", "apple"]
+      .iter()
+      .enumerate()
+    {
+      tree.insert(key, id as i64);
+    }
+
+    assert_eq!(true, tree.remove("band"));
+    assert_eq!(false, tree.remove("band"));
+    assert_eq!(false, tree.contains("band"));
+    assert_eq!(true, tree.search("band").is_empty());
+    assert_eq!(true, tree.contains("ban"));
+    assert_eq!(true, tree.contains("bandana"));
+    assert_eq!(
+      vec!["ban", "banana", "bandana"],
+      tree.keys_with_prefix("ban")
+    );
+    assert_eq!(vec![0, 2, 3], sorted(tree.search_prefix("ban").to_vec()));
+    assert_eq!(vec![3], tree.search_prefix("band").to_vec());
+
+    assert_eq!(false, tree.remove("bandanas"));
+    assert_eq!(false, tree.remove("b"));
+    assert_eq!(false, tree.remove(""));
+    assert_eq!(false, tree.remove("cherry"));
+    assert_eq!(4, tree.keys_with_prefix("").len());
+
+    // Removed keys can be inserted again.
+    tree.insert("band", 9);
+    assert_eq!(vec![9], tree.search("band").to_vec());
+  }
+
+  #[test]
+  fn remove_ids() {
+    let mut tree = TernaryTree::new();
+    tree.insert("word", 1);
+    tree.insert("word", 1);
+    tree.insert("word", 2);
+    tree.insert("words", 1);
+
+    assert_eq!(true, tree.remove_id("word", 1));
+    assert_eq!(false, tree.remove_id("word", 1));
+    assert_eq!(false, tree.remove_id("word", 3));
+    assert_eq!(false, tree.remove_id("wor", 2));
+    assert_eq!(false, tree.remove_id("", 2));
+    assert_eq!(vec![(2, 1)], tree.search("word").values_and_counts());
+    assert_eq!(vec![1], tree.search("words").to_vec());
+    assert_eq!(2, tree.search_prefix("wor").len());
+
+    assert_eq!(true, tree.remove_id("word", 2));
+    assert_eq!(false, tree.contains("word"));
+    assert_eq!(vec!["words"], tree.keys_with_prefix(""));
+    assert_eq!(vec![1], tree.search_prefix("wor").to_vec());
+  }
+
+  #[test]
+  fn remove_from_empty_tree() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(false, tree.remove("a"));
+    assert_eq!(false, tree.remove_id("a", 1));
+    tree.compact();
+    assert_eq!(true, tree.keys_with_prefix("").is_empty());
+  }
+
+  #[test]
+  fn compact_after_removals() {
+    let mut tree = TernaryTree::new();
+    for i in 0..1000 {
+      tree.insert(&format!("key{}", i), i);
+      tree.insert(&format!("key{}", i), i + 1);
+    }
+    for i in (0..1000).filter(|i| i % 3 != 0) {
+      assert_eq!(true, tree.remove(&format!("key{}", i)));
+    }
+    tree.remove_id("key0", 1);
+
+    let before = tree.to_bytes();
+    let keys = tree.keys_with_prefix("");
+    tree.compact();
+    assert_eq!(334, keys.len());
+    assert_eq!(keys, tree.keys_with_prefix(""));
+    assert_eq!(before, tree.to_bytes());
+    assert_eq!(vec![0], tree.search("key0").to_vec());
+    assert_eq!(false, tree.contains("key1"));
+    assert_eq!(
+      tree.search_prefix("key9").len(),
+      TernaryTree::from_bytes(&before)
+        .unwrap()
+        .search_prefix("key9")
+        .len()
+    );
+
+    tree.insert("key1", 5);
+    assert_eq!(true, tree.contains("key1"));
+  }
+
+  #[test]
+  fn len_counts_keys() {
+    let mut tree = TernaryTree::new();
+    assert_eq!(0, tree.len());
+    assert_eq!(true, tree.is_empty());
+
+    tree.insert("", 1);
+    assert_eq!(0, tree.len());
+    for i in 0..500 {
+      let key = format!("key{}", i % 100);
+      tree.insert(&key, i);
+      tree.insert(&key[..4], i);
+    }
+    // "key0" to "key9" are both their own keys and prefixes of others.
+    assert_eq!(100, tree.len());
+    assert_eq!(tree.keys_with_prefix("").len(), tree.len());
+
+    assert_eq!(true, tree.remove("key1"));
+    assert_eq!(false, tree.remove("key1"));
+    assert_eq!(false, tree.remove("ke"));
+    assert_eq!(99, tree.len());
+    assert_eq!(true, tree.contains("key10"));
+
+    // The key stays until its last id is removed.
+    let ids = tree.search("key42").to_vec();
+    assert_eq!(5, ids.len());
+    for id in &ids {
+      assert_eq!(99, tree.len());
+      assert_eq!(true, tree.remove_id("key42", *id));
+    }
+    assert_eq!(98, tree.len());
+    assert_eq!(false, tree.remove_id("key42", ids[0]));
+    assert_eq!(false, tree.remove_id("key43", -1));
+    assert_eq!(98, tree.len());
+
+    tree.insert("key42", 1);
+    assert_eq!(99, tree.len());
+    assert_eq!(tree.keys_with_prefix("").len(), tree.len());
+
+    let copy = tree.clone();
+    tree.compact();
+    assert_eq!(99, tree.len());
+    assert_eq!(99, copy.len());
+    assert_eq!(99, TernaryTree::from_bytes(&copy.to_bytes()).unwrap().len());
+
+    for key in copy.keys_with_prefix("") {
+      tree.remove(&key);
+    }
+    assert_eq!(true, tree.is_empty());
+    assert_eq!(false, copy.is_empty());
+  }
+
+  #[test]
+  fn len_of_tree_from_bytes_with_repeated_keys() {
+    let mut bytes = b"TSTT".to_vec();
+    bytes.extend_from_slice(&TernaryTree::BYTES_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&3u64.to_le_bytes());
+    for key in &["same", "other", "same"] {
+      bytes.extend_from_slice(&(key.len() as u64).to_le_bytes());
+      bytes.extend_from_slice(key.as_bytes());
+      bytes.extend_from_slice(&1u64.to_le_bytes());
+      bytes.extend_from_slice(&7i64.to_le_bytes());
+      bytes.extend_from_slice(&1u64.to_le_bytes());
+    }
+
+    let tree = TernaryTree::from_bytes(&bytes).unwrap();
+    assert_eq!(2, tree.len());
+    assert_eq!(2, tree.search("same").get_count(7));
+  }
+
+  #[test]
+  fn to_bytes_and_from_bytes_round_trip() {
+    let mut tree = TernaryTree::new();
+    let words = ["banana", "band", "ban", "apple", "cherry", "café", "東京"];
+    for (id, word) in words.iter().enumerate() {
+      tree.insert(word, id as i64);
+      tree.insert(word, -(id as i64));
+      tree.insert(word, i64::MAX);
+    }
+    tree.insert("band", 1);
+
+    let copy = TernaryTree::from_bytes(&tree.to_bytes()).unwrap();
+    assert_eq!(tree.keys_with_prefix(""), copy.keys_with_prefix(""));
+    assert_eq!(tree.to_bytes(), copy.to_bytes());
+    for word in &["banana", "band", "ban", "b", "東京", "cafe", "zebra"] {
+      assert_eq!(tree.contains(word), copy.contains(word), "{}", word);
+      assert_eq!(tree.search(word), copy.search(word), "{}", word);
+      assert_eq!(
+        tree.search_prefix(word),
+        copy.search_prefix(word),
+        "{}",
+        word
+      );
+    }
+    assert_eq!(2, copy.search("band").get_count(1));
+  }
+
+  #[test]
+  fn to_bytes_empty_tree() {
+    let bytes = TernaryTree::new().to_bytes();
+    assert_eq!(16, bytes.len());
+    let copy = TernaryTree::from_bytes(&bytes).unwrap();
+    assert_eq!(Vec::<String>::new(), copy.keys_with_prefix(""));
+  }
+
+  #[test]
+  fn from_bytes_balances_sorted_keys() {
+    let mut tree = TernaryTree::new();
+    for i in 0..2000 {
+      tree.insert(&format!("{:05}", i), i);
+    }
+    let copy = TernaryTree::from_bytes(&tree.to_bytes()).unwrap();
+    for i in 0..2000 {
+      assert_eq!(true, copy.search(&format!("{:05}", i)).contains(i));
+    }
+  }
+
+  #[test]
+  fn from_bytes_rejects_invalid_bytes() {
+    let mut tree = TernaryTree::new();
+    tree.insert("key", 7);
+    let bytes = tree.to_bytes();
+
+    let invalid = |bytes: &[u8]| {
+      assert_eq!(
+        Some(TextSearchError::InvalidBytes),
+        TernaryTree::from_bytes(bytes).err()
+      );
+    };
+
+    invalid(&[]);
+    invalid(&bytes[..bytes.len() - 1]);
+    invalid(&[&bytes[..], &[0]].concat());
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    invalid(&wrong_magic);
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = 2;
+    invalid(&wrong_version);
+
+    // The key "key" starts at offset 24.
+    let mut invalid_utf8 = bytes.clone();
+    invalid_utf8[24] = 0xFF;
+    invalid(&invalid_utf8);
+
+    let mut nul = bytes.clone();
+    nul[25] = 0;
+    invalid(&nul);
+
+    // The id's count is the last eight bytes.
+    let mut zero_count = bytes.clone();
+    let len = zero_count.len();
+    zero_count[len - 8] = 0;
+    invalid(&zero_count);
+
+    let mut empty_key = Vec::new();
+    empty_key.extend_from_slice(&bytes[..16]);
+    empty_key.extend_from_slice(&0u64.to_le_bytes());
+    empty_key.extend_from_slice(&bytes[bytes.len() - 24..]);
+    invalid(&empty_key);
+
+    let mut no_ids = bytes[..27].to_vec();
+    no_ids.extend_from_slice(&0u64.to_le_bytes());
+    invalid(&no_ids);
+  }
+
+  #[test]
+  fn search_within_distance_matches_levenshtein() {
+    let words = [
+      "a", "an", "and", "band", "bandana", "banana", "bend", "brand", "café",
+      "cafe", "caffè", "naïve", "naive", "東京", "京都", "東", "🦀", "🦀🦀",
+      "crab", "Ærø", "aero",
+    ];
+    let mut tree = TernaryTree::new();
+    for (id, word) in words.iter().enumerate() {
+      tree.insert(word, id as i64);
+    }
+    let mut sorted_words = words.to_vec();
+    sorted_words.sort_unstable();
+
+    let queries = [
+      "", "a", "and", "bnd", "banan", "cafe", "cafè", "naive", "東", "京東",
+      "🦀", "crabs", "aerø", "zzzzzz",
+    ];
+    for query in &queries {
+      for max_edits in 0..4 {
+        let expected: Vec<(String, i64)> = sorted_words
+          .iter()
+          .filter(|word| levenshtein(query, word) <= max_edits as usize)
+          .map(|word| {
+            let id = words.iter().position(|w| w == word).unwrap();
+            (word.to_string(), id as i64)
+          })
+          .collect();
+        assert_eq!(
+          expected,
+          tree.search_within_distance(query, max_edits),
+          "{:?} within {}",
+          query,
+          max_edits
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn search_within_distance_returns_every_id() {
+    let mut tree = TernaryTree::new();
+    tree.insert("cat", 3);
+    tree.insert("cat", 1);
+    tree.insert("cat", 3);
+    tree.insert("cut", 2);
+    tree.insert("cart", 4);
+    assert_eq!(
+      vec![
+        ("cart".to_string(), 4),
+        ("cat".to_string(), 1),
+        ("cat".to_string(), 3),
+        ("cut".to_string(), 2),
+      ],
+      tree.search_within_distance("cat", 1)
+    );
+
+    tree.remove("cut");
+    tree.remove_id("cat", 1);
+    assert_eq!(
+      vec![("cart".to_string(), 4), ("cat".to_string(), 3)],
+      tree.search_within_distance("cat", u32::MAX)
+    );
+    assert_eq!(
+      Vec::<(String, i64)>::new(),
+      TernaryTree::new().search_within_distance("cat", 2)
+    );
+  }
+
+  #[test]
+  fn search_within_distance_of_long_keys() {
+    let mut tree = TernaryTree::new();
+    let long = "ü".repeat(100);
+    tree.insert(&long, 1);
+    tree.insert(&format!("{}x", long), 2);
+    let query = format!("{}u", "ü".repeat(99));
+    assert_eq!(
+      vec![(long.clone(), 1)],
+      tree.search_within_distance(&query, 1)
+    );
+    assert_eq!(2, tree.search_within_distance(&query, 2).len());
+  }
+
+  #[test]
+  #[should_panic(expected = "NUL")]
+  fn search_within_distance_of_key_containing_nul() {
+    TernaryTree::new().search_within_distance("a\0b", 1);
+  }
+
+  fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+      let mut diagonal = row[0];
+      row[0] = i + 1;
+      for (j, b) in b.iter().enumerate() {
+        let substitution = diagonal + if a == *b { 0 } else { 1 };
+        diagonal = row[j + 1];
+        row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+      }
+    }
+    row[b.len()]
+  }
+
+  fn sorted(mut values: Vec<i64>) -> Vec<i64> {
+    values.sort_unstable();
+    values
+  }
+}
@@ -0,0 +1,1014 @@
+use crate::{error::TextSearchError, string_buf::StringBuf};
+use std::{
+  borrow::Cow,
+  collections::HashSet,
+  ffi::CString,
+  hash::BuildHasher,
+  iter::FusedIterator,
+  ops::Range,
+  os::raw::{c_char, c_void},
+  slice,
+};
+use text_search_sys::{
+  tsearch_cstring_tokenize, tsearch_range, tsearch_string_next_token,
+};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Splits the input into tokens using GNETextSearch's UTF-8 tokenizer and
+/// returns the tokens in the order they appear in the input.
+///
+/// Tokens are separated by whitespace: spaces (including the ideographic
+/// space and the Unicode spaces from U+2002 to U+200B), tabs, and newlines.
+/// The tokenizer decodes the input one code point at a time, so multi-byte
+/// characters are never split. Empty tokens are never returned, so each
+/// token can be inserted straight into a
+/// [`TernaryTree`](../ternary_tree/struct.TernaryTree.html).
+///
+/// # Panics
+///
+/// Panics if the input contains a NUL character or if the tokenizer's buffer
+/// could not be allocated. Use [`try_tokenize`](fn.try_tokenize.html) to
+/// handle those cases instead.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::tokenize;
+///
+/// assert_eq!(vec!["café", "über"], tokenize("café  über"));
+/// assert_eq!(vec!["東京", "大阪"], tokenize("東京\u{3000}大阪\n"));
+/// assert_eq!(Vec::<String>::new(), tokenize(" \t "));
+/// ```
+pub fn tokenize(input: &str) -> Vec<String> {
+  match try_tokenize(input) {
+    Ok(tokens) => tokens,
+    Err(TextSearchError::ContainsNul) => {
+      panic!("tokenized strings must not contain NUL characters")
+    },
+    Err(_) => panic!("failed to allocate the tokenizer's buffer"),
+  }
+}
+
+/// Splits the input into tokens like [`tokenize`](fn.tokenize.html),
+/// returning an error instead of panicking if the input can't be tokenized.
+///
+/// GNETextSearch's tokenizer reads the input as a C string, which would end
+/// at the first NUL character, so an input containing one is rejected
+/// rather than having its remaining tokens silently dropped.
+///
+/// # Errors
+///
+/// Returns [`TextSearchError::ContainsNul`] if the input contains a NUL
+/// character, or [`TextSearchError::AllocationFailed`] if the tokenizer's
+/// buffer could not be allocated.
+///
+/// [`TextSearchError::ContainsNul`]: ../error/enum.TextSearchError.html#variant.ContainsNul
+/// [`TextSearchError::AllocationFailed`]: ../error/enum.TextSearchError.html#variant.AllocationFailed
+///
+/// # Examples
+///
+/// ```
+/// use text_search::{error::TextSearchError, tokenize::try_tokenize};
+///
+/// assert_eq!(Ok(vec!["a".to_string(), "b".to_string()]), try_tokenize("a b"));
+/// assert_eq!(Err(TextSearchError::ContainsNul), try_tokenize("a\0b"));
+/// ```
+pub fn try_tokenize(input: &str) -> Result<Vec<String>, TextSearchError> {
+  let input = CString::new(input).map_err(|_| TextSearchError::ContainsNul)?;
+  let mut tokens: Vec<String> = Vec::new();
+  let result = unsafe {
+    tsearch_cstring_tokenize(
+      input.as_ptr(),
+      Some(push_token),
+      &mut tokens as *mut Vec<String> as *mut c_void,
+    )
+  };
+  if result == 1 {
+    Ok(tokens)
+  } else {
+    Err(TextSearchError::AllocationFailed)
+  }
+}
+
+/// Options that control how [`tokenize_with`](fn.tokenize_with.html)
+/// normalizes tokens.
+///
+/// The default options don't change the tokens, so `tokenize_with` with
+/// `TokenizeOptions::default()` returns the same tokens as
+/// [`tokenize`](fn.tokenize.html).
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::{tokenize_with, TokenizeOptions};
+///
+/// let options = TokenizeOptions {
+///   lowercase: true,
+///   strip_diacritics: true,
+///   ..TokenizeOptions::default()
+/// };
+/// assert_eq!(vec!["cafe", "cafe", "cafe"], tokenize_with("Café cafe CAFE", &options));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TokenizeOptions {
+  /// Converts each token to lowercase using Unicode's case mappings, so
+  /// `"ÉCOLE"` becomes `"école"` and `"ÆRØ"` becomes `"ærø"`.
+  pub lowercase: bool,
+  /// Removes combining marks, such as accents, from each token. Tokens are
+  /// decomposed into base characters and combining marks (Unicode
+  /// normalization form D), the marks are dropped, and the rest is
+  /// recomposed (form C). Letters that don't decompose, such as `ø` or `ß`,
+  /// are kept as they are.
+  pub strip_diacritics: bool,
+  /// Drops tokens with fewer than this many characters, counted in code
+  /// points after the other options have been applied.
+  pub min_token_len: usize,
+}
+
+/// Splits the input into tokens like [`tokenize`](fn.tokenize.html), then
+/// normalizes each token according to the options.
+///
+/// The options only change the characters within each token, so tokens are
+/// still split on the same whitespace and never within a code point.
+///
+/// # Panics
+///
+/// Panics if the input contains a NUL character or if the tokenizer's buffer
+/// could not be allocated.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::{tokenize_with, TokenizeOptions};
+///
+/// let options = TokenizeOptions {
+///   lowercase: true,
+///   strip_diacritics: true,
+///   min_token_len: 3,
+/// };
+/// assert_eq!(
+///   vec!["uber", "naive", "ærøskøbing"],
+///   tokenize_with("Über a naïve Ærøskøbing", &options)
+/// );
+/// ```
+pub fn tokenize_with(input: &str, options: &TokenizeOptions) -> Vec<String> {
+  tokenize(input)
+    .into_iter()
+    .map(|token| normalize(token, options))
+    .filter(|token| token.chars().count() >= options.min_token_len)
+    .collect()
+}
+
+/// Splits the input into tokens like [`tokenize_with`](fn.tokenize_with.html)
+/// and drops the tokens that are stopwords, such as `"the"` or `"and"`.
+///
+/// Each stopword is normalized with the same options as the tokens before
+/// they're compared, so with `lowercase` enabled, `"The"` in the input
+/// matches the stopword `"THE"`, and with `strip_diacritics` enabled,
+/// `"café"` matches `"cafe"`. Normalizing the stopwords takes time
+/// proportional to their number on every call, so prefer tokenizing a whole
+/// document at once over tokenizing it one line at a time.
+///
+/// # Panics
+///
+/// Panics if the input contains a NUL character or if the tokenizer's buffer
+/// could not be allocated.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use text_search::tokenize::{tokenize_filtered, TokenizeOptions};
+///
+/// let stopwords: HashSet<String> =
+///   vec!["the".to_string(), "and".to_string(), "of".to_string()]
+///     .into_iter()
+///     .collect();
+/// let options = TokenizeOptions {
+///   lowercase: true,
+///   ..TokenizeOptions::default()
+/// };
+///
+/// assert_eq!(
+///   vec!["cat", "hat"],
+///   tokenize_filtered("The cat AND the hat", &options, &stopwords)
+/// );
+/// assert_eq!(
+///   vec!["The", "cat", "AND", "hat"],
+///   tokenize_filtered(
+///     "The cat AND the hat",
+///     &TokenizeOptions::default(),
+///     &stopwords
+///   )
+/// );
+/// ```
+pub fn tokenize_filtered<S: BuildHasher>(
+  input: &str,
+  options: &TokenizeOptions,
+  stopwords: &HashSet<String, S>,
+) -> Vec<String> {
+  let stopwords: HashSet<String> = stopwords
+    .iter()
+    .map(|stopword| normalize(stopword.clone(), options))
+    .collect();
+  let mut tokens = tokenize_with(input, options);
+  tokens.retain(|token| !stopwords.contains(token));
+  tokens
+}
+
+/// Applies the options that change the characters in a token.
+fn normalize(token: String, options: &TokenizeOptions) -> String {
+  let token = if options.lowercase {
+    token.to_lowercase()
+  } else {
+    token
+  };
+  if options.strip_diacritics {
+    token
+      .nfd()
+      .filter(|c| !is_combining_mark(*c))
+      .nfc()
+      .collect()
+  } else {
+    token
+  }
+}
+
+/// The kind of characters a token is made of, as returned by
+/// [`classify_tokens`](fn.classify_tokens.html).
+///
+/// GNETextSearch's tokenizer only splits text on whitespace, so punctuation
+/// stays attached to the letters and digits next to it. Tokens like
+/// `"foo,"`, `"3.14"`, or `"e-mail"` are `Mixed`. Combining marks are
+/// ignored when classifying a token, so `"cafe\u{301}"` is a `Word` like
+/// `"café"`.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::TokenKind;
+///
+/// assert_eq!(TokenKind::Word, TokenKind::of("Ærø"));
+/// assert_eq!(TokenKind::Number, TokenKind::of("2024"));
+/// assert_eq!(TokenKind::Alphanumeric, TokenKind::of("mp3"));
+/// assert_eq!(TokenKind::Punctuation, TokenKind::of("—"));
+/// assert_eq!(TokenKind::Mixed, TokenKind::of("it's"));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TokenKind {
+  /// A token made only of letters, such as `"hello"` or `"東京"`.
+  Word,
+  /// A token made only of numeric characters, such as `"42"` or `"½"`.
+  Number,
+  /// A token made of letters and numeric characters, such as `"abc123"`.
+  Alphanumeric,
+  /// A token without any letters or numeric characters, such as `"..."`,
+  /// `"&"`, or `"🦀"`.
+  Punctuation,
+  /// A token made of letters or numeric characters along with other
+  /// characters, such as `"foo,"` or `"3.14"`.
+  Mixed,
+}
+
+impl TokenKind {
+  /// Returns the kind of characters the token is made of. An empty token is
+  /// `Punctuation`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use text_search::tokenize::TokenKind;
+  ///
+  /// assert_eq!(TokenKind::Word, TokenKind::of("naïve"));
+  /// assert_eq!(TokenKind::Mixed, TokenKind::of("bar."));
+  /// ```
+  pub fn of(token: &str) -> TokenKind {
+    let mut has_letters = false;
+    let mut has_numbers = false;
+    let mut has_others = false;
+    for c in token.chars() {
+      if c.is_alphabetic() {
+        has_letters = true;
+      } else if c.is_numeric() {
+        has_numbers = true;
+      } else if !is_combining_mark(c) {
+        has_others = true;
+      }
+    }
+    match (has_letters, has_numbers, has_others) {
+      (false, false, _) => TokenKind::Punctuation,
+      (_, _, true) => TokenKind::Mixed,
+      (true, false, false) => TokenKind::Word,
+      (false, true, false) => TokenKind::Number,
+      (true, true, false) => TokenKind::Alphanumeric,
+    }
+  }
+}
+
+/// Splits the input into tokens like [`tokenize`](fn.tokenize.html) and
+/// pairs each token with its [`TokenKind`](enum.TokenKind.html).
+///
+/// The tokens are the same as those returned by `tokenize`, so numbers can
+/// be treated specially or punctuation dropped before the tokens are added
+/// to an [`Index`](../index/struct.Index.html).
+///
+/// # Panics
+///
+/// Panics if the input contains a NUL character or if the tokenizer's buffer
+/// could not be allocated.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::{classify_tokens, TokenKind};
+///
+/// assert_eq!(
+///   vec![
+///     ("Call".to_string(), TokenKind::Word),
+///     ("555".to_string(), TokenKind::Number),
+///     ("now!".to_string(), TokenKind::Mixed),
+///     ("-".to_string(), TokenKind::Punctuation),
+///   ],
+///   classify_tokens("Call 555 now! -")
+/// );
+/// ```
+pub fn classify_tokens(input: &str) -> Vec<(String, TokenKind)> {
+  tokenize(input)
+    .into_iter()
+    .map(|token| {
+      let kind = TokenKind::of(&token);
+      (token, kind)
+    })
+    .collect()
+}
+
+/// Returns an iterator over the tokens in the input, paired with the byte
+/// offsets at which they start.
+///
+/// The tokens are slices of the input, so nothing is allocated per token,
+/// and they are found lazily, one at a time, as the iterator advances. They
+/// are split by the same rules as in [`tokenize`](fn.tokenize.html), and the
+/// iterator yields exactly the same tokens in the same order.
+///
+/// # Panics
+///
+/// Panics if the input contains a NUL character.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::tokens;
+///
+/// let mut tokens = tokens("café  über 東京");
+/// assert_eq!(Some(("café", 0)), tokens.next());
+/// assert_eq!(Some(("über", 7)), tokens.next());
+/// assert_eq!(Some(("東京", 13)), tokens.next());
+/// assert_eq!(None, tokens.next());
+/// ```
+pub fn tokens(input: &str) -> Tokens<'_> {
+  assert!(
+    !input.contains('\0'),
+    "tokenized strings must not contain NUL characters"
+  );
+  Tokens { input, index: 0 }
+}
+
+/// An iterator over the tokens in a string and the byte offsets at which
+/// they start.
+///
+/// This `struct` is created by the [`tokens`](fn.tokens.html) function.
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+  input: &'a str,
+  index: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+  type Item = (&'a str, usize);
+
+  fn next(&mut self) -> Option<(&'a str, usize)> {
+    let length = self.input.len();
+    if self.index >= length {
+      return None;
+    }
+
+    let mut range = tsearch_range {
+      location: 0,
+      length: 0,
+    };
+    let found = unsafe {
+      tsearch_string_next_token(
+        self.input.as_ptr() as *const c_char,
+        length,
+        self.index,
+        &mut range,
+      )
+    };
+    if !found {
+      self.index = length;
+      return None;
+    }
+
+    let end = range.location + range.length;
+    self.index = end;
+    Some((&self.input[range.location..end], range.location))
+  }
+}
+
+impl FusedIterator for Tokens<'_> {}
+
+/// Returns an iterator over the tokens in the input, normalized according to
+/// the options, paired with the byte ranges of the original tokens in the
+/// input.
+///
+/// The tokens are the same as those returned by
+/// [`tokenize_with`](fn.tokenize_with.html), and like
+/// [`tokens`](fn.tokens.html), they're found lazily. Normalizing a token can
+/// change its length, so the range always refers to the token as it appears
+/// in the input, which makes it suitable for highlighting matches in the
+/// original text. Tokens that normalizing doesn't change are borrowed from
+/// the input.
+///
+/// # Panics
+///
+/// Panics if the input contains a NUL character.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::tokenize::{tokens_with, TokenizeOptions};
+///
+/// let options = TokenizeOptions {
+///   lowercase: true,
+///   strip_diacritics: true,
+///   ..TokenizeOptions::default()
+/// };
+/// let input = "Naïve café";
+/// let tokens: Vec<_> = tokens_with(input, &options).collect();
+///
+/// assert_eq!("naive", tokens[0].0);
+/// assert_eq!("Naïve", &input[tokens[0].1.clone()]);
+/// assert_eq!("cafe", tokens[1].0);
+/// assert_eq!(7..12, tokens[1].1);
+/// ```
+pub fn tokens_with<'a>(
+  input: &'a str,
+  options: &TokenizeOptions,
+) -> TokensWith<'a> {
+  TokensWith {
+    tokens: tokens(input),
+    options: *options,
+  }
+}
+
+/// An iterator over the normalized tokens in a string and the byte ranges of
+/// the original tokens.
+///
+/// This `struct` is created by the [`tokens_with`](fn.tokens_with.html)
+/// function.
+#[derive(Clone, Debug)]
+pub struct TokensWith<'a> {
+  tokens: Tokens<'a>,
+  options: TokenizeOptions,
+}
+
+impl<'a> Iterator for TokensWith<'a> {
+  type Item = (Cow<'a, str>, Range<usize>);
+
+  fn next(&mut self) -> Option<(Cow<'a, str>, Range<usize>)> {
+    for (token, start) in &mut self.tokens {
+      let range = start..start + token.len();
+      let token = if self.options.lowercase || self.options.strip_diacritics {
+        let normalized = normalize(token.to_owned(), &self.options);
+        if normalized == token {
+          Cow::Borrowed(token)
+        } else {
+          Cow::Owned(normalized)
+        }
+      } else {
+        Cow::Borrowed(token)
+      };
+      if token.chars().count() >= self.options.min_token_len {
+        return Some((token, range));
+      }
+    }
+    None
+  }
+}
+
+impl FusedIterator for TokensWith<'_> {}
+
+/// Splits the contents of the buffer into tokens and returns them in the
+/// order they appear in the buffer.
+///
+/// The tokenizer runs over the buffer's contents in place, so unlike
+/// [`tokenize`](fn.tokenize.html), which copies its input into a C string,
+/// the buffer is never copied. The tokens are split by the same rules and
+/// are the same as those returned by `tokenize(buf.as_str())`.
+///
+/// # Panics
+///
+/// Panics if the buffer contains a NUL character.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use text_search::{string_buf::StringBuf, tokenize::tokenize_buf};
+///
+/// let mut buf = StringBuf::new();
+/// write!(buf, "café  über\n{}", "東京").unwrap();
+///
+/// assert_eq!(vec!["café", "über", "東京"], tokenize_buf(&buf));
+/// ```
+pub fn tokenize_buf(buf: &StringBuf) -> Vec<String> {
+  tokens_buf(buf)
+    .map(|(token, _)| token.to_string())
+    .collect()
+}
+
+/// Returns an iterator over the tokens in the buffer, paired with the byte
+/// offsets at which they start.
+///
+/// The tokens are slices of the buffer's contents, found lazily by the
+/// tokenizer as the iterator advances, just like those returned by
+/// [`tokens`](fn.tokens.html). The iterator borrows the buffer, so the
+/// buffer can't be modified or dropped while the iterator or any of its
+/// tokens are still in use.
+///
+/// # Panics
+///
+/// Panics if the buffer contains a NUL character.
+///
+/// # Examples
+///
+/// ```
+/// use text_search::{string_buf::StringBuf, tokenize::tokens_buf};
+///
+/// let mut buf = StringBuf::new();
+/// buf.push_str("one two");
+/// buf.push_str(" three");
+///
+/// let tokens: Vec<(&str, usize)> = tokens_buf(&buf).collect();
+/// assert_eq!(vec![("one", 0), ("two", 4), ("three", 8)], tokens);
+/// ```
+///
+/// Tokens can't outlive the buffer they were borrowed from:
+///
+/// ```compile_fail
+/// use text_search::{string_buf::StringBuf, tokenize::tokens_buf};
+///
+/// let token = {
+///   let mut buf = StringBuf::new();
+///   buf.push_str("dangling");
+///   tokens_buf(&buf).next().unwrap().0
+/// };
+/// assert_eq!("dangling", token);
+/// ```
+pub fn tokens_buf(buf: &StringBuf) -> Tokens<'_> {
+  tokens(buf.as_str())
+}
+
+/// Appends the token, made of `length` code points, to the `Vec<String>`
+/// pointed to by `context`.
+unsafe extern "C" fn push_token(
+  _string: *const c_char,
+  _range: tsearch_range,
+  token: *mut u32,
+  length: usize,
+  context: *const c_void,
+) {
+  let tokens = &mut *(context as *mut Vec<String>);
+  let code_points = slice::from_raw_parts(token, length);
+  // The input came from a `&str`, so every code point is a valid `char`.
+  tokens.push(
+    code_points
+      .iter()
+      .filter_map(|c| char::from_u32(*c))
+      .collect(),
+  );
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  #[test]
+  fn tokenize_ascii() {
+    assert_eq!(
+      vec!["The", "quick", "brown", "fox."],
+      tokenize("The quick brown fox.")
+    );
+    assert_eq!(
+      vec!["one", "two", "three", "four"],
+      tokenize("  one\ttwo\r\nthree\n\nfour  ")
+    );
+    assert_eq!(vec!["a"], tokenize("a"));
+  }
+
+  #[test]
+  fn tokenize_empty_and_whitespace_only_input() {
+    assert_eq!(Vec::<String>::new(), tokenize(""));
+    assert_eq!(Vec::<String>::new(), tokenize(" "));
+    assert_eq!(Vec::<String>::new(), tokenize("\t\r\n \u{3000}\u{2003}"));
+  }
+
+  #[test]
+  fn tokenize_accented_latin() {
+    assert_eq!(vec!["café", "über"], tokenize("café über"));
+    assert_eq!(
+      vec!["Ærøskøbing", "naïve", "façade", "Ångström"],
+      tokenize("Ærøskøbing naïve\tfaçade\nÅngström")
+    );
+    // A combining accent is part of the token it follows.
+    assert_eq!(vec!["cafe\u{301}", "x"], tokenize("cafe\u{301} x"));
+  }
+
+  #[test]
+  fn tokenize_cjk() {
+    assert_eq!(vec!["東京", "大阪"], tokenize("東京 大阪"));
+    assert_eq!(
+      vec!["日本語", "テキスト"],
+      tokenize("日本語\u{3000}テキスト")
+    );
+    assert_eq!(vec!["한국어"], tokenize("한국어"));
+  }
+
+  #[test]
+  fn tokenize_emoji() {
+    assert_eq!(vec!["🦀", "rust🦀", "👍🏽"], tokenize("🦀 rust🦀 👍🏽"));
+    assert_eq!(
+      vec!["👨\u{200D}👩\u{200D}👧"],
+      tokenize("👨\u{200D}👩\u{200D}👧")
+    );
+  }
+
+  #[test]
+  fn tokenize_long_tokens() {
+    let long = "ü".repeat(1000);
+    let input = format!("{} {} {}", long, "x", long);
+    assert_eq!(vec![long.as_str(), "x", long.as_str()], tokenize(&input));
+  }
+
+  #[test]
+  fn tokenize_unicode_spaces() {
+    for space in &['\u{2002}', '\u{2005}', '\u{2009}', '\u{200A}', '\u{3000}'] {
+      let input = format!("a{}b", space);
+      assert_eq!(vec!["a", "b"], tokenize(&input), "{:?}", space);
+    }
+    // Non-breaking spaces don't separate tokens.
+    assert_eq!(vec!["a\u{A0}b"], tokenize("a\u{A0}b"));
+  }
+
+  #[test]
+  fn tokenize_with_default_options() {
+    let options = TokenizeOptions::default();
+    for input in &["", "Café über", "Ærøskøbing NAÏVE cafe\u{301}", "東京 🦀"]
+    {
+      assert_eq!(tokenize(input), tokenize_with(input, &options));
+    }
+  }
+
+  #[test]
+  fn tokenize_with_lowercase() {
+    let options = TokenizeOptions {
+      lowercase: true,
+      ..TokenizeOptions::default()
+    };
+    assert_eq!(
+      vec!["café", "école", "straße", "ærøskøbing", "東京", "🦀"],
+      tokenize_with("CAFÉ École Straße ÆRØSKØBING 東京 🦀", &options)
+    );
+    // A capital sigma at the end of a word becomes a final sigma.
+    assert_eq!(
+      vec!["\u{3BF}\u{3B4}\u{3BF}\u{3C2}"],
+      tokenize_with("ΟΔΟΣ", &options)
+    );
+  }
+
+  #[test]
+  fn tokenize_with_strip_diacritics() {
+    let options = TokenizeOptions {
+      strip_diacritics: true,
+      ..TokenizeOptions::default()
+    };
+    assert_eq!(
+      vec!["Cafe", "cafe", "naive", "facade", "Angstrom", "Ærøskøbing"],
+      tokenize_with(
+        "Café cafe\u{301} naïve façade Ångström Ærøskøbing",
+        &options
+      )
+    );
+    // Hangul syllables decompose into jamo, which aren't combining marks,
+    // so they're recomposed unchanged.
+    assert_eq!(
+      vec!["한국어", "東京"],
+      tokenize_with("한국어 東京", &options)
+    );
+  }
+
+  #[test]
+  fn tokenize_with_all_options() {
+    let options = TokenizeOptions {
+      lowercase: true,
+      strip_diacritics: true,
+      min_token_len: 2,
+    };
+    assert_eq!(
+      vec!["cafe", "cafe", "cafe", "東京", "ab"],
+      tokenize_with("Café cafe CAFE É 東京 a 🦀 AB", &options)
+    );
+
+    let options = TokenizeOptions {
+      min_token_len: 2,
+      ..TokenizeOptions::default()
+    };
+    // Lengths are counted in code points, not bytes.
+    assert_eq!(vec!["東京", "éé"], tokenize_with("東 東京 é éé", &options));
+  }
+
+  #[test]
+  fn tokens_can_be_inserted_into_ternary_tree() {
+    use crate::ternary_tree::TernaryTree;
+
+    let mut tree = TernaryTree::new();
+    for token in tokenize("café über 東京 🦀") {
+      tree.insert(&token, 1);
+    }
+    for key in &["café", "über", "東京", "🦀"] {
+      assert_eq!(true, tree.contains(key), "{}", key);
+    }
+  }
+
+  #[test]
+  fn tokens_with_offsets() {
+    let input = "\tThe quick\u{3000}東京 🦀!\n";
+    let expected = vec![("The", 1), ("quick", 5), ("東京", 13), ("🦀!", 20)];
+    assert_eq!(expected, tokens(input).collect::<Vec<_>>());
+    for (token, offset) in tokens(input) {
+      assert_eq!(token, &input[offset..offset + token.len()]);
+    }
+
+    assert_eq!(None, tokens("").next());
+    assert_eq!(None, tokens(" \r\n\u{2003}").next());
+    assert_eq!(vec![("a", 0)], tokens("a").collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn tokens_is_fused() {
+    let mut iter = tokens("one two");
+    assert_eq!(Some(("one", 0)), iter.next());
+    assert_eq!(Some(("two", 4)), iter.next());
+    assert_eq!(None, iter.next());
+    assert_eq!(None, iter.next());
+  }
+
+  #[test]
+  fn tokens_agree_with_tokenize() {
+    let inputs = [
+      "",
+      "   ",
+      "The quick brown fox.",
+      "  one\ttwo\r\nthree\n\nfour  ",
+      "Ærøskøbing naïve\tfaçade\nÅngström cafe\u{301}",
+      "日本語\u{3000}テキスト 한국어",
+      "🦀 rust🦀 👍🏽 👨\u{200D}👩\u{200D}👧",
+      "a\u{2002}b\u{200B}c\u{A0}d",
+    ];
+    for input in &inputs {
+      let lazy: Vec<&str> = tokens(input).map(|(token, _)| token).collect();
+      assert_eq!(tokenize(input), lazy, "{:?}", input);
+    }
+
+    let long = format!("{} x {}", "ü".repeat(1000), "東".repeat(500));
+    let lazy: Vec<&str> = tokens(&long).map(|(token, _)| token).collect();
+    assert_eq!(tokenize(&long), lazy);
+  }
+
+  #[test]
+  fn tokenize_string_buf() {
+    let mut buf = StringBuf::new();
+    assert_eq!(Vec::<String>::new(), tokenize_buf(&buf));
+    assert_eq!(None, tokens_buf(&buf).next());
+
+    let inputs = [
+      "The quick brown fox.",
+      "  one\ttwo\r\nthree\n\nfour  ",
+      "Ærøskøbing naïve\tfaçade\nÅngström cafe\u{301}",
+      "日本語\u{3000}テキスト 한국어",
+      "🦀 rust🦀 👍🏽 👨\u{200D}👩\u{200D}👧",
+      "a\u{2002}b\u{200B}c\u{A0}d",
+    ];
+    for input in &inputs {
+      buf.push_str(input);
+      let expected = tokenize(buf.as_str());
+      assert_eq!(expected, tokenize_buf(&buf), "{:?}", buf.as_str());
+
+      let borrowed: Vec<(&str, usize)> = tokens_buf(&buf).collect();
+      let owned: Vec<(&str, usize)> = tokens(buf.as_str()).collect();
+      assert_eq!(owned, borrowed);
+    }
+  }
+
+  #[test]
+  fn tokenize_string_buf_filled_by_write() {
+    let mut buf = StringBuf::new();
+    for i in 0..1000 {
+      write!(buf, "{}ü ", i).unwrap();
+    }
+    let tokens = tokenize_buf(&buf);
+    assert_eq!(1000, tokens.len());
+    assert_eq!("0ü", tokens[0]);
+    assert_eq!("999ü", tokens[999]);
+    assert_eq!(tokenize(buf.as_str()), tokens);
+  }
+
+  #[test]
+  #[should_panic(expected = "NUL")]
+  fn tokenize_buf_containing_nul() {
+    let mut buf = StringBuf::new();
+    buf.push_str("a\0b");
+    tokenize_buf(&buf);
+  }
+
+  #[test]
+  fn tokens_with_original_ranges() {
+    let options = TokenizeOptions {
+      lowercase: true,
+      strip_diacritics: true,
+      min_token_len: 2,
+    };
+    let input = "  CAFÉ a cafe\u{301}\tÆrøskøbing ÀÉÎÕÜ 東京 x ß";
+    let tokens: Vec<(Cow<str>, Range<usize>)> =
+      tokens_with(input, &options).collect();
+    let normalized: Vec<&str> =
+      tokens.iter().map(|(token, _)| token.as_ref()).collect();
+    assert_eq!(
+      vec!["cafe", "cafe", "ærøskøbing", "aeiou", "東京"],
+      normalized
+    );
+    let originals: Vec<&str> = tokens
+      .iter()
+      .map(|(_, range)| &input[range.clone()])
+      .collect();
+    assert_eq!(
+      vec!["CAFÉ", "cafe\u{301}", "Ærøskøbing", "ÀÉÎÕÜ", "東京"],
+      originals
+    );
+    // Stripping the diacritics shortens the tokens.
+    assert_eq!(2..7, tokens[0].1);
+    assert_eq!(10..16, tokens[1].1);
+    assert_eq!(31..41, tokens[3].1);
+    assert_eq!(5, tokens[3].0.len());
+    assert_eq!(true, matches!(tokens[4].0, Cow::Borrowed(_)));
+    assert_eq!(true, matches!(tokens[0].0, Cow::Owned(_)));
+
+    assert_eq!(tokenize_with(input, &options), normalized);
+  }
+
+  #[test]
+  fn tokens_with_default_options_are_borrowed() {
+    let input = "The quick\u{3000}brown 🦀";
+    let expected: Vec<(Cow<str>, Range<usize>)> = tokens(input)
+      .map(|(token, start)| (Cow::Borrowed(token), start..start + token.len()))
+      .collect();
+    let actual: Vec<(Cow<str>, Range<usize>)> =
+      tokens_with(input, &TokenizeOptions::default()).collect();
+    assert_eq!(expected, actual);
+    for (token, _) in &actual {
+      assert_eq!(true, matches!(token, Cow::Borrowed(_)));
+    }
+
+    let mut iter = tokens_with("", &TokenizeOptions::default());
+    assert_eq!(None, iter.next());
+    assert_eq!(None, iter.next());
+  }
+
+  #[test]
+  fn tokenize_filtered_normalizes_stopwords() {
+    let stopwords: HashSet<String> =
+      vec!["THE".to_string(), "Cafe".to_string(), "a".to_string()]
+        .into_iter()
+        .collect();
+    let input = "The café sells a Café au lait, the end";
+
+    assert_eq!(
+      vec!["The", "café", "sells", "Café", "au", "lait,", "the", "end"],
+      tokenize_filtered(input, &TokenizeOptions::default(), &stopwords)
+    );
+
+    let lowercase = TokenizeOptions {
+      lowercase: true,
+      ..TokenizeOptions::default()
+    };
+    assert_eq!(
+      vec!["café", "sells", "café", "au", "lait,", "end"],
+      tokenize_filtered(input, &lowercase, &stopwords)
+    );
+
+    let all = TokenizeOptions {
+      lowercase: true,
+      strip_diacritics: true,
+      min_token_len: 3,
+    };
+    assert_eq!(
+      vec!["sells", "lait,", "end"],
+      tokenize_filtered(input, &all, &stopwords)
+    );
+  }
+
+  #[test]
+  fn tokenize_filtered_without_stopwords() {
+    let options = TokenizeOptions {
+      lowercase: true,
+      ..TokenizeOptions::default()
+    };
+    let input = "One two THREE";
+    assert_eq!(
+      tokenize_with(input, &options),
+      tokenize_filtered(input, &options, &HashSet::new())
+    );
+
+    let stopwords: HashSet<String> =
+      vec!["one".to_string(), "two".to_string(), "three".to_string()]
+        .into_iter()
+        .collect();
+    assert_eq!(
+      Vec::<String>::new(),
+      tokenize_filtered(input, &options, &stopwords)
+    );
+  }
+
+  #[test]
+  fn classify_mixed_input() {
+    assert_eq!(
+      vec![
+        ("abc123".to_string(), TokenKind::Alphanumeric),
+        ("foo,".to_string(), TokenKind::Mixed),
+        ("bar.".to_string(), TokenKind::Mixed),
+      ],
+      classify_tokens("abc123 foo, bar.")
+    );
+    assert_eq!(Vec::<(String, TokenKind)>::new(), classify_tokens(" \t\n"));
+  }
+
+  #[test]
+  fn classify_unicode_tokens() {
+    let kinds: Vec<TokenKind> =
+      classify_tokens("東京 ٣٤ ½ x² naïve cafe\u{301} 🦀 «» 3.14 1,000")
+        .into_iter()
+        .map(|(_, kind)| kind)
+        .collect();
+    assert_eq!(
+      vec![
+        TokenKind::Word,
+        TokenKind::Number,
+        TokenKind::Number,
+        TokenKind::Alphanumeric,
+        TokenKind::Word,
+        TokenKind::Word,
+        TokenKind::Punctuation,
+        TokenKind::Punctuation,
+        TokenKind::Mixed,
+        TokenKind::Mixed,
+      ],
+      kinds
+    );
+    assert_eq!(TokenKind::Punctuation, TokenKind::of(""));
+    assert_eq!(TokenKind::Punctuation, TokenKind::of("\u{301}"));
+  }
+
+  #[test]
+  fn classify_tokens_matches_tokenize() {
+    let input = "The 3 quick-brown foxes, aged 2½, jumped!";
+    let tokens: Vec<String> = classify_tokens(input)
+      .into_iter()
+      .map(|(token, _)| token)
+      .collect();
+    assert_eq!(tokenize(input), tokens);
+  }
+
+  #[test]
+  #[should_panic(expected = "NUL")]
+  fn tokens_of_input_containing_nul() {
+    tokens("a\0b");
+  }
+
+  #[test]
+  #[should_panic(expected = "NUL")]
+  fn tokenize_input_containing_nul() {
+    tokenize("a\0b");
+  }
+
+  #[test]
+  fn try_tokenize_rejects_nul() {
+    for input in &["a\0b", "a b\0", "\0", "ab \0 cd"] {
+      assert_eq!(Err(TextSearchError::ContainsNul), try_tokenize(input));
+    }
+    let input = "Ærø 東京\u{3000}x";
+    assert_eq!(Ok(tokenize(input)), try_tokenize(input));
+    assert_eq!(Ok(Vec::new()), try_tokenize(""));
+  }
+}